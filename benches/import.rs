@@ -0,0 +1,73 @@
+//compares checking the records of a large import on a single thread against spreading each batch over workers
+//each record goes through what import r does before the write, parsing, deserializing and serializing again for the size check
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::json;
+
+//the modules import r goes through, so each record is checked exactly as it is there
+#[path = "../src/config.rs"]
+//the tests module of config.rs is left with only its imports when benches are built
+#[allow(dead_code, unused_imports)]
+mod config;
+#[path = "../src/error.rs"]
+#[allow(dead_code)]
+mod error;
+#[path = "../src/ingredients.rs"]
+#[allow(dead_code)]
+mod ingredients;
+#[path = "../src/recipe.rs"]
+#[allow(dead_code)]
+mod recipe;
+#[path = "../src/validation.rs"]
+#[allow(dead_code)]
+mod validation;
+#[path = "../src/workers.rs"]
+mod workers;
+
+use recipe::Recipe;
+
+//records read before one write, as import.rs batches them
+const IMPORT_BATCH: usize = 500;
+//a large import, checked a batch at a time
+const IMPORT_RECORDS: usize = 10 * IMPORT_BATCH;
+
+fn records() -> Vec<Vec<u8>> {
+    (0..IMPORT_RECORDS)
+        .map(|id| {
+            let value = json!({
+                "id": id,
+                "name": format!("recipe {}", id),
+                "ingredients": "flour, water, salt, yeast, olive oil",
+                "instructions": "mix everything\nleave to rise for an hour\nbake at 220 for 25 minutes",
+                "public": true,
+                "tags": ["bread", "baking"],
+                "structured_ingredients": (0..20).map(|i| json!({"name": format!("ingredient {}", i), "quantity": 1.5, "unit": "g"})).collect::<Vec<_>>(),
+            });
+            serde_json::to_vec(&value).unwrap()
+        })
+        .collect()
+}
+
+fn import(c: &mut Criterion) {
+    let records = records();
+    let max_bytes = config::Config::default().max_recipe_bytes;
+    //import_workers 1 is the default and how every record was checked before, 0 is one worker per cpu which can be any of the others
+    let mut counts = vec![1, 2, 4, workers::available()];
+    counts.sort_unstable();
+    counts.dedup();
+    let mut group = c.benchmark_group("import_workers");
+    group.throughput(Throughput::Elements(IMPORT_RECORDS as u64));
+    for workers in counts {
+        group.bench_with_input(BenchmarkId::from_parameter(workers), &workers, |b, &workers| {
+            b.iter(|| {
+                records
+                    .chunks(IMPORT_BATCH)
+                    .flat_map(|batch| workers::map_ordered(black_box(batch.to_vec()), workers, |record| validation::parse_record(&record, max_bytes).map(|r| r.id).ok()))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, import);
+criterion_main!(benches);
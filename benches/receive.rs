@@ -0,0 +1,127 @@
+//compares how floodsub payloads used to be read, trying each message type in turn,
+//against peeking at their fields first so only one deserializer runs
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{json, Value};
+
+//the handler's own modules, so what is read is exactly what main.rs reads
+#[path = "../src/digest.rs"]
+#[allow(dead_code)]
+mod digest;
+#[path = "../src/error.rs"]
+#[allow(dead_code)]
+mod error;
+#[path = "../src/ingredients.rs"]
+#[allow(dead_code)]
+mod ingredients;
+#[path = "../src/manifests.rs"]
+#[allow(dead_code)]
+mod manifests;
+#[path = "../src/messages.rs"]
+#[allow(dead_code)]
+mod messages;
+#[path = "../src/patch.rs"]
+#[allow(dead_code)]
+mod patch;
+#[path = "../src/recipe.rs"]
+#[allow(dead_code)]
+mod recipe;
+#[path = "../src/wire.rs"]
+mod wire;
+
+use digest::AvailabilityDigest;
+use messages::{ListRequest, ListResponse, LocateQuery, ManifestAnnouncement, NameMatches, NameQuery, RecipeAnnouncement};
+use patch::RecipePatch;
+use recipe::{Recipe, Recipes};
+
+const LOCAL_PEER: &str = "12D3KooWLocalPeerLocalPeerLocalPeerLocalPeerLocal";
+const OTHER_PEER: &str = "12D3KooWOtherPeerOtherPeerOtherPeerOtherPeerOther";
+
+//the else-if chain the handler used, returns which type matched
+fn sequential(data: &[u8]) -> Option<u8> {
+    if let Ok(resp) = serde_json::from_slice::<ListResponse>(data) {
+        (resp.receiver == LOCAL_PEER).then_some(0)
+    } else if let Ok(resp) = serde_json::from_slice::<NameMatches>(data) {
+        (resp.receiver == LOCAL_PEER).then_some(1)
+    } else if serde_json::from_slice::<RecipeAnnouncement>(data).is_ok() {
+        Some(2)
+    } else if serde_json::from_slice::<RecipePatch>(data).is_ok() {
+        Some(3)
+    } else if serde_json::from_slice::<ManifestAnnouncement>(data).is_ok() {
+        Some(4)
+    } else if serde_json::from_slice::<LocateQuery>(data).is_ok() {
+        Some(5)
+    } else if serde_json::from_slice::<NameQuery>(data).is_ok() {
+        Some(6)
+    } else if serde_json::from_slice::<ListRequest>(data).is_ok() {
+        Some(7)
+    } else if serde_json::from_slice::<AvailabilityDigest>(data).is_ok() {
+        Some(8)
+    } else {
+        None
+    }
+}
+
+//classifying first then parsing once, as the handler does now
+fn classified(data: &[u8]) -> Option<u8> {
+    use wire::MessageKind::*;
+    match wire::classify(data, LOCAL_PEER)? {
+        ListResponse => serde_json::from_slice::<messages::ListResponse>(data).ok().map(|_| 0),
+        NameMatches => serde_json::from_slice::<messages::NameMatches>(data).ok().map(|_| 1),
+        RecipeAnnouncement => serde_json::from_slice::<messages::RecipeAnnouncement>(data).ok().map(|_| 2),
+        RecipePatch => serde_json::from_slice::<patch::RecipePatch>(data).ok().map(|_| 3),
+        ManifestAnnouncement => serde_json::from_slice::<messages::ManifestAnnouncement>(data).ok().map(|_| 4),
+        LocateQuery => serde_json::from_slice::<messages::LocateQuery>(data).ok().map(|_| 5),
+        NameQuery => serde_json::from_slice::<messages::NameQuery>(data).ok().map(|_| 6),
+        ListRequest => serde_json::from_slice::<messages::ListRequest>(data).ok().map(|_| 7),
+        AvailabilityDigest => serde_json::from_slice::<digest::AvailabilityDigest>(data).ok().map(|_| 8),
+    }
+}
+
+fn recipes(count: usize) -> Vec<Value> {
+    (0..count)
+        .map(|id| {
+            json!({
+                "id": id,
+                "name": format!("recipe {}", id),
+                "ingredients": "flour, water, salt, yeast, olive oil",
+                "instructions": "mix everything\nleave to rise for an hour\nbake at 220 for 25 minutes",
+                "public": true,
+                "tags": ["bread", "baking"],
+            })
+        })
+        .collect()
+}
+
+fn payloads() -> Vec<(&'static str, Vec<u8>)> {
+    let request_id = "0b5e7ad4-7c1e-4b8a-9a53-2f0d5b6b7c11";
+    let list_response = |receiver: &str| json!({"mode": "ALL", "data": recipes(50), "receiver": receiver, "request_id": request_id});
+    [
+        ("list-request", json!({"mode": "ALL", "request_id": request_id})),
+        ("name-query", json!({"name": "bread", "request_id": request_id})),
+        ("recipe-patch", json!({"author": OTHER_PEER, "id": 3, "base": 1234567, "changed_fields": {"name": "rye bread"}})),
+        ("list-response", list_response(LOCAL_PEER)),
+        (
+            "digest",
+            json!({"digest": (0..200).map(|id| json!({"id": id, "name": format!("recipe {}", id), "hash": 1234567})).collect::<Vec<_>>(), "round": 1, "page": 0, "pages": 1, "interval_secs": 60}),
+        ),
+        ("list-response-for-other-peer", list_response(OTHER_PEER)),
+        ("unknown", json!({"greeting": "hello", "payload": recipes(5)})),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name, serde_json::to_vec(&value).unwrap()))
+    .collect()
+}
+
+fn receive(c: &mut Criterion) {
+    for (name, data) in payloads() {
+        //both ways have to read a payload as the same message
+        assert_eq!(sequential(&data), classified(&data), "{}", name);
+        let mut group = c.benchmark_group(name);
+        group.bench_function("sequential", |b| b.iter(|| sequential(black_box(&data))));
+        group.bench_function("classified", |b| b.iter(|| classified(black_box(&data))));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, receive);
+criterion_main!(benches);
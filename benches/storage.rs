@@ -0,0 +1,72 @@
+//compares reading the storage file the way every command used to, parsing it each time,
+//against the cache, both copying the cached recipes as read_local_recipes does and sharing them as read_shared_recipes does
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::json;
+
+//the modules a read goes through, so what is measured is exactly what ls r and inbound requests run
+#[path = "../src/config.rs"]
+//the tests modules of config.rs and migrate.rs are left with only their imports when benches are built
+#[allow(dead_code, unused_imports)]
+mod config;
+#[path = "../src/error.rs"]
+#[allow(dead_code)]
+mod error;
+#[path = "../src/ingredients.rs"]
+#[allow(dead_code)]
+mod ingredients;
+#[path = "../src/migrate.rs"]
+#[allow(dead_code, unused_imports)]
+mod migrate;
+#[path = "../src/recipe.rs"]
+#[allow(dead_code)]
+mod recipe;
+#[path = "../src/storage.rs"]
+#[allow(dead_code)]
+mod storage;
+#[path = "../src/validation.rs"]
+#[allow(dead_code)]
+mod validation;
+
+use recipe::{Recipe, Recipes};
+
+//a large collection, the cost of parsing and of copying both grow with it
+const RECIPES: usize = 5000;
+
+fn write_storage_file() -> String {
+    let recipes: Recipes = (0..RECIPES)
+        .map(|id| {
+            let mut recipe = recipe::parse_recipe_args(&format!(
+                "recipe {}|500 g flour, 300 ml water, 2 tsp salt, 7 g yeast|mix everything\nleave to rise for an hour\nbake at 220 for 25 minutes|bread,baking",
+                id
+            ))
+            .unwrap();
+            recipe.id = id;
+            recipe
+        })
+        .collect();
+    let path = std::env::temp_dir().join(format!("p2precipe-bench-storage-{}.json", std::process::id()));
+    std::fs::write(&path, serde_json::to_vec(&json!({"schema_version": migrate::SCHEMA_VERSION, "recipes": recipes})).unwrap()).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+fn read(c: &mut Criterion) {
+    let path = write_storage_file();
+    //set before the config is first read, STORAGE_FILE wins over whatever config.toml the bench runs next to
+    std::env::set_var("STORAGE_FILE", &path);
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    //both ways have to read the same recipes
+    let parsed = migrate::parse(&std::fs::read(&path).unwrap()).unwrap().1;
+    let shared = runtime.block_on(storage::read_shared_recipes()).unwrap();
+    assert_eq!(serde_json::to_vec(&parsed).unwrap(), serde_json::to_vec(&*shared).unwrap());
+    let mut group = c.benchmark_group("read-5000-recipes");
+    group.bench_function("parsed", |b| {
+        b.iter(|| runtime.block_on(async { migrate::parse(&tokio::fs::read(black_box(&path)).await.unwrap()).unwrap().1.len() }))
+    });
+    group.bench_function("cached-copy", |b| b.iter(|| runtime.block_on(storage::read_local_recipes()).unwrap().len()));
+    group.bench_function("cached-shared", |b| b.iter(|| runtime.block_on(storage::read_shared_recipes()).unwrap().len()));
+    group.finish();
+    std::fs::remove_file(&path).unwrap();
+}
+
+criterion_group!(benches, read);
+criterion_main!(benches);
@@ -0,0 +1,139 @@
+use crate::config;
+use crate::digest;
+use crate::error::{RecipeError, RecipeResult};
+use crate::import;
+use crate::validation::validate_recipe_within;
+use crate::{Recipe, Recipes};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::fs;
+use tokio::sync::Mutex;
+
+//loaded on first use, the async mutex keeps read-modify-write cycles from interleaving like the cache's
+static ARCHIVE: Lazy<Mutex<Option<Archive>>> = Lazy::new(|| Mutex::new(None));
+
+//a public recipe received from a peer, kept for good once archive_all is on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedRecipe {
+    //peer id of the peer the recipe was first received from
+    pub author: String,
+    pub recipe: Recipe,
+    //hash of what the recipe holds, the same recipe received again or from someone else is only kept once
+    pub hash: u64,
+    //seconds since the unix epoch when the recipe was first received
+    pub archived_at: u64,
+}
+
+//the archived recipes with their hashes and total size, so inserting doesnt go through every entry
+struct Archive {
+    entries: Vec<ArchivedRecipe>,
+    hashes: HashSet<u64>,
+    bytes: usize,
+}
+
+//how archiving one batch of received recipes went
+#[derive(Default)]
+pub struct ArchiveOutcome {
+    pub added: usize,
+    //recipes the archive already has, from any peer
+    pub duplicates: usize,
+    //recipes that arent public or fail validation
+    pub rejected: usize,
+    //recipes left out because archive_max_bytes was reached
+    pub over_limit: usize,
+}
+
+//hash of a recipe's content, leaving out its id, who it is shared with and when it changed like merging does
+fn content_hash(recipe: &Recipe) -> RecipeResult<u64> {
+    Ok(digest::fnv1a(import::content_key(recipe)?.as_bytes()))
+}
+
+fn size(recipe: &Recipe) -> usize {
+    serde_json::to_vec(recipe).map(|b| b.len()).unwrap_or(0)
+}
+
+//logic for reading the archive file, no file yet just means nothing was archived
+async fn read_archive(path: &str) -> RecipeResult<Vec<ArchivedRecipe>> {
+    match fs::read(path).await {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//runs a change against the archive and writes it back if asked to
+async fn with_archive<R, F: FnOnce(&mut Archive) -> R>(path: &str, persist: bool, change: F) -> RecipeResult<R> {
+    let mut guard = ARCHIVE.lock().await;
+    if guard.is_none() {
+        let entries = read_archive(path).await?;
+        let hashes = entries.iter().map(|e| e.hash).collect();
+        let bytes = entries.iter().map(|e| size(&e.recipe)).sum();
+        *guard = Some(Archive { entries, hashes, bytes });
+    }
+    let archive = guard.as_mut().expect("archive is loaded");
+    let result = change(archive);
+    if persist {
+        let json = serde_json::to_string(&archive.entries)?;
+        fs::write(path, &json).await?;
+    }
+    Ok(result)
+}
+
+//keeps the public recipes that pass validation and arent archived yet, nothing is ever evicted
+//once archive_max_bytes is reached further recipes are left out, 0 leaves the archive unbounded
+pub async fn insert(author: &str, recipes: &Recipes, received_at: u64) -> RecipeResult<ArchiveOutcome> {
+    let config = config::get()?;
+    let mut outcome = ArchiveOutcome::default();
+    let mut candidates = Vec::new();
+    for recipe in recipes {
+        if !recipe.public || validate_recipe_within(recipe, config.max_recipe_bytes).is_err() {
+            outcome.rejected += 1;
+            continue;
+        }
+        candidates.push((content_hash(recipe)?, recipe));
+    }
+    if candidates.is_empty() {
+        return Ok(outcome);
+    }
+    with_archive(&config.archive_file, true, |archive| {
+        for (hash, recipe) in candidates {
+            if archive.hashes.contains(&hash) {
+                outcome.duplicates += 1;
+                continue;
+            }
+            let bytes = size(recipe);
+            if config.archive_max_bytes > 0 && archive.bytes + bytes > config.archive_max_bytes {
+                outcome.over_limit += 1;
+                continue;
+            }
+            debug!("archiving recipe {} from {}", recipe.id, author);
+            archive.hashes.insert(hash);
+            archive.bytes += bytes;
+            archive.entries.push(ArchivedRecipe { author: author.to_owned(), recipe: recipe.clone(), hash, archived_at: received_at });
+            outcome.added += 1;
+        }
+    })
+    .await?;
+    if outcome.over_limit > 0 {
+        warn!("archive is full at archive_max_bytes {}, {} recipes from {} were left out", config.archive_max_bytes, outcome.over_limit, author);
+    }
+    Ok(outcome)
+}
+
+//every archived recipe, oldest first
+pub async fn entries() -> RecipeResult<Vec<ArchivedRecipe>> {
+    with_archive(&config::get()?.archive_file, false, |archive| archive.entries.clone()).await
+}
+
+//writes the archived recipes to a json array import r --json reads, returns how many there were
+pub async fn export(path: &str) -> RecipeResult<usize> {
+    let entries = entries().await?;
+    if entries.is_empty() {
+        return Err(RecipeError::Validation("the archive is empty, nothing was exported".to_owned()));
+    }
+    let recipes: Recipes = entries.into_iter().map(|e| e.recipe).collect();
+    fs::write(path, serde_json::to_string_pretty(&recipes)?).await?;
+    Ok(recipes.len())
+}
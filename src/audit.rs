@@ -0,0 +1,44 @@
+use crate::error::RecipeResult;
+use crate::Recipes;
+use serde::{Deserialize, Serialize};
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+//file path for the log of every recipe received from other peers, one json event per line
+pub const AUDIT_LOG_PATH: &str = "./events.log";
+
+//recipes received from a peer at a point in time
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveEvent {
+    //seconds since the unix epoch
+    pub received_at: u64,
+    //peer id of the recipes' author
+    pub author: String,
+    pub recipes: Recipes,
+    //topic the recipes came in on, None for direct fetches and events logged before topics existed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+}
+
+//appends a receive event to the audit log
+pub async fn record(event: &ReceiveEvent) -> RecipeResult<()> {
+    let mut line = serde_json::to_string(event)?;
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(AUDIT_LOG_PATH).await?;
+    file.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+//reads every event in a log, lines that arent events are reported by line number instead of failing the whole log
+pub async fn read_events(path: &str) -> RecipeResult<(Vec<ReceiveEvent>, Vec<String>)> {
+    let content = tokio::fs::read_to_string(path).await?;
+    let mut events = Vec::new();
+    let mut failures = Vec::new();
+    for (number, line) in content.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(event) => events.push(event),
+            Err(e) => failures.push(format!("line {}: {}", number + 1, e)),
+        }
+    }
+    Ok((events, failures))
+}
@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+//spaces outbound announcements at least cooldown apart, bursts wait in a queue instead of being dropped
+pub struct BroadcastQueue<T> {
+    cooldown: Duration,
+    last_sent: Option<Instant>,
+    queued: VecDeque<T>,
+}
+
+impl<T: PartialEq> BroadcastQueue<T> {
+    //a zero cooldown sends everything straight away
+    pub fn new(cooldown: Duration) -> BroadcastQueue<T> {
+        BroadcastQueue {
+            cooldown,
+            last_sent: None,
+            queued: VecDeque::new(),
+        }
+    }
+
+    //returns the payload if it can go out now, otherwise queues it
+    //a payload identical to one still waiting is coalesced into it
+    pub fn send_or_queue(&mut self, payload: T) -> Option<T> {
+        if self.queued.is_empty() && self.is_due(Instant::now()) {
+            self.last_sent = Some(Instant::now());
+            return Some(payload);
+        }
+        if !self.queued.contains(&payload) {
+            self.queued.push_back(payload);
+        }
+        None
+    }
+
+    //the next queued payload once the cooldown since the last one has passed
+    pub fn pop_due(&mut self) -> Option<T> {
+        if !self.is_due(Instant::now()) {
+            return None;
+        }
+        let payload = self.queued.pop_front()?;
+        self.last_sent = Some(Instant::now());
+        Some(payload)
+    }
+
+    //when the next queued payload can go out, None if nothing is waiting
+    pub fn next_due(&self) -> Option<Instant> {
+        if self.queued.is_empty() {
+            return None;
+        }
+        Some(self.last_sent.map(|sent| sent + self.cooldown).unwrap_or_else(Instant::now))
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        self.last_sent.is_none_or(|sent| now.duration_since(sent) >= self.cooldown)
+    }
+}
@@ -0,0 +1,295 @@
+use crate::config;
+use crate::error::{RecipeError, RecipeResult};
+use crate::patch::{self, RecipePatch};
+use crate::{Recipe, Recipes};
+use log::debug;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+//file path for recipes received from other peers
+const CACHE_FILE_PATH: &str = "./cache.json";
+//loaded on first use, the async mutex keeps read-modify-write cycles from interleaving
+static REMOTE_CACHE: Lazy<Mutex<Option<Vec<CachedRecipe>>>> = Lazy::new(|| Mutex::new(None));
+//counters behind status, kept outside the mutex so they can be read without waiting on it
+static ENTRIES: AtomicUsize = AtomicUsize::new(0);
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+//orders accesses for eviction, continues from the highest one in the file once it is loaded
+static ACCESS_CLOCK: AtomicU64 = AtomicU64::new(0);
+
+//a recipe received from another peer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedRecipe {
+    //peer id of the recipe's author
+    pub author: String,
+    pub recipe: Recipe,
+    //topic the recipe was last received on, entries cached before topics existed were all on the default one
+    #[serde(default = "default_topic")]
+    pub topic: String,
+    //pinned recipes are exempt from any automatic pruning
+    #[serde(default)]
+    pub pinned: bool,
+    //seconds since the unix epoch when the recipe was last received
+    pub cached_at: u64,
+    //when the recipe was last received or looked up, the lowest unpinned one is evicted first
+    #[serde(default)]
+    pub last_used: u64,
+}
+
+fn default_topic() -> String {
+    crate::DEFAULT_TOPIC.to_owned()
+}
+
+//how full the cache is and how often lookups found what they were after
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    //share of lookups that were found, none before the first lookup
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+//the next point on the access clock
+fn tick() -> u64 {
+    ACCESS_CLOCK.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+//logic for reading the cache file, no file yet just means an empty cache
+async fn read_cache() -> RecipeResult<Vec<CachedRecipe>> {
+    match fs::read(CACHE_FILE_PATH).await {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//runs a change against the cache and writes it back if asked to
+async fn with_cache<R, F: FnOnce(&mut Vec<CachedRecipe>) -> R>(persist: bool, change: F) -> RecipeResult<R> {
+    let mut guard = REMOTE_CACHE.lock().await;
+    if guard.is_none() {
+        let entries = read_cache().await?;
+        let latest = entries.iter().map(|e| e.last_used).max().unwrap_or(0);
+        ACCESS_CLOCK.fetch_max(latest, Ordering::Relaxed);
+        *guard = Some(entries);
+    }
+    let entries = guard.as_mut().expect("cache is loaded");
+    let result = change(entries);
+    ENTRIES.store(entries.len(), Ordering::Relaxed);
+    if persist {
+        let json = serde_json::to_string(entries)?;
+        fs::write(CACHE_FILE_PATH, &json).await?;
+    }
+    Ok(result)
+}
+
+//evicts the least recently used unpinned entries until the cache fits max_entries and max_bytes, 0 leaves a limit off
+//pinned entries are never evicted even if they alone go over the limits, returns how many were evicted
+fn evict(entries: &mut Vec<CachedRecipe>, max_entries: usize, max_bytes: usize) -> usize {
+    let size = |e: &CachedRecipe| serde_json::to_vec(&e.recipe).map(|b| b.len()).unwrap_or(0);
+    let mut bytes: usize = entries.iter().map(size).sum();
+    let mut evicted = 0;
+    loop {
+        let over = (max_entries > 0 && entries.len() > max_entries) || (max_bytes > 0 && bytes > max_bytes);
+        let oldest = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !e.pinned)
+            .min_by_key(|(_, e)| e.last_used)
+            .map(|(i, _)| i);
+        match oldest {
+            Some(index) if over => {
+                let entry = entries.remove(index);
+                debug!("evicting recipe {} from {} from the cache", entry.recipe.id, entry.author);
+                bytes -= size(&entry);
+                evicted += 1;
+            }
+            _ => return evicted,
+        }
+    }
+}
+
+//stores recipes received from a peer at the given time, replacing older copies but keeping their pinned flag
+//a copy received later than that is kept instead
+//least recently used entries are evicted once the cache is over cache_max_entries or cache_max_bytes
+//topic is what they were received on, None keeps the topic of a cached copy and puts new ones on the default topic
+//returns how many entries were added or changed so applying the same recipes twice reports nothing the second time
+pub async fn insert_at(author: &str, recipes: Recipes, topic: Option<&str>, received_at: u64) -> RecipeResult<usize> {
+    let config = config::get()?;
+    with_cache(true, |entries| {
+        let mut changed = 0;
+        for recipe in recipes {
+            match entries.iter_mut().find(|e| e.author == author && e.recipe.id == recipe.id) {
+                Some(existing) if existing.cached_at > received_at => {}
+                Some(existing) => {
+                    existing.last_used = tick();
+                    if serde_json::to_value(&existing.recipe).ok() != serde_json::to_value(&recipe).ok() {
+                        changed += 1;
+                    }
+                    existing.recipe = recipe;
+                    existing.cached_at = received_at;
+                    if let Some(topic) = topic {
+                        existing.topic = topic.to_owned();
+                    }
+                }
+                None => {
+                    changed += 1;
+                    entries.push(CachedRecipe {
+                        author: author.to_owned(),
+                        recipe,
+                        topic: topic.map(str::to_owned).unwrap_or_else(default_topic),
+                        pinned: false,
+                        cached_at: received_at,
+                        last_used: tick(),
+                    })
+                }
+            }
+        }
+        evict(entries, config.cache_max_entries, config.cache_max_bytes);
+        changed
+    })
+    .await
+}
+
+//what became of a patch received for a cached recipe
+pub enum PatchOutcome {
+    //the cached copy was brought up to date, carries the patched recipe boxed so the other outcomes stay small
+    Applied(Box<Recipe>),
+    //the cached copy isnt the version the patch was made against, the whole recipe has to be fetched
+    Stale,
+    //nothing from that author with that id is cached, so there is nothing to patch
+    Missing,
+}
+
+//lays a patch over the cached copy of the recipe it is for, if that copy is the version the patch was made against
+pub async fn apply_patch(patch: &RecipePatch, received_at: u64) -> RecipeResult<PatchOutcome> {
+    with_cache(true, |entries| {
+        let entry = match entries.iter_mut().find(|e| e.author == patch.author && e.recipe.id == patch.id) {
+            Some(entry) => entry,
+            None => return Ok(PatchOutcome::Missing),
+        };
+        if patch::version(&entry.recipe)? != patch.base {
+            return Ok(PatchOutcome::Stale);
+        }
+        entry.recipe = patch::apply(&entry.recipe, &patch.changed_fields)?;
+        entry.cached_at = received_at;
+        entry.last_used = tick();
+        Ok(PatchOutcome::Applied(Box::new(entry.recipe.clone())))
+    })
+    .await?
+}
+
+//pins or unpins a cached recipe
+pub async fn set_pinned(author: &str, id: usize, pinned: bool) -> RecipeResult<()> {
+    with_cache(true, |entries| {
+        match entries.iter_mut().find(|e| e.author == author && e.recipe.id == id) {
+            Some(entry) => {
+                entry.pinned = pinned;
+                Ok(())
+            }
+            None => Err(RecipeError::NotFound { id }),
+        }
+    })
+    .await?
+}
+
+//every pinned recipe in the cache
+pub async fn pinned() -> RecipeResult<Vec<CachedRecipe>> {
+    with_cache(false, |entries| entries.iter().filter(|e| e.pinned).cloned().collect()).await
+}
+
+//a single cached recipe by author and id, a lookup counts as a use so it is evicted later
+//the new access time is written out with the next change rather than on every lookup
+pub async fn get(author: &str, id: usize) -> RecipeResult<Option<CachedRecipe>> {
+    let found = with_cache(false, |entries| {
+        entries.iter_mut().find(|e| e.author == author && e.recipe.id == id).map(|e| {
+            e.last_used = tick();
+            e.clone()
+        })
+    })
+    .await?;
+    match found {
+        Some(_) => HITS.fetch_add(1, Ordering::Relaxed),
+        None => MISSES.fetch_add(1, Ordering::Relaxed),
+    };
+    Ok(found)
+}
+
+//every cached recipe, most recently used first
+pub async fn entries() -> RecipeResult<Vec<CachedRecipe>> {
+    let mut entries = with_cache(false, |entries| entries.clone()).await?;
+    entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+    Ok(entries)
+}
+
+//drops every unpinned cached recipe, or only those from one author, returns how many went and their serialized size
+//local recipes live in storage so they are never affected
+pub async fn clear(author: Option<&str>) -> RecipeResult<(usize, usize)> {
+    with_cache(true, |entries| {
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(entries)
+            .into_iter()
+            .partition(|e| !e.pinned && author.is_none_or(|a| e.author == a));
+        *entries = kept;
+        let freed = removed.iter().map(|e| serde_json::to_vec(&e.recipe).map(|b| b.len()).unwrap_or(0)).sum();
+        (removed.len(), freed)
+    })
+    .await
+}
+
+//size and hit counts since startup, entries is 0 until the cache was first used
+pub fn stats() -> CacheStats {
+    CacheStats {
+        entries: ENTRIES.load(Ordering::Relaxed),
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: usize, last_used: u64, pinned: bool) -> CachedRecipe {
+        let mut recipe = crate::recipe::parse_recipe_args("Bread|flour|bake").unwrap();
+        recipe.id = id;
+        CachedRecipe { author: "peer".to_owned(), recipe, topic: default_topic(), pinned, cached_at: 0, last_used }
+    }
+
+    fn ids(entries: &[CachedRecipe]) -> Vec<usize> {
+        entries.iter().map(|e| e.recipe.id).collect()
+    }
+
+    #[test]
+    fn inserting_past_capacity_evicts_the_least_recently_used_entry() {
+        let mut entries = vec![entry(1, 5, false), entry(2, 1, false), entry(3, 9, false), entry(4, 10, false)];
+        assert_eq!(evict(&mut entries, 3, 0), 1);
+        assert_eq!(ids(&entries), [1, 3, 4]);
+    }
+
+    #[test]
+    fn pinned_entries_are_never_evicted() {
+        let mut entries = vec![entry(1, 1, true), entry(2, 2, false), entry(3, 3, true), entry(4, 4, false)];
+        assert_eq!(evict(&mut entries, 2, 0), 2);
+        assert_eq!(ids(&entries), [1, 3]);
+        //with only pinned entries left the cache stays over the limit rather than losing one
+        assert_eq!(evict(&mut entries, 1, 0), 0);
+        assert_eq!(ids(&entries), [1, 3]);
+    }
+
+    #[test]
+    fn the_byte_budget_evicts_until_the_rest_fits() {
+        let one = serde_json::to_vec(&entry(1, 0, false).recipe).unwrap().len();
+        let mut entries = vec![entry(1, 3, false), entry(2, 1, false), entry(3, 2, false)];
+        assert_eq!(evict(&mut entries, 0, one * 2), 1);
+        assert_eq!(ids(&entries), [1, 3]);
+        assert_eq!(evict(&mut entries, 0, 0), 0);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::Recipe;
+
+//narrowest card that still fits a numbered step next to its text
+const MIN_CARD_WIDTH: usize = 20;
+
+//renders a recipe as a boxed plaintext card, width is the number of characters inside the border
+pub fn render(recipe: &Recipe, width: usize) -> Vec<String> {
+    let width = width.max(MIN_CARD_WIDTH);
+    let border = format!("+{}+", "-".repeat(width + 2));
+    let mut card = vec![border.clone()];
+    let mut section = |lines: Vec<String>| {
+        card.extend(lines.into_iter().map(|l| format!("| {}{} |", l, " ".repeat(width - l.chars().count()))));
+        card.push(border.clone());
+    };
+
+    let mut header = wrap(&recipe.name.to_uppercase(), width);
+    let details = details_line(recipe);
+    if !details.is_empty() {
+        header.extend(wrap(&details, width));
+    }
+    if let Some(time) = recipe.time_breakdown() {
+        header.extend(wrap(&time, width));
+    }
+    section(header);
+
+    let mut ingredients = vec!["Ingredients".to_owned()];
+    ingredients.extend(ingredient_column(recipe, width));
+    section(ingredients);
+
+    let mut steps = vec!["Steps".to_owned()];
+    let recipe_steps = recipe.steps();
+    let number_width = recipe_steps.len().to_string().len();
+    for (number, step) in recipe_steps.iter().enumerate() {
+        let label = format!(" {:>w$}. ", number + 1, w = number_width);
+        let indent = " ".repeat(label.len());
+        for (i, line) in wrap(step, width - label.len()).into_iter().enumerate() {
+            steps.push(format!("{}{}", if i == 0 { &label } else { &indent }, line));
+        }
+    }
+    section(steps);
+
+    if !recipe.substitutions.is_empty() {
+        let mut substitutions = vec!["Substitutions".to_owned()];
+        for substitution in &recipe.substitutions {
+            substitutions.extend(wrap(&substitution.to_string(), width - 2).into_iter().map(|l| format!("  {}", l)));
+        }
+        section(substitutions);
+    }
+    card
+}
+
+//servings, yield, category and dietary on one line, whichever of them are set
+fn details_line(recipe: &Recipe) -> String {
+    let mut details = Vec::new();
+    if let Some(servings) = recipe.servings {
+        details.push(format!("Serves {}", servings));
+    }
+    if let Some(recipe_yield) = &recipe.recipe_yield {
+        details.push(format!("Makes {}", recipe_yield));
+    }
+    if let Some(category) = &recipe.category {
+        details.push(category.clone());
+    }
+    if !recipe.dietary.is_empty() {
+        details.push(recipe.dietary.join(", "));
+    }
+    details.join(" - ")
+}
+
+//ingredients with their amounts right aligned in a column of their own, falling back to the plain string
+fn ingredient_column(recipe: &Recipe, width: usize) -> Vec<String> {
+    if recipe.structured_ingredients.is_empty() {
+        return wrap(&recipe.ingredients, width - 2).into_iter().map(|l| format!("  {}", l)).collect();
+    }
+    let amounts: Vec<String> = recipe
+        .structured_ingredients
+        .iter()
+        .map(|i| {
+            let quantity = i.quantity_text();
+            [quantity, i.unit.clone()].into_iter().flatten().collect::<Vec<_>>().join(" ")
+        })
+        .collect();
+    let amount_width = amounts.iter().map(|a| a.chars().count()).max().unwrap_or(0);
+    let mut lines = Vec::new();
+    let mut group = None;
+    for (ingredient, amount) in recipe.structured_ingredients.iter().zip(&amounts) {
+        //each named section starts with its name as a heading
+        if ingredient.group != group {
+            group = ingredient.group.clone();
+            if let Some(name) = &group {
+                lines.extend(wrap(&format!("{}:", name), width - 2).into_iter().map(|l| format!("  {}", l)));
+            }
+        }
+        let label = format!("  {:>w$}  ", amount, w = amount_width);
+        let indent = " ".repeat(label.chars().count());
+        //very long amounts leave no room for the name so they get a line of their own
+        let room = width.saturating_sub(label.chars().count());
+        if room < MIN_CARD_WIDTH / 2 {
+            lines.extend(wrap(&format!("  {}", ingredient), width));
+            continue;
+        }
+        for (i, line) in wrap(&ingredient.name, room).into_iter().enumerate() {
+            lines.push(format!("{}{}", if i == 0 { &label } else { &indent }, line));
+        }
+    }
+    lines
+}
+
+//word wraps text to the given width, words longer than a whole line are split
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > width {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(word.drain(..width).collect());
+        }
+        let word: String = word.into_iter().collect();
+        let needed = if line.is_empty() { word.chars().count() } else { line.chars().count() + 1 + word.chars().count() };
+        if needed > width && !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
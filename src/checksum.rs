@@ -0,0 +1,95 @@
+//last field of a sealed message, the crc32 covers the message as it was before the field was added
+//peers that dont check it skip the field like any other unknown one
+const FIELD: &[u8] = b",\"checksum\":\"crc32:";
+
+//the field, eight hex digits and the closing quote and brace
+const SUFFIX_LEN: usize = FIELD.len() + 8 + 2;
+
+//what came of checking a received message
+pub enum Checked<'a> {
+    //the checksum matched, the message without it
+    Intact(Vec<u8>),
+    //no checksum, sent by a peer that doesnt add them
+    Unsealed(&'a [u8]),
+    //the checksum didnt match the message
+    Corrupt,
+}
+
+//appends the checksum field to a json object, anything else is returned as it was
+pub fn seal(payload: &[u8]) -> Vec<u8> {
+    match payload.strip_suffix(b"}") {
+        //an empty object has no field to put the comma after
+        Some(body) if body.len() > 1 => {
+            let mut sealed = body.to_vec();
+            sealed.extend_from_slice(FIELD);
+            sealed.extend_from_slice(format!("{:08x}\"}}", crc32(payload)).as_bytes());
+            sealed
+        }
+        _ => payload.to_vec(),
+    }
+}
+
+//checks the checksum a message ends in, if it has one
+pub fn check(data: &[u8]) -> Checked<'_> {
+    let split = match data.len().checked_sub(SUFFIX_LEN) {
+        Some(split) if data[split..].starts_with(FIELD) && data.ends_with(b"\"}") => split,
+        _ => return Checked::Unsealed(data),
+    };
+    let hex = &data[split + FIELD.len()..data.len() - 2];
+    //a flipped byte in the digits themselves doesnt parse
+    let expected = std::str::from_utf8(hex).ok().and_then(|h| u32::from_str_radix(h, 16).ok());
+    let mut original = data[..split].to_vec();
+    original.push(b'}');
+    if expected == Some(crc32(&original)) {
+        Checked::Intact(original)
+    } else {
+        Checked::Corrupt
+    }
+}
+
+//crc-32 as used by zip and ethernet, bit by bit since messages are small
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, b| {
+        (0..8).fold(crc ^ u32::from(*b), |crc, _| if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] = br#"{"mode":"ALL","receiver":"peer","data":[]}"#;
+
+    #[test]
+    fn crc32_matches_the_standard_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn sealed_messages_check_out_as_they_were_sent() {
+        let sealed = seal(MESSAGE);
+        assert!(matches!(check(&sealed), Checked::Intact(ref m) if m == MESSAGE));
+        //peers that dont check it still read a json object, with one more field
+        let value: serde_json::Value = serde_json::from_slice(&sealed).unwrap();
+        assert!(value["checksum"].as_str().unwrap().starts_with("crc32:"));
+        assert!(matches!(check(MESSAGE), Checked::Unsealed(m) if m == MESSAGE));
+    }
+
+    #[test]
+    fn a_flipped_byte_is_detected() {
+        let sealed = seal(MESSAGE);
+        let digits = sealed.len() - 10..sealed.len() - 2;
+        //every byte of the message itself and of the checksum digits
+        for at in (0..MESSAGE.len() - 1).chain(digits) {
+            let mut flipped = sealed.clone();
+            flipped[at] ^= 0x01;
+            assert!(matches!(check(&flipped), Checked::Corrupt), "flipping byte {} went unnoticed", at);
+        }
+    }
+
+    #[test]
+    fn only_json_objects_are_sealed() {
+        assert_eq!(seal(b"{}"), b"{}");
+        assert_eq!(seal(b"[1,2]"), b"[1,2]");
+    }
+}
@@ -0,0 +1,644 @@
+use crate::error::{RecipeError, RecipeResult};
+use log::error;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tokio::fs;
+
+//file path for the config
+pub const CONFIG_FILE_PATH: &str = "./config.toml";
+//config file contents are loaded once at startup and shared by every handler
+static CONFIG: Lazy<RwLock<LoadedConfig>> = Lazy::new(|| RwLock::new(LoadedConfig::new(Config::load())));
+
+//the config as the file holds it next to the effective one handlers get, which is only worked out again when the config changes
+//file values are kept unexpanded so writing the config back keeps references like $HOME
+struct LoadedConfig {
+    file: Config,
+    //env var references expanded and env overrides layered on top, or why expanding failed
+    effective: Result<Arc<Config>, String>,
+}
+
+impl LoadedConfig {
+    fn new(file: Config) -> LoadedConfig {
+        let effective = file.clone().expanded().map(|c| Arc::new(c.with_env()));
+        LoadedConfig { file, effective }
+    }
+
+    fn effective(&self) -> RecipeResult<Arc<Config>> {
+        self.effective.clone().map_err(RecipeError::Validation)
+    }
+}
+
+//largest serialized recipe accepted unless configured otherwise
+const DEFAULT_MAX_RECIPE_BYTES: usize = 64 * 1024;
+
+//no speedup from spreading import checks over threads has been measured yet, so they stay on one unless configured
+const DEFAULT_IMPORT_WORKERS: usize = 1;
+
+//how long a seen message is remembered for deduplication unless configured otherwise
+const DEFAULT_DEDUP_WINDOW_SECS: u64 = 60;
+
+//how long responses to a broadcast request are collected unless configured otherwise
+const DEFAULT_RESPONSE_TIMEOUT_SECS: u64 = 5;
+
+//how long an idle connection is kept open unless configured otherwise
+const DEFAULT_IDLE_CONNECTION_TIMEOUT_SECS: u64 = 30;
+
+//how many requests are answered at once unless configured otherwise
+const DEFAULT_MAX_CONCURRENT_RESPONDERS: usize = 16;
+
+//characters inside the border of a recipe card unless configured otherwise
+const DEFAULT_CARD_WIDTH: usize = 48;
+
+//how far periodic tasks drift from their interval unless configured otherwise
+const DEFAULT_TIMER_JITTER_PERCENT: u8 = 10;
+
+//recipes received from peers that are kept unless configured otherwise
+const DEFAULT_CACHE_MAX_ENTRIES: usize = 1000;
+
+//time between outbound announcements unless configured otherwise
+const DEFAULT_PUBLISH_COOLDOWN_MS: u64 = 500;
+
+//when peer prune forgets a peer unless configured otherwise, 10 failures in a row is minutes of retrying at the longest backoff
+const DEFAULT_PEER_PRUNE_FAILURES: u32 = 10;
+const DEFAULT_PEER_PRUNE_TTL_SECS: u64 = 24 * 60 * 60;
+
+//peers messages are propagated to unless configured otherwise
+const DEFAULT_MAX_VIEW_PEERS: usize = 50;
+
+//seconds a page fetched by import r --url may take unless configured otherwise
+const DEFAULT_URL_IMPORT_TIMEOUT_SECS: u64 = 10;
+
+//largest page import r --url downloads unless configured otherwise
+const DEFAULT_URL_IMPORT_MAX_BYTES: usize = 2 * 1024 * 1024;
+
+//where archive_all keeps received recipes unless configured otherwise
+const DEFAULT_ARCHIVE_FILE_PATH: &str = "./archive.json";
+
+//where image fetch r keeps images and the largest one it downloads unless configured otherwise
+const DEFAULT_IMAGE_DIR: &str = "./images";
+const DEFAULT_IMAGE_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+//deletes and edits undo can reverse unless configured otherwise
+const DEFAULT_UNDO_DEPTH: usize = 10;
+
+//milliseconds after startup before requests from peers are answered unless configured otherwise
+const DEFAULT_STARTUP_GRACE_MS: u64 = 1000;
+
+//seconds between discovery summaries unless configured otherwise
+const DEFAULT_DISCOVERY_SUMMARY_SECS: u64 = 10;
+
+//seconds between pings to each connected peer unless configured otherwise
+const DEFAULT_PING_INTERVAL_SECS: u64 = 15;
+
+//seconds between availability digests and recipes listed on one digest page unless configured otherwise
+const DEFAULT_DIGEST_INTERVAL_SECS: u64 = 60;
+const DEFAULT_DIGEST_PAGE_SIZE: usize = 200;
+
+//file path for recipes unless configured otherwise
+const DEFAULT_STORAGE_FILE_PATH: &str = "./recipes.json";
+
+//file paths for the pid and log of a node started by daemon start unless configured otherwise
+const DEFAULT_DAEMON_PID_FILE_PATH: &str = "./p2precipe.pid";
+const DEFAULT_DAEMON_LOG_FILE_PATH: &str = "./p2precipe.log";
+
+//settings the user can change, missing keys fall back to their defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    //criteria an inbound recipe has to match to be kept, empty keeps everything
+    pub interests: Vec<String>,
+    //multiaddrs of relays (ending in /p2p/<relay peer id>) to reserve a slot on, empty disables the relay client
+    pub relay_addresses: Vec<String>,
+    //largest serialized size of a single recipe in bytes, checked on create, edit and import
+    pub max_recipe_bytes: usize,
+    //threads records are parsed and validated on during import r, they are still added in file order in one write per batch
+    //0 uses one per available cpu, the default of 1 checks them one after another
+    pub import_workers: usize,
+    //seconds an inbound message is remembered so identical repeats are dropped, 0 disables deduplication
+    pub dedup_window_secs: u64,
+    //received recipes and patches dated more than this many seconds ago are refused as replays, 0 accepts any age
+    pub max_update_age_secs: u64,
+    //received recipes and patches dated more than this many seconds ahead of the local clock are refused, 0 accepts any date
+    pub max_future_skew_secs: u64,
+    //seconds responses to ls r all or ls r <peer> are collected before the summary is printed
+    pub response_timeout_secs: u64,
+    //seconds an idle connection is kept open, longer means more keep-alive traffic but recipes propagate without waiting for rediscovery
+    //can be overridden with the IDLE_CONNECTION_TIMEOUT_SECS env var
+    pub idle_connection_timeout_secs: u64,
+    //requests from peers answered at the same time (at least 1), anything beyond that is dropped instead of queued up on the disk
+    pub max_concurrent_responders: usize,
+    //characters inside the border of card r output, long lines are wrapped to fit
+    pub card_width: usize,
+    //how far each periodic task may randomly drift from its interval, as a percentage either way (up to 100)
+    pub timer_jitter_percent: u8,
+    //whether messages from peers on a different major protocol version are ignored, mismatches are logged either way
+    pub reject_incompatible_peers: bool,
+    //listener errors or closures within listener_failure_window_secs before the watchdog sets listeners up again
+    pub listener_failure_threshold: usize,
+    pub listener_failure_window_secs: u64,
+    //times listeners are set up again before the node exits with a non-zero code so a supervisor can restart it
+    pub max_listener_restarts: u32,
+    //tcp port to listen on, 0 lets the os pick one (LISTEN_PORT)
+    pub listen_port: u16,
+    //whether peers on the local network are discovered with mdns (ENABLE_MDNS)
+    pub enable_mdns: bool,
+    //file local recipes are stored in (STORAGE_FILE)
+    pub storage_file: String,
+    //named collections besides the default one, each stored in a file next to storage_file
+    pub collections: Vec<String>,
+    //milliseconds recipe writes are buffered for before being flushed together, 0 writes every change straight away
+    //buffered writes are also flushed by save and on shutdown (STORAGE_AUTOSAVE_MS)
+    pub storage_autosave_ms: u64,
+    //order recipes are written to storage and listed by ls r in, "insertion", "id" or "name", sorted files diff cleanly
+    pub storage_sort: String,
+    //default log filter, RUST_LOG still takes precedence when set (LOG_LEVEL)
+    pub log_level: String,
+    //how peers are found, "mdns" or "static" (DISCOVERY)
+    pub discovery: String,
+    //multiaddrs ending in /p2p/<peer id> used by static discovery
+    pub static_peers: Vec<String>,
+    //recipes from other peers kept in the cache before the least recently used unpinned ones are evicted, 0 is unbounded
+    pub cache_max_entries: usize,
+    //serialized bytes of cached recipes allowed before evicting the same way, 0 is unbounded
+    pub cache_max_bytes: usize,
+    //whether create r makes recipes public and announces them right away, create r --publish does it once (AUTO_PUBLISH)
+    pub auto_publish: bool,
+    //auto_publish for single collections by name, e.g. desserts = true, collections not listed use auto_publish
+    pub collection_auto_publish: BTreeMap<String, bool>,
+    //milliseconds between outbound announcements, bursts are queued and sent one by one rather than dropped
+    pub publish_cooldown_ms: u64,
+    //whether an announcement no peer is connected to receive is kept and sent once one is, otherwise it is reported as unheard
+    pub hold_announcements: bool,
+    //outbound dials to discovered and static peers in flight at once, further dials wait for a free slot
+    pub max_concurrent_dials: usize,
+    //seconds a dial may take before it is aborted and retried with backoff
+    pub dial_timeout_secs: u64,
+    //failed dials in a row after which peer prune forgets a known peer, 0 never prunes for failures
+    pub peer_prune_failures: u32,
+    //seconds since a known peer was last seen after which peer prune forgets it, 0 never prunes for age
+    pub peer_prune_ttl_secs: u64,
+    //seconds between automatic prunes, 0 only prunes when peer prune is run, allowlisted peers are never pruned
+    pub peer_prune_interval_secs: u64,
+    //peers messages are propagated to, further peers stay connected but are left out until a spot frees up, 0 is unbounded
+    pub max_view_peers: usize,
+    //peer ids always propagated to, they are let in over max_view_peers and never evicted for others
+    pub allowlisted_peers: Vec<String>,
+    //peer ids, which are derived from the peer's public key, the only peers anything is exchanged with when set
+    //everyone else is disconnected from and their messages dropped unread, empty trusts every peer
+    pub trusted_peers: Vec<String>,
+    //seconds import r --url waits for a page before giving up, only used when built with the http feature
+    pub url_import_timeout_secs: u64,
+    //largest page in bytes import r --url downloads, bigger pages are refused
+    pub url_import_max_bytes: usize,
+    //directory image fetch r keeps downloaded recipe images in, one file per image url
+    pub image_dir: String,
+    //largest image in bytes image fetch r downloads, bigger ones are refused
+    pub image_max_bytes: usize,
+    //topics subscribed to at startup besides recipes, which every node is on, e.g. desserts or regional/italy
+    pub topics: Vec<String>,
+    //most recent deletes and edits undo can reverse this session, 0 turns undo off
+    pub undo_depth: usize,
+    //where daemon start records the pid of the node it started, daemon stop signals the pid in it
+    pub daemon_pid_file: String,
+    //file the node started by daemon start logs to, appended to across runs
+    pub daemon_log_file: String,
+    //json file of per 100 g nutrition values by ingredient name laid over the bundled table, empty uses only the bundled one
+    pub nutrition_file: String,
+    //milliseconds after startup during which list requests and queries from peers are ignored, 0 answers straight away
+    pub startup_grace_ms: u64,
+    //seconds between round trip pings to each connected peer, shown in ls p and reported by peer ping
+    pub ping_interval_secs: u64,
+    //seconds between the logged summary of peers discovery found or lost, each peer is only logged at debug level, 0 turns it off
+    pub discovery_summary_secs: u64,
+    //seconds between broadcasts of the ids, names and hashes of the recipes served to everyone, 0 stops sending them
+    //peers answer locate and find-remote from these digests when every peer has sent one recently
+    pub digest_interval_secs: u64,
+    //recipes listed in one digest message, bigger collections are sent as several pages
+    pub digest_page_size: usize,
+    //whether every valid public recipe received from a peer is kept in archive_file, apart from the cache and never evicted (ARCHIVE_ALL)
+    pub archive_all: bool,
+    //file archive_all keeps received recipes in, the same recipe from any peer is kept once
+    pub archive_file: String,
+    //serialized bytes of archived recipes after which further ones are left out, 0 is unbounded
+    pub archive_max_bytes: usize,
+    //whether a crc32 of each published message is appended to it, peers drop received messages whose checksum doesnt match
+    //peers check checksums whether or not they add them, and older peers ignore them (MESSAGE_CHECKSUMS)
+    pub message_checksums: bool,
+    //file written once the node listens, the startup grace period is over and, with readiness_requires_peer, a peer is connected
+    //removed again when that stops being true and on shutdown, orchestrators probe it with test -f, empty writes none (READINESS_FILE)
+    pub readiness_file: String,
+    //whether the node only counts as ready with at least one peer connected (READINESS_REQUIRES_PEER)
+    pub readiness_requires_peer: bool,
+    //manages local recipes without starting any networking, network commands are refused (OFFLINE)
+    pub offline: bool,
+    //shown when waiting for a command on a terminal, piped input gets no prompt
+    pub prompt: String,
+    //"pretty" for human readable logs, "json" for one structured object per line (LOG_FORMAT)
+    pub log_format: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            interests: Vec::new(),
+            relay_addresses: Vec::new(),
+            max_recipe_bytes: DEFAULT_MAX_RECIPE_BYTES,
+            import_workers: DEFAULT_IMPORT_WORKERS,
+            dedup_window_secs: DEFAULT_DEDUP_WINDOW_SECS,
+            max_update_age_secs: 0,
+            max_future_skew_secs: 0,
+            response_timeout_secs: DEFAULT_RESPONSE_TIMEOUT_SECS,
+            idle_connection_timeout_secs: DEFAULT_IDLE_CONNECTION_TIMEOUT_SECS,
+            max_concurrent_responders: DEFAULT_MAX_CONCURRENT_RESPONDERS,
+            card_width: DEFAULT_CARD_WIDTH,
+            timer_jitter_percent: DEFAULT_TIMER_JITTER_PERCENT,
+            reject_incompatible_peers: false,
+            listener_failure_threshold: 5,
+            listener_failure_window_secs: 60,
+            max_listener_restarts: 3,
+            listen_port: 0,
+            enable_mdns: true,
+            storage_file: DEFAULT_STORAGE_FILE_PATH.to_owned(),
+            collections: Vec::new(),
+            storage_autosave_ms: 0,
+            storage_sort: "insertion".to_owned(),
+            log_level: "info".to_owned(),
+            log_format: "pretty".to_owned(),
+            discovery: "mdns".to_owned(),
+            static_peers: Vec::new(),
+            cache_max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+            cache_max_bytes: 0,
+            auto_publish: false,
+            collection_auto_publish: BTreeMap::new(),
+            publish_cooldown_ms: DEFAULT_PUBLISH_COOLDOWN_MS,
+            hold_announcements: true,
+            max_concurrent_dials: 8,
+            dial_timeout_secs: 10,
+            peer_prune_failures: DEFAULT_PEER_PRUNE_FAILURES,
+            peer_prune_ttl_secs: DEFAULT_PEER_PRUNE_TTL_SECS,
+            peer_prune_interval_secs: 0,
+            max_view_peers: DEFAULT_MAX_VIEW_PEERS,
+            allowlisted_peers: Vec::new(),
+            trusted_peers: Vec::new(),
+            prompt: "recipe> ".to_owned(),
+            offline: false,
+            startup_grace_ms: DEFAULT_STARTUP_GRACE_MS,
+            ping_interval_secs: DEFAULT_PING_INTERVAL_SECS,
+            discovery_summary_secs: DEFAULT_DISCOVERY_SUMMARY_SECS,
+            digest_interval_secs: DEFAULT_DIGEST_INTERVAL_SECS,
+            digest_page_size: DEFAULT_DIGEST_PAGE_SIZE,
+            archive_all: false,
+            archive_file: DEFAULT_ARCHIVE_FILE_PATH.to_owned(),
+            archive_max_bytes: 0,
+            message_checksums: false,
+            readiness_file: String::new(),
+            readiness_requires_peer: false,
+            nutrition_file: String::new(),
+            daemon_pid_file: DEFAULT_DAEMON_PID_FILE_PATH.to_owned(),
+            daemon_log_file: DEFAULT_DAEMON_LOG_FILE_PATH.to_owned(),
+            undo_depth: DEFAULT_UNDO_DEPTH,
+            topics: Vec::new(),
+            url_import_timeout_secs: DEFAULT_URL_IMPORT_TIMEOUT_SECS,
+            url_import_max_bytes: DEFAULT_URL_IMPORT_MAX_BYTES,
+            image_dir: DEFAULT_IMAGE_DIR.to_owned(),
+            image_max_bytes: DEFAULT_IMAGE_MAX_BYTES,
+        }
+    }
+}
+
+//the profile selected with RECIPE_PROFILE, if any
+pub fn active_profile() -> Option<String> {
+    std::env::var("RECIPE_PROFILE").ok().filter(|p| !p.is_empty())
+}
+
+//defaults for the active profile, layered beneath the config file and env vars
+fn profile_defaults() -> Config {
+    let mut config = Config::default();
+    match active_profile().as_deref() {
+        //a throwaway node, verbose and on whatever port is free
+        Some("dev") => {
+            config.log_level = "debug".to_owned();
+            config.listen_port = 0;
+        }
+        //a long running node, on a fixed port without mdns chatter and with logs for machines
+        Some("prod") => {
+            config.enable_mdns = false;
+            config.listen_port = 4001;
+            config.log_format = "json".to_owned();
+        }
+        Some(other) => error!("unknown RECIPE_PROFILE {:?}, expected dev or prod, using built-in defaults", other),
+        None => {}
+    }
+    config
+}
+
+//turns a config into a toml table so layers can be merged key by key
+fn to_table(config: &Config) -> toml::Table {
+    toml::Table::try_from(config).expect("config serializes to a table")
+}
+
+impl Config {
+    //whether recipes created in a collection are public unless create r says otherwise
+    pub fn publishes_by_default(&self, collection: &str) -> bool {
+        self.collection_auto_publish.get(collection).copied().unwrap_or(self.auto_publish)
+    }
+
+    //reads the config file on top of the profile defaults, falling back to them if it is missing or unreadable
+    fn load() -> Config {
+        let defaults = profile_defaults();
+        let content = match std::fs::read_to_string(CONFIG_FILE_PATH) {
+            Ok(content) => content,
+            Err(_) => return defaults,
+        };
+        let file: toml::Table = match toml::from_str(&content) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("invalid config file {}, using defaults: {}", CONFIG_FILE_PATH, e);
+                return defaults;
+            }
+        };
+        //keys set in the file win over the profile defaults
+        let mut merged = to_table(&defaults);
+        merged.extend(file);
+        toml::Value::Table(merged).try_into().unwrap_or_else(|e| {
+            error!("invalid config file {}, using defaults: {}", CONFIG_FILE_PATH, e);
+            defaults
+        })
+    }
+
+    //expands env var references in the path and address fields, every one of them is listed here
+    fn expanded(mut self) -> Result<Config, String> {
+        let invalid = |key: &str, e: String| format!("{} in {}: {}", key, CONFIG_FILE_PATH, e);
+        let paths = [
+            ("storage_file", &mut self.storage_file),
+            ("image_dir", &mut self.image_dir),
+            ("daemon_pid_file", &mut self.daemon_pid_file),
+            ("daemon_log_file", &mut self.daemon_log_file),
+            ("nutrition_file", &mut self.nutrition_file),
+            ("archive_file", &mut self.archive_file),
+            ("readiness_file", &mut self.readiness_file),
+        ];
+        for (key, value) in paths {
+            *value = expand_vars(value).map_err(|e| invalid(key, e))?;
+        }
+        for (key, list) in [("relay_addresses", &mut self.relay_addresses), ("static_peers", &mut self.static_peers)] {
+            for value in list.iter_mut() {
+                *value = expand_vars(value).map_err(|e| invalid(key, e))?;
+            }
+        }
+        Ok(self)
+    }
+
+    //layers env var overrides on top of the file config, invalid values are logged and ignored
+    fn with_env(mut self) -> Config {
+        if let Some(secs) = env_value("IDLE_CONNECTION_TIMEOUT_SECS") {
+            self.idle_connection_timeout_secs = secs;
+        }
+        if let Some(port) = env_value("LISTEN_PORT") {
+            self.listen_port = port;
+        }
+        if let Some(enabled) = env_value("ENABLE_MDNS") {
+            self.enable_mdns = enabled;
+        }
+        if let Some(path) = env_value("STORAGE_FILE") {
+            self.storage_file = path;
+        }
+        if let Some(ms) = env_value("STORAGE_AUTOSAVE_MS") {
+            self.storage_autosave_ms = ms;
+        }
+        if let Some(level) = env_value("LOG_LEVEL") {
+            self.log_level = level;
+        }
+        if let Some(format) = env_value("LOG_FORMAT") {
+            self.log_format = format;
+        }
+        if let Some(enabled) = env_value("AUTO_PUBLISH") {
+            self.auto_publish = enabled;
+        }
+        if let Some(offline) = env_value("OFFLINE") {
+            self.offline = offline;
+        }
+        if let Some(discovery) = env_value("DISCOVERY") {
+            self.discovery = discovery;
+        }
+        if let Some(enabled) = env_value("ARCHIVE_ALL") {
+            self.archive_all = enabled;
+        }
+        if let Some(enabled) = env_value("MESSAGE_CHECKSUMS") {
+            self.message_checksums = enabled;
+        }
+        if let Some(path) = env_value("READINESS_FILE") {
+            self.readiness_file = path;
+        }
+        if let Some(required) = env_value("READINESS_REQUIRES_PEER") {
+            self.readiness_requires_peer = required;
+        }
+        self
+    }
+}
+
+//expands $VAR, ${VAR} and ${VAR:-default} where the default may hold references itself, $$ is a literal $
+//an undefined variable without a default is an error rather than silently becoming an empty string
+fn expand_vars(value: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(inner) = rest.strip_prefix('{') {
+            //the matching brace, skipping over any nested ${...} in a default
+            let mut depth = 1;
+            let end = inner
+                .char_indices()
+                .find(|(_, c)| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(|| format!("unclosed ${{ in {:?}", value))?;
+            let reference = &inner[..end];
+            let (name, default) = match reference.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (reference, None),
+            };
+            //like the shell, :- also falls back when the variable is set but empty
+            match (std::env::var(name).ok().filter(|v| !v.is_empty() || default.is_none()), default) {
+                (Some(v), _) => expanded.push_str(&v),
+                (None, Some(default)) => expanded.push_str(&expand_vars(default)?),
+                (None, None) => return Err(format!("env var {} is not set", name)),
+            }
+            rest = &inner[end + 1..];
+        } else {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("$ without a variable name in {:?}, use $$ for a literal $", value));
+            }
+            let name = &rest[..end];
+            expanded.push_str(&std::env::var(name).map_err(|_| format!("env var {} is not set", name))?);
+            rest = &rest[end..];
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+//reads and parses an env var override
+fn env_value<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let value = std::env::var(name).ok()?;
+    match value.parse() {
+        Ok(v) => Some(v),
+        Err(_) => {
+            error!("ignoring invalid value {:?} for {}", value, name);
+            None
+        }
+    }
+}
+
+//returns the effective config, shared rather than copied and never holding the lock across awaits
+pub fn get() -> RecipeResult<Arc<Config>> {
+    CONFIG.read().map_err(|e| RecipeError::Lock(e.to_string()))?.effective()
+}
+
+//sets one key from its text form, the value is read as toml so numbers, booleans and lists work and anything else is a string
+pub async fn set(key: &str, value: &str) -> RecipeResult<Arc<Config>> {
+    let current = CONFIG.read().map(|c| c.file.clone()).map_err(|e| RecipeError::Lock(e.to_string()))?;
+    let mut table = to_table(&current);
+    if !table.contains_key(key) {
+        return Err(RecipeError::Validation(format!("unknown config key {}", key)));
+    }
+    let parsed = toml::from_str::<toml::Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut t| t.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()));
+    table.insert(key.to_owned(), parsed);
+    let changed: Config = toml::Value::Table(table)
+        .try_into()
+        .map_err(|e| RecipeError::Validation(format!("invalid value {:?} for {}: {}", value, key, e)))?;
+    update(|c| *c = changed).await
+}
+
+//applies a change to the config file and returns the effective config, env overrides are never written to the file
+pub async fn update<F: FnOnce(&mut Config)>(change: F) -> RecipeResult<Arc<Config>> {
+    let (updated, effective) = {
+        let mut loaded = CONFIG.write().map_err(|e| RecipeError::Lock(e.to_string()))?;
+        let mut file = loaded.file.clone();
+        change(&mut file);
+        *loaded = LoadedConfig::new(file);
+        (loaded.file.clone(), loaded.effective())
+    };
+    //keys still matching the profile defaults are left out so switching profile keeps working
+    let defaults = to_table(&profile_defaults());
+    let mut file = to_table(&updated);
+    file.retain(|key, value| defaults.get(key) != Some(value));
+    let content = toml::to_string(&file).map_err(|e| RecipeError::Validation(e.to_string()))?;
+    fs::write(CONFIG_FILE_PATH, content).await?;
+    effective
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //env vars are process wide, so every test sets its own names
+    fn set_var(name: &str, value: &str) -> String {
+        let name = format!("P2PRECIPE_TEST_{}", name);
+        std::env::set_var(&name, value);
+        name
+    }
+
+    #[test]
+    fn set_variables_are_expanded_in_both_forms() {
+        let home = set_var("HOME_DIR", "/home/cook");
+        assert_eq!(expand_vars(&format!("${}/.recipes/recipes.json", home)).unwrap(), "/home/cook/.recipes/recipes.json");
+        assert_eq!(expand_vars(&format!("${{{}}}-backup.json", home)).unwrap(), "/home/cook-backup.json");
+        assert_eq!(expand_vars("./recipes.json").unwrap(), "./recipes.json");
+    }
+
+    #[test]
+    fn unset_variables_are_an_error_naming_them() {
+        let e = expand_vars("$P2PRECIPE_TEST_NEVER_SET/recipes.json").unwrap_err();
+        assert!(e.contains("P2PRECIPE_TEST_NEVER_SET"), "{}", e);
+        assert!(expand_vars("${P2PRECIPE_TEST_NEVER_SET}").is_err());
+    }
+
+    #[test]
+    fn double_dollars_are_a_literal_dollar() {
+        let name = set_var("DOLLAR", "x");
+        assert_eq!(expand_vars("price$$5").unwrap(), "price$5");
+        assert_eq!(expand_vars(&format!("$${}", name)).unwrap(), format!("${}", name));
+        assert!(expand_vars("a $ b").is_err());
+    }
+
+    #[test]
+    fn defaults_apply_when_unset_or_empty_and_may_nest() {
+        let empty = set_var("EMPTY", "");
+        let data = set_var("DATA", "/data");
+        assert_eq!(expand_vars("${P2PRECIPE_TEST_NEVER_SET:-./recipes.json}").unwrap(), "./recipes.json");
+        assert_eq!(expand_vars(&format!("${{{}:-/tmp}}/r.json", empty)).unwrap(), "/tmp/r.json");
+        assert_eq!(expand_vars(&format!("${{P2PRECIPE_TEST_NEVER_SET:-${{{}}}/r.json}}", data)).unwrap(), "/data/r.json");
+        assert!(expand_vars("${P2PRECIPE_TEST_NEVER_SET:-${P2PRECIPE_TEST_NEVER_SET}}").is_err());
+        assert!(expand_vars("${P2PRECIPE_TEST_NEVER_SET:-x").is_err());
+    }
+
+    #[test]
+    fn every_path_field_is_expanded() {
+        let dir = set_var("STATE_DIR", "/var/lib/recipes");
+        let path = |file: &str| format!("${{{}}}/{}", dir, file);
+        let config = Config {
+            storage_file: path("recipes.json"),
+            image_dir: path("images"),
+            daemon_pid_file: path("daemon.pid"),
+            daemon_log_file: path("daemon.log"),
+            nutrition_file: path("nutrition.json"),
+            archive_file: path("archive.json"),
+            readiness_file: path("ready"),
+            ..Config::default()
+        }
+        .expanded()
+        .unwrap();
+        let paths = [
+            config.storage_file,
+            config.image_dir,
+            config.daemon_pid_file,
+            config.daemon_log_file,
+            config.nutrition_file,
+            config.archive_file,
+            config.readiness_file,
+        ];
+        assert!(paths.iter().all(|p| p.starts_with("/var/lib/recipes/")), "{:?}", paths);
+    }
+
+    #[test]
+    fn expanding_a_config_names_the_key_that_failed() {
+        let addr = set_var("RELAY", "/ip4/10.0.0.1/tcp/4001");
+        let config = Config { relay_addresses: vec![format!("${}", addr)], ..Config::default() };
+        assert_eq!(config.expanded().unwrap().relay_addresses, ["/ip4/10.0.0.1/tcp/4001"]);
+        let config = Config { static_peers: vec!["$P2PRECIPE_TEST_NEVER_SET".to_owned()], ..Config::default() };
+        let e = config.expanded().unwrap_err().to_string();
+        assert!(e.contains("static_peers") && e.contains("P2PRECIPE_TEST_NEVER_SET"), "{}", e);
+    }
+
+    #[test]
+    fn the_effective_config_is_worked_out_once_and_the_file_values_kept() {
+        let dir = set_var("CACHED_DIR", "/srv/recipes");
+        let loaded = LoadedConfig::new(Config { image_dir: format!("${}/images", dir), ..Config::default() });
+        assert_eq!(loaded.file.image_dir, format!("${}/images", dir));
+        let (first, second) = (loaded.effective().unwrap(), loaded.effective().unwrap());
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.image_dir, "/srv/recipes/images");
+        let loaded = LoadedConfig::new(Config { image_dir: "$P2PRECIPE_TEST_NEVER_SET".to_owned(), ..Config::default() });
+        match loaded.effective() {
+            Err(RecipeError::Validation(e)) => assert!(e.contains("image_dir"), "{}", e),
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::Recipe;
+use log::info;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+//the recipe being cooked, while there is one the lines typed move through its steps instead of running commands
+static SESSION: Lazy<Mutex<Option<Session>>> = Lazy::new(|| Mutex::new(None));
+
+//words a timer phrase can end in and the seconds in one of them
+const TIME_UNITS: &[(&str, u64)] = &[
+    ("sec", 1),
+    ("secs", 1),
+    ("second", 1),
+    ("seconds", 1),
+    ("min", 60),
+    ("mins", 60),
+    ("minute", 60),
+    ("minutes", 60),
+    ("hr", 3600),
+    ("hrs", 3600),
+    ("hour", 3600),
+    ("hours", 3600),
+];
+
+struct Session {
+    name: String,
+    ingredients: Vec<String>,
+    steps: Vec<String>,
+    current: usize,
+    //started timers keep counting when the step changes, leaving cook mode stops them
+    timers: Vec<JoinHandle<()>>,
+}
+
+impl Session {
+    fn show_step(&self) {
+        let step = &self.steps[self.current];
+        info!("{} - step {} of {}", self.name, self.current + 1, self.steps.len());
+        info!("Ingredients: {}", self.ingredients.join(", "));
+        info!("{}. {}", self.current + 1, step);
+        if let Some(timer) = parse_timer(step) {
+            info!("Timer: {}, type t to start it", format_duration(timer));
+        }
+    }
+}
+
+//whether a recipe is being cooked
+pub fn is_cooking() -> bool {
+    SESSION.lock().map(|s| s.is_some()).unwrap_or(false)
+}
+
+//starts cooking a recipe at its first step, instructions without separate lines are a single step
+pub fn start(recipe: &Recipe) {
+    let steps = recipe.steps();
+    if steps.is_empty() {
+        info!("{} has no instructions to cook from", recipe.name);
+        return;
+    }
+    let session = Session {
+        name: recipe.name.clone(),
+        ingredients: recipe.ingredient_lines(),
+        steps,
+        current: 0,
+        timers: Vec::new(),
+    };
+    info!("Cooking {}, Enter for the next step, b to go back, t to start the step's timer, i for the ingredients, q to stop", session.name);
+    session.show_step();
+    if let Ok(mut current) = SESSION.lock() {
+        *current = Some(session);
+    }
+}
+
+//handles a line typed while cooking
+pub fn handle_line(line: &str) {
+    let mut guard = match SESSION.lock() {
+        Ok(guard) => guard,
+        Err(_) => return,
+    };
+    let session = match guard.as_mut() {
+        Some(session) => session,
+        None => return,
+    };
+    match line.trim() {
+        "" | "n" | "next" => {
+            if session.current + 1 == session.steps.len() {
+                info!("Finished cooking {}", session.name);
+                //timers still running are left to go off
+                *guard = None;
+                return;
+            }
+            session.current += 1;
+            session.show_step();
+        }
+        "b" | "back" => {
+            session.current = session.current.saturating_sub(1);
+            session.show_step();
+        }
+        "t" | "timer" => match parse_timer(&session.steps[session.current]) {
+            Some(duration) => {
+                let step = session.current + 1;
+                info!("Started a {} timer for step {}", format_duration(duration), step);
+                session.timers.retain(|t| !t.is_finished());
+                session.timers.push(tokio::spawn(count_down(step, duration)));
+            }
+            None => info!("step {} has no time in it to set a timer from", session.current + 1),
+        },
+        "i" | "ingredients" => {
+            info!("Ingredients:");
+            session.ingredients.iter().for_each(|i| info!("- {}", i));
+        }
+        "q" | "quit" => {
+            session.timers.iter().for_each(|t| t.abort());
+            info!("Stopped cooking {}", session.name);
+            *guard = None;
+        }
+        other => info!("{:?} isnt a cook mode key, Enter for the next step, b to go back, t to start the step's timer, i for the ingredients, q to stop", other),
+    }
+}
+
+//the first time in a step, "bake for 20 minutes" gives 20 minutes and a range like "20-25 min" its shortest time
+fn parse_timer(step: &str) -> Option<Duration> {
+    let words: Vec<String> = step
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && c != '.' && c != '-').trim_end_matches('.').to_lowercase())
+        .collect();
+    words.windows(2).find_map(|pair| {
+        let seconds = TIME_UNITS.iter().find(|(unit, _)| *unit == pair[1]).map(|(_, s)| *s)?;
+        let amount: f64 = pair[0].split('-').next()?.parse().ok()?;
+        (amount > 0.0).then(|| Duration::from_secs_f64(amount * seconds as f64))
+    })
+}
+
+//"1 h 5 min", "20 min" or "45 s"
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds % 3600 / 60, seconds % 60) {
+        (0, 0, s) => format!("{} s", s),
+        (0, m, 0) => format!("{} min", m),
+        (0, m, s) => format!("{} min {} s", m, s),
+        (h, 0, _) => format!("{} h", h),
+        (h, m, _) => format!("{} h {} min", h, m),
+    }
+}
+
+//reports the time left every 5 minutes, every minute in the last 10 and every 10 seconds in the last one
+async fn count_down(step: usize, duration: Duration) {
+    let mut left = duration.as_secs();
+    while left > 0 {
+        let every = match left {
+            601.. => 300,
+            61..=600 => 60,
+            _ => 10,
+        };
+        let next = (left - 1) / every * every;
+        tokio::time::sleep(Duration::from_secs(left - next)).await;
+        left = next;
+        if left > 0 {
+            info!("Step {} timer: {} left", step, format_duration(Duration::from_secs(left)));
+        }
+    }
+    info!("Step {} timer is done", step);
+}
@@ -0,0 +1,171 @@
+use crate::config;
+use crate::error::{RecipeError, RecipeResult};
+use libp2p::{Multiaddr, PeerId};
+use log::{error, info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+//flag daemon start passes to the node it starts, it reads no commands and reports where it listens on stdout
+pub const DETACHED_FLAG: &str = "--detached";
+
+//last line a detached node writes to stdout, anything after it goes to the log file
+const READY_LINE: &str = "ready";
+
+//how long daemon start waits for the node to come up and daemon stop waits for it to exit
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+//whether this process is the node started by daemon start
+pub fn is_detached() -> bool {
+    std::env::args().any(|a| a == DETACHED_FLAG)
+}
+
+//tells daemon start where the detached node is, stdout isnt written to again since nothing reads it after this
+pub fn report_ready<'a>(peer: &PeerId, listeners: impl Iterator<Item = &'a Multiaddr>) {
+    let mut report = format!("Peer ID: {}\n", peer);
+    listeners.for_each(|a| report.push_str(&format!("Listening on: {}\n", a)));
+    report.push_str(READY_LINE);
+    println!("{}", report);
+}
+
+//the pid recorded in the pid file, None when there is no file
+fn read_pid(path: &str) -> RecipeResult<Option<u32>> {
+    match fs::read_to_string(path) {
+        Ok(content) => content
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| RecipeError::Validation(format!("pid file {} doesnt hold a pid, remove it if no node is running", path))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//whether a process with the pid is still running, kill -0 only checks it can be signalled
+fn is_running(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+//a pid file left behind by a node that is no longer running is removed, a running one means there is nothing to start
+fn check_pid_file(path: &str) -> RecipeResult<()> {
+    match read_pid(path)? {
+        Some(pid) if is_running(pid) => Err(RecipeError::Validation(format!(
+            "a node is already running as pid {}, stop it with: daemon stop",
+            pid
+        ))),
+        Some(pid) => {
+            warn!("removing stale pid file {}, pid {} is not running", path, pid);
+            fs::remove_file(path)?;
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+//starts the node in the background with the same arguments, logging to daemon_log_file, and records it in daemon_pid_file
+//returns once the node has reported its peer id and listen addresses, which are printed here
+pub fn start(args: &[String]) -> RecipeResult<()> {
+    let config = config::get()?;
+    if config.offline || args.iter().any(|a| a == "--offline") {
+        return Err(RecipeError::Validation("daemon start runs a networked node, it cant be offline".to_owned()));
+    }
+    check_pid_file(&config.daemon_pid_file)?;
+    let log = OpenOptions::new().create(true).append(true).open(&config.daemon_log_file)?;
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .args(args)
+        .arg(DETACHED_FLAG)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(log);
+    //its own process group so ctrl-c at the terminal that started it doesnt reach it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn()?;
+    fs::write(&config.daemon_pid_file, child.id().to_string())?;
+    //the node is read on another thread so a node that never comes up doesnt block past the timeout
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let (sender, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        match lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) if line == READY_LINE => break,
+            Ok(line) => info!("{}", line),
+            Err(_) => {
+                //it exited or is stuck, either way it isnt running as a daemon
+                let _ = child.kill();
+                let _ = fs::remove_file(&config.daemon_pid_file);
+                return Err(RecipeError::Validation(format!(
+                    "the node didnt come up within {}s, see {}",
+                    STARTUP_TIMEOUT.as_secs(),
+                    config.daemon_log_file
+                )));
+            }
+        }
+    }
+    info!("Running in the background as pid {}, logging to {}", child.id(), config.daemon_log_file);
+    info!("pid file: {}, stop it with: daemon stop", config.daemon_pid_file);
+    Ok(())
+}
+
+//asks the node in daemon_pid_file to shut down and waits for it to exit, it saves recipes the same as on ctrl-c
+pub fn stop() -> RecipeResult<()> {
+    let path = config::get()?.daemon_pid_file.clone();
+    let pid = match read_pid(&path)? {
+        Some(pid) => pid,
+        None => return Err(RecipeError::Validation(format!("no node is running, there is no pid file at {}", path))),
+    };
+    if !is_running(pid) {
+        fs::remove_file(&path)?;
+        info!("No node was running as pid {}, removed the stale pid file {}", pid, path);
+        return Ok(());
+    }
+    let signalled = Command::new("kill").args(["-TERM", &pid.to_string()]).status()?;
+    if !signalled.success() {
+        return Err(RecipeError::Validation(format!("cant signal pid {}", pid)));
+    }
+    let deadline = Instant::now() + STOP_TIMEOUT;
+    while is_running(pid) {
+        if Instant::now() >= deadline {
+            return Err(RecipeError::Validation(format!("pid {} is still running after {}s", pid, STOP_TIMEOUT.as_secs())));
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    //the node removes it itself on a clean shutdown
+    let _ = fs::remove_file(&path);
+    info!("Stopped the node running as pid {}", pid);
+    Ok(())
+}
+
+//removes the pid file on shutdown if it still names this process, a newer node may have replaced it
+pub fn release_pid_file() {
+    let path = match config::get() {
+        Ok(config) => config.daemon_pid_file.clone(),
+        Err(e) => {
+            error!("error reading config: {}", e);
+            return;
+        }
+    };
+    if let Ok(Some(pid)) = read_pid(&path) {
+        if pid == std::process::id() {
+            if let Err(e) = fs::remove_file(&path) {
+                error!("error removing pid file {}: {}", path, e);
+            }
+        }
+    }
+}
+
@@ -0,0 +1,40 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+//remembers hashes of recently seen message contents so repeats inside the window can be dropped
+pub struct DedupCache {
+    window: Duration,
+    seen: HashMap<u64, Instant>,
+}
+
+impl DedupCache {
+    //a zero window disables deduplication
+    pub fn new(window: Duration) -> DedupCache {
+        DedupCache {
+            window,
+            seen: HashMap::new(),
+        }
+    }
+
+    //records the message and returns true if it hasn't been seen within the window
+    pub fn is_new(&mut self, data: &[u8]) -> bool {
+        if self.window.is_zero() {
+            return true;
+        }
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        let now = Instant::now();
+        match self.seen.insert(hasher.finish(), now) {
+            Some(seen_at) => now.duration_since(seen_at) >= self.window,
+            None => true,
+        }
+    }
+
+    //forgets messages older than the window so the cache doesnt grow forever
+    pub fn prune(&mut self) {
+        let window = self.window;
+        self.seen.retain(|_, seen_at| seen_at.elapsed() < window);
+    }
+}
@@ -0,0 +1,154 @@
+use libp2p::PeerId;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+//first wait before a failed dial is retried, doubled on every further failure
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+//longest wait between retries, the peer keeps being retried as long as it is known
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+//how long the dial stats of a peer that is no longer known are kept, in case it comes back
+const STATS_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+//what became of the dials to a peer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DialStats {
+    pub attempts: u32,
+    pub successes: u32,
+    pub failures: u32,
+    //when the peer stopped being known, its stats are dropped once this is older than STATS_RETENTION
+    forgotten_at: Option<Instant>,
+}
+
+impl DialStats {
+    //share of finished dials that connected
+    pub fn success_rate(&self) -> Option<f64> {
+        let finished = self.successes + self.failures;
+        (finished > 0).then(|| f64::from(self.successes) * 100.0 / f64::from(finished))
+    }
+}
+
+//dials known peers a few at a time, dials that fail or take longer than the timeout are retried with exponential backoff
+pub struct Dialer {
+    max_concurrent: usize,
+    timeout: Duration,
+    waiting: VecDeque<PeerId>,
+    //when each dial in flight was started
+    in_flight: HashMap<PeerId, Instant>,
+    //failed attempts in a row and when the next may start
+    backoff: HashMap<PeerId, (u32, Instant)>,
+    stats: HashMap<PeerId, DialStats>,
+}
+
+impl Dialer {
+    //at least one dial is always allowed
+    pub fn new(max_concurrent: usize, timeout: Duration) -> Dialer {
+        Dialer {
+            max_concurrent: max_concurrent.max(1),
+            timeout,
+            waiting: VecDeque::new(),
+            in_flight: HashMap::new(),
+            backoff: HashMap::new(),
+            stats: HashMap::new(),
+        }
+    }
+
+    //queues a dial unless one is already waiting or in flight
+    pub fn enqueue(&mut self, peer: PeerId) {
+        if let Some(stats) = self.stats.get_mut(&peer) {
+            stats.forgotten_at = None;
+        }
+        if !self.in_flight.contains_key(&peer) && !self.waiting.contains(&peer) {
+            self.waiting.push_back(peer);
+        }
+    }
+
+    //stops dialing a peer that is no longer known
+    pub fn forget(&mut self, peer: &PeerId) {
+        self.waiting.retain(|p| p != peer);
+        self.in_flight.remove(peer);
+        self.backoff.remove(peer);
+        if let Some(stats) = self.stats.get_mut(peer) {
+            stats.forgotten_at = Some(Instant::now());
+        }
+    }
+
+    //peers to dial now, as many as there are free slots for and whose backoff has passed
+    pub fn next_dials(&mut self) -> Vec<PeerId> {
+        let now = Instant::now();
+        self.stats.retain(|_, s| s.forgotten_at.is_none_or(|at| now.duration_since(at) < STATS_RETENTION));
+        let mut dials = Vec::new();
+        let mut deferred = VecDeque::new();
+        while self.in_flight.len() < self.max_concurrent {
+            let peer = match self.waiting.pop_front() {
+                Some(peer) => peer,
+                None => break,
+            };
+            match self.backoff.get(&peer) {
+                Some((_, retry_at)) if *retry_at > now => deferred.push_back(peer),
+                _ => {
+                    self.in_flight.insert(peer, now);
+                    self.stats.entry(peer).or_default().attempts += 1;
+                    dials.push(peer);
+                }
+            }
+        }
+        deferred.append(&mut self.waiting);
+        self.waiting = deferred;
+        dials
+    }
+
+    //a connection to the peer was established, however it came about
+    pub fn connected(&mut self, peer: &PeerId) {
+        //only a connection while a dial was in flight counts as the dial succeeding
+        if self.in_flight.remove(peer).is_some() {
+            self.stats.entry(*peer).or_default().successes += 1;
+        }
+        self.backoff.remove(peer);
+        self.waiting.retain(|p| p != peer);
+    }
+
+    //a dial failed, it is queued again behind a backoff, returns how long until the retry
+    pub fn failed(&mut self, peer: PeerId) -> Duration {
+        //a dial that already timed out isnt counted again when its addresses fail
+        if self.in_flight.remove(&peer).is_some() {
+            self.stats.entry(peer).or_default().failures += 1;
+        }
+        let attempts = self.backoff.get(&peer).map(|(a, _)| a + 1).unwrap_or(1);
+        let delay = BASE_BACKOFF.saturating_mul(1 << (attempts - 1).min(6)).min(MAX_BACKOFF);
+        self.backoff.insert(peer, (attempts, Instant::now() + delay));
+        self.enqueue(peer);
+        delay
+    }
+
+    //dials in flight for longer than the timeout, they are counted as failed
+    pub fn take_timed_out(&mut self) -> Vec<PeerId> {
+        let timeout = self.timeout;
+        let timed_out: Vec<PeerId> = self.in_flight.iter().filter(|(_, started)| started.elapsed() > timeout).map(|(p, _)| *p).collect();
+        timed_out.iter().for_each(|p| {
+            self.in_flight.remove(p);
+        });
+        timed_out
+    }
+
+    pub fn stats(&self, peer: &PeerId) -> Option<DialStats> {
+        self.stats.get(peer).copied()
+    }
+
+    //failed attempts in a row and how long until the next retry may start
+    pub fn backoff(&self, peer: &PeerId) -> Option<(u32, Duration)> {
+        self.backoff.get(peer).map(|(attempts, retry_at)| (*attempts, retry_at.saturating_duration_since(Instant::now())))
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    //dials in flight and waiting
+    pub fn pending(&self) -> (usize, usize) {
+        (self.in_flight.len(), self.waiting.len())
+    }
+}
@@ -0,0 +1,155 @@
+use crate::Recipe;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+//most pages a peer's digest is read from and most entries on one page, anything bigger is ignored so the index stays bounded
+const MAX_PAGES: usize = 64;
+const MAX_PAGE_ENTRIES: usize = 1000;
+
+//a digest is trusted for this many of its sender's intervals, so one lost round doesnt drop the peer from the index
+const FRESH_INTERVALS: u32 = 3;
+
+//a round whose pages dont all arrive within this long is given up on
+const INCOMPLETE_TIMEOUT: Duration = Duration::from_secs(60);
+
+//one recipe a peer serves to everyone, the hash tells whether a cached copy is still the same recipe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestEntry {
+    pub id: usize,
+    pub name: String,
+    pub hash: u64,
+}
+
+//one page of the recipes a peer serves, pages of the same round together list all of them
+//round is when the broadcast started, so repeats of an unchanged digest arent dropped as duplicates
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AvailabilityDigest {
+    pub digest: Vec<DigestEntry>,
+    pub round: u64,
+    pub page: usize,
+    pub pages: usize,
+    //seconds until the sender broadcasts its next round
+    pub interval_secs: u64,
+}
+
+//fnv-1a, unlike the std hasher it is the same on every build and peer
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3))
+}
+
+//hash of the recipe as it is sent
+pub fn recipe_hash(recipe: &Recipe) -> u64 {
+    fnv1a(&serde_json::to_vec(recipe).unwrap_or_default())
+}
+
+//splits the recipes into digest pages of at most page_size entries, no recipes is still one empty page so peers learn there are none
+pub fn pages(recipes: &[Recipe], page_size: usize, round: u64, interval_secs: u64) -> Vec<AvailabilityDigest> {
+    let entries: Vec<DigestEntry> = recipes
+        .iter()
+        .map(|r| DigestEntry { id: r.id, name: r.name.clone(), hash: recipe_hash(r) })
+        .collect();
+    let chunks: Vec<&[DigestEntry]> = if entries.is_empty() { vec![&[]] } else { entries.chunks(page_size.max(1)).collect() };
+    let pages = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(page, chunk)| AvailabilityDigest { digest: chunk.to_vec(), round, page, pages, interval_secs })
+        .collect()
+}
+
+//what one peer serves according to its last complete digest, and the round still coming in
+struct PeerDigest {
+    entries: Vec<DigestEntry>,
+    completed_at: Option<Instant>,
+    interval: Duration,
+    round: u64,
+    pages: usize,
+    received: HashMap<usize, Vec<DigestEntry>>,
+    //when the first page of the round coming in arrived
+    started_at: Instant,
+}
+
+impl PeerDigest {
+    fn is_fresh(&self) -> bool {
+        self.completed_at.is_some_and(|at| at.elapsed() <= self.interval * FRESH_INTERVALS)
+    }
+}
+
+//which peers hold which recipes, from their digests
+#[derive(Default)]
+pub struct DigestIndex {
+    peers: HashMap<PeerId, PeerDigest>,
+}
+
+impl DigestIndex {
+    //adds a page, returns the peer's recipes once it completes a round
+    pub fn record(&mut self, peer: PeerId, digest: AvailabilityDigest) -> Option<&[DigestEntry]> {
+        if digest.pages == 0 || digest.pages > MAX_PAGES || digest.page >= digest.pages || digest.digest.len() > MAX_PAGE_ENTRIES {
+            return None;
+        }
+        let known = self.peers.entry(peer).or_insert_with(|| PeerDigest {
+            entries: Vec::new(),
+            completed_at: None,
+            interval: Duration::ZERO,
+            round: digest.round,
+            pages: digest.pages,
+            received: HashMap::new(),
+            started_at: Instant::now(),
+        });
+        //pages of an older round arriving late are left out, a newer round starts over
+        if digest.round < known.round {
+            return None;
+        }
+        if digest.round > known.round || digest.pages != known.pages {
+            known.round = digest.round;
+            known.pages = digest.pages;
+            known.received.clear();
+        }
+        if known.received.is_empty() {
+            known.started_at = Instant::now();
+        }
+        known.received.insert(digest.page, digest.digest);
+        if known.received.len() < known.pages {
+            return None;
+        }
+        let mut received: Vec<(usize, Vec<DigestEntry>)> = known.received.drain().collect();
+        received.sort_by_key(|(page, _)| *page);
+        known.entries = received.into_iter().flat_map(|(_, entries)| entries).collect();
+        known.completed_at = Some(Instant::now());
+        known.interval = Duration::from_secs(digest.interval_secs.max(1));
+        //the next round starts after this one
+        known.round += 1;
+        Some(&known.entries)
+    }
+
+    //whether every one of the peers has a digest recent enough to answer for it
+    pub fn covers(&self, peers: &HashSet<PeerId>) -> bool {
+        peers.iter().all(|p| self.peers.get(p).is_some_and(PeerDigest::is_fresh))
+    }
+
+    //entries of the given peers matching the filter, by peer
+    pub fn find<F: Fn(&DigestEntry) -> bool>(&self, peers: &HashSet<PeerId>, wanted: F) -> Vec<(PeerId, &DigestEntry)> {
+        let mut found: Vec<(PeerId, &DigestEntry)> = peers
+            .iter()
+            .filter_map(|p| self.peers.get(p).filter(|d| d.is_fresh()).map(|d| (p, d)))
+            .flat_map(|(p, d)| d.entries.iter().filter(|e| wanted(e)).map(move |e| (*p, e)))
+            .collect();
+        found.sort_by_key(|(p, e)| (p.to_string(), e.id));
+        found
+    }
+
+    //peers with a fresh digest and the recipes they list in total
+    pub fn summary(&self) -> (usize, usize) {
+        self.peers
+            .values()
+            .filter(|d| d.is_fresh())
+            .fold((0, 0), |(peers, recipes), d| (peers + 1, recipes + d.entries.len()))
+    }
+
+    //forgets peers whose digest went stale unless a newer round is still coming in
+    pub fn prune(&mut self) {
+        self.peers.retain(|_, d| d.is_fresh() || !d.received.is_empty() && d.started_at.elapsed() < INCOMPLETE_TIMEOUT);
+    }
+}
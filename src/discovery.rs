@@ -0,0 +1,90 @@
+use crate::error::{RecipeError, RecipeResult};
+use libp2p::futures::Stream;
+use libp2p::multiaddr::Protocol;
+use libp2p::{Multiaddr, PeerId};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+//something a discovery mechanism found out about a peer
+#[derive(Debug, Clone)]
+pub enum DiscoveryEvent {
+    Discovered(PeerId, Multiaddr),
+    Expired(PeerId, Multiaddr),
+}
+
+//a source of peers, the swarm consumes whichever one is configured the same way
+pub trait Discovery: Stream<Item = DiscoveryEvent> + Unpin {
+    //name shown in status
+    fn name(&self) -> &'static str;
+}
+
+//peers found by the mdns behaviour, which has to run inside the swarm and forwards its events here
+pub struct MdnsDiscovery {
+    events: mpsc::UnboundedReceiver<DiscoveryEvent>,
+}
+
+impl MdnsDiscovery {
+    //returns the discovery along with the sender the mdns behaviour forwards events to
+    pub fn new() -> (MdnsDiscovery, mpsc::UnboundedSender<DiscoveryEvent>) {
+        let (sender, events) = mpsc::unbounded_channel();
+        (MdnsDiscovery { events }, sender)
+    }
+}
+
+impl Stream for MdnsDiscovery {
+    type Item = DiscoveryEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<DiscoveryEvent>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl Discovery for MdnsDiscovery {
+    fn name(&self) -> &'static str {
+        "mdns"
+    }
+}
+
+//a fixed list of peers from config, each reported once at startup
+pub struct StaticDiscovery {
+    pending: VecDeque<DiscoveryEvent>,
+}
+
+impl StaticDiscovery {
+    //every address has to end in /p2p/<peer id> so the peer is known before dialing
+    pub fn from_addresses(addresses: &[String]) -> RecipeResult<StaticDiscovery> {
+        let mut pending = VecDeque::new();
+        for address in addresses {
+            let mut addr: Multiaddr = address
+                .parse()
+                .map_err(|e| RecipeError::Validation(format!("invalid static peer address {}: {}", address, e)))?;
+            let peer = match addr.pop() {
+                Some(Protocol::P2p(hash)) => PeerId::from_multihash(hash).ok(),
+                _ => None,
+            }
+            .ok_or_else(|| RecipeError::Validation(format!("static peer address {} has to end in /p2p/<peer id>", address)))?;
+            pending.push_back(DiscoveryEvent::Discovered(peer, addr));
+        }
+        Ok(StaticDiscovery { pending })
+    }
+}
+
+impl Stream for StaticDiscovery {
+    type Item = DiscoveryEvent;
+
+    //the list never expires so once it is drained the stream just stays pending
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<DiscoveryEvent>> {
+        match self.pending.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Discovery for StaticDiscovery {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+}
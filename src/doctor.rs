@@ -0,0 +1,209 @@
+use crate::config::Config;
+use crate::discovery::StaticDiscovery;
+use crate::storage;
+use libp2p::{
+    core::upgrade,
+    futures::StreamExt,
+    identity,
+    mdns::{Mdns, MdnsEvent},
+    mplex,
+    noise::{Keypair, NoiseConfig, X25519Spec},
+    swarm::{SwarmBuilder, SwarmEvent},
+    tcp::TokioTcpConfig,
+    PeerId, Transport,
+};
+use log::{error, info, warn};
+use std::collections::HashSet;
+use std::time::Duration;
+
+//how long the doctor listens for peers on the local network
+const PEER_SEARCH_SECS: u64 = 5;
+
+//startup grace periods longer than this are warned about
+const LONG_STARTUP_GRACE_MS: u64 = 30_000;
+
+//how one check went, only failures of critical checks make the doctor fail
+enum Outcome {
+    Pass(String),
+    Fail { problem: String, hint: String },
+    //a problem worth knowing about that doesnt stop the node from working
+    Warn { problem: String, hint: String },
+    Skip(String),
+}
+
+//logs a check as one line of the checklist, returns whether it failed
+fn report(name: &str, outcome: Outcome) -> bool {
+    match outcome {
+        Outcome::Pass(detail) => info!("[pass] {}: {}", name, detail),
+        Outcome::Skip(reason) => info!("[skip] {}: {}", name, reason),
+        Outcome::Warn { problem, hint } => warn!("[warn] {}: {} - {}", name, problem, hint),
+        Outcome::Fail { problem, hint } => {
+            error!("[fail] {}: {} - {}", name, problem, hint);
+            return true;
+        }
+    }
+    false
+}
+
+//runs every check and prints a checklist, returns false if a critical check failed
+pub async fn run(config: &Config) -> bool {
+    info!("Checking setup");
+    let failures = [
+        report("listen port", check_listen_port(config.listen_port)),
+        report("storage", check_storage().await),
+        report("discovery", check_discovery(config).await),
+        report("startup grace", check_startup_grace(config.startup_grace_ms)),
+    ];
+    let failed = failures.iter().filter(|f| **f).count();
+    if failed == 0 {
+        info!("All critical checks passed");
+    } else {
+        error!("{} critical checks failed", failed);
+    }
+    failed == 0
+}
+
+//the port has to be free for the node to listen on it, 0 always is since the os picks one
+fn check_listen_port(port: u16) -> Outcome {
+    match std::net::TcpListener::bind(("0.0.0.0", port)) {
+        Ok(_) if port == 0 => Outcome::Pass("the os picks a free port".to_owned()),
+        Ok(_) => Outcome::Pass(format!("port {} is free", port)),
+        Err(e) => Outcome::Fail {
+            problem: format!("cant bind port {}: {}", port, e),
+            hint: "stop whatever is using it or set listen_port / LISTEN_PORT to another port, 0 picks one".to_owned(),
+        },
+    }
+}
+
+//peers asking during the grace period get no answer, a long one makes a freshly started node look empty
+fn check_startup_grace(grace_ms: u64) -> Outcome {
+    match grace_ms {
+        0 => Outcome::Pass("requests are answered as soon as the node starts".to_owned()),
+        ms if ms > LONG_STARTUP_GRACE_MS => Outcome::Warn {
+            problem: format!("requests are ignored for {}ms after startup", ms),
+            hint: "peers listing recipes in that time see none from this node, lower startup_grace_ms".to_owned(),
+        },
+        ms => Outcome::Pass(format!("requests are answered {}ms after startup", ms)),
+    }
+}
+
+//the recipes file has to parse and be writable, a missing file is fine as long as its directory is writable
+async fn check_storage() -> Outcome {
+    let path = match storage::storage_path() {
+        Ok(path) => path,
+        Err(e) => {
+            return Outcome::Fail {
+                problem: e.to_string(),
+                hint: "check the config".to_owned(),
+            }
+        }
+    };
+    match tokio::fs::read(&path).await {
+        Ok(content) => {
+            //an empty file is fine, the node starts it off as an empty collection
+            let corruption = (!content.iter().all(|b| b.is_ascii_whitespace())).then(|| storage::find_corruption(&content)).flatten();
+            if let Some(problem) = corruption {
+                return Outcome::Fail {
+                    problem: format!("{} is corrupt: {}", path, problem),
+                    hint: "fix it by hand or start the node with --repair to back it up and start fresh".to_owned(),
+                };
+            }
+            //opening for writing without truncating leaves the file as it is
+            match std::fs::OpenOptions::new().write(true).open(&path) {
+                Ok(_) => Outcome::Pass(format!("{} is readable and writable", path)),
+                Err(e) => Outcome::Fail {
+                    problem: format!("{} is not writable: {}", path, e),
+                    hint: "fix the file permissions or set storage_file / STORAGE_FILE to a writable path".to_owned(),
+                },
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let probe = format!("{}.doctor", path);
+            match tokio::fs::write(&probe, b"").await {
+                Ok(_) => {
+                    let _ = tokio::fs::remove_file(&probe).await;
+                    Outcome::Pass(format!("{} doesnt exist yet but can be created", path))
+                }
+                Err(e) => Outcome::Fail {
+                    problem: format!("{} cant be created: {}", path, e),
+                    hint: "create its directory or set storage_file / STORAGE_FILE to a writable path".to_owned(),
+                },
+            }
+        }
+        Err(e) => Outcome::Fail {
+            problem: format!("cant read {}: {}", path, e),
+            hint: "fix the file permissions or set storage_file / STORAGE_FILE to a readable path".to_owned(),
+        },
+    }
+}
+
+//the configured discovery has to start, finding nobody is only a warning since the node may just be the first one up
+async fn check_discovery(config: &Config) -> Outcome {
+    match config.discovery.as_str() {
+        "static" => match StaticDiscovery::from_addresses(&config.static_peers) {
+            Ok(_) if config.static_peers.is_empty() => Outcome::Warn {
+                problem: "static discovery has no peers configured".to_owned(),
+                hint: "add multiaddrs ending in /p2p/<peer id> to static_peers".to_owned(),
+            },
+            Ok(_) => Outcome::Pass(format!("{} static peers configured", config.static_peers.len())),
+            Err(e) => Outcome::Fail {
+                problem: e.to_string(),
+                hint: "every static_peers entry needs to be a multiaddr ending in /p2p/<peer id>".to_owned(),
+            },
+        },
+        "mdns" if !config.enable_mdns => Outcome::Skip("mdns is disabled, peers are only reachable by address".to_owned()),
+        "mdns" => check_mdns().await,
+        other => Outcome::Fail {
+            problem: format!("unknown discovery {:?}", other),
+            hint: "set discovery / DISCOVERY to mdns or static".to_owned(),
+        },
+    }
+}
+
+//starts mdns on a throwaway identity and listens for peers for a few seconds
+async fn check_mdns() -> Outcome {
+    let mdns = match Mdns::new(Default::default()).await {
+        Ok(mdns) => mdns,
+        Err(e) => {
+            return Outcome::Fail {
+                problem: format!("mdns cant start: {}", e),
+                hint: "allow udp port 5353 multicast through the firewall or set discovery to static".to_owned(),
+            }
+        }
+    };
+    let keys = identity::Keypair::generate_ed25519();
+    let local_peer = PeerId::from(keys.public());
+    let auth_keys = Keypair::<X25519Spec>::new().into_authentic(&keys).expect("Can create auth keys");
+    let transport = TokioTcpConfig::new()
+        .upgrade(upgrade::Version::V1)
+        .authenticate(NoiseConfig::xx(auth_keys).into_authenticated())
+        .multiplex(mplex::MplexConfig::new())
+        .boxed();
+    let mut swarm = SwarmBuilder::new(transport, mdns, local_peer)
+        .executor(Box::new(|fut| {
+            tokio::spawn(fut);
+        }))
+        .build();
+    info!("Looking for peers for {}s", PEER_SEARCH_SECS);
+    let mut peers = HashSet::new();
+    let search = tokio::time::sleep(Duration::from_secs(PEER_SEARCH_SECS));
+    tokio::pin!(search);
+    loop {
+        tokio::select! {
+            _ = &mut search => break,
+            event = swarm.select_next_some() => {
+                if let SwarmEvent::Behaviour(MdnsEvent::Discovered(discovered)) = event {
+                    peers.extend(discovered.map(|(peer, _)| peer));
+                }
+            }
+        }
+    }
+    if peers.is_empty() {
+        Outcome::Warn {
+            problem: "mdns started but no peers answered".to_owned(),
+            hint: "start another node on this network, or check that multicast isnt blocked between machines".to_owned(),
+        }
+    } else {
+        Outcome::Pass(format!("mdns found {} peers", peers.len()))
+    }
+}
@@ -0,0 +1,41 @@
+use thiserror::Error;
+
+//creates RecipeResult type which carries a RecipeError so callers can match on the category of failure
+pub type RecipeResult<T> = std::result::Result<T, RecipeError>;
+
+//every category of failure the app can run into
+#[derive(Debug, Error)]
+pub enum RecipeError {
+    //reading or writing the storage file failed
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    //recipe data couldn't be converted to or from json
+    #[error("serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+    //no recipe with the given id exists locally
+    #[error("no recipe with id {id}")]
+    NotFound { id: usize },
+    //user input or recipe content was rejected
+    #[error("invalid input: {0}")]
+    Validation(String),
+    //the storage file or its directory cant be written, changes stay in memory until a save gets through
+    #[error("cant write {path}: {reason}. Fix the permissions or move it with: config set storage_file <path>, then run save. Changes are kept in memory until then")]
+    Storage { path: String, reason: String },
+    //sending or receiving over the network failed
+    #[error("network error: {0}")]
+    Network(String),
+    //downloading a page for import r --url failed, timed out or the page was too large
+    #[cfg(feature = "http")]
+    #[error("fetch error: {0}")]
+    Fetch(String),
+    //the page had no schema.org Recipe json-ld to import
+    #[cfg(feature = "http")]
+    #[error("no recipe found at {url}, the page has no schema.org Recipe json-ld")]
+    NoRecipeFound { url: String },
+    //a command that needs the network was run in offline mode
+    #[error("{0} needs the network, restart without --offline or OFFLINE to use it")]
+    Offline(String),
+    //shared state couldn't be locked because another thread panicked while holding it
+    #[error("lock poisoned: {0}")]
+    Lock(String),
+}
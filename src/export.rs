@@ -0,0 +1,152 @@
+use crate::error::{RecipeError, RecipeResult};
+use crate::Recipe;
+use serde::Serialize;
+
+//formats a recipe can be exported to for other recipe apps
+//neither has a place for the id, visibility, archived flag, structured yield or substitutions as data,
+//so dietary labels and substitutions end up as text in the notes and dont come back as fields on import
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    //paprika's yaml import, tags become categories and times are written as "N min"
+    Paprika,
+    //meal-master text, units without a meal-master code stay in the ingredient name and tags are dropped past the category
+    MealMaster,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = RecipeError;
+
+    fn from_str(s: &str) -> RecipeResult<Self> {
+        match s.to_lowercase().as_str() {
+            "paprika" => Ok(ExportFormat::Paprika),
+            "mealmaster" | "mmf" => Ok(ExportFormat::MealMaster),
+            other => Err(RecipeError::Validation(format!("unknown export format {:?}, expected paprika or mealmaster", other))),
+        }
+    }
+}
+
+//renders a recipe in the given format
+pub fn render(recipe: &Recipe, format: ExportFormat) -> RecipeResult<String> {
+    match format {
+        ExportFormat::Paprika => to_paprika(recipe),
+        ExportFormat::MealMaster => Ok(to_mealmaster(recipe)),
+    }
+}
+
+//one recipe in paprika's yaml import format, a file holds a list of them
+#[derive(Serialize)]
+struct PaprikaRecipe {
+    name: String,
+    servings: String,
+    prep_time: String,
+    cook_time: String,
+    total_time: String,
+    categories: Vec<String>,
+    ingredients: String,
+    directions: String,
+    notes: String,
+}
+
+//dietary labels and substitutions as text, for formats without fields for them
+fn notes(recipe: &Recipe) -> Vec<String> {
+    let mut notes = Vec::new();
+    if !recipe.dietary.is_empty() {
+        notes.push(format!("Dietary: {}", recipe.dietary.join(", ")));
+    }
+    recipe.substitutions.iter().for_each(|s| notes.push(format!("Substitution: {}", s)));
+    notes
+}
+
+fn minutes(value: Option<u32>) -> String {
+    value.map(|m| format!("{} min", m)).unwrap_or_default()
+}
+
+fn to_paprika(recipe: &Recipe) -> RecipeResult<String> {
+    let servings = match (recipe.servings, &recipe.recipe_yield) {
+        (Some(servings), _) => servings.to_string(),
+        (None, Some(recipe_yield)) => recipe_yield.to_string(),
+        (None, None) => String::new(),
+    };
+    let paprika = PaprikaRecipe {
+        name: recipe.name.clone(),
+        servings,
+        prep_time: minutes(recipe.prep_minutes),
+        cook_time: minutes(recipe.cook_minutes),
+        total_time: minutes(recipe.total_minutes()),
+        categories: recipe.category.iter().chain(&recipe.tags).cloned().collect(),
+        ingredients: recipe.ingredient_lines().join("\n"),
+        directions: recipe.steps().join("\n"),
+        notes: notes(recipe).join("\n"),
+    };
+    serde_yaml::to_string(&vec![paprika]).map_err(|e| RecipeError::Validation(format!("cant write paprika yaml: {}", e)))
+}
+
+//meal-master's two letter unit codes for the units recipes are parsed with
+const MEALMASTER_UNITS: &[(&str, &str)] = &[
+    ("g", "g"),
+    ("kg", "kg"),
+    ("mg", "mg"),
+    ("ml", "ml"),
+    ("l", "l"),
+    ("tsp", "ts"),
+    ("tbsp", "tb"),
+    ("cup", "c"),
+    ("cups", "c"),
+    ("oz", "oz"),
+    ("lb", "lb"),
+    ("lbs", "lb"),
+    ("pinch", "pn"),
+    ("slice", "sl"),
+    ("slices", "sl"),
+    ("can", "cn"),
+    ("cans", "cn"),
+];
+
+fn to_mealmaster(recipe: &Recipe) -> String {
+    let mut lines = vec!["MMMMM----- Recipe via Meal-Master (tm) v8.05".to_owned(), String::new()];
+    lines.push(format!("      Title: {}", recipe.name));
+    let categories: Vec<&String> = recipe.category.iter().chain(&recipe.tags).take(5).collect();
+    lines.push(format!(
+        " Categories: {}",
+        categories.iter().map(|c| c.as_str()).collect::<Vec<_>>().join(", ")
+    ));
+    let servings = match (recipe.servings, &recipe.recipe_yield) {
+        (Some(servings), _) => format!("{} servings", servings),
+        (None, Some(recipe_yield)) => recipe_yield.to_string(),
+        (None, None) => "1 servings".to_owned(),
+    };
+    lines.push(format!("      Yield: {}", servings));
+    lines.push(String::new());
+    if recipe.structured_ingredients.is_empty() {
+        lines.extend(recipe.ingredients.split(',').map(|i| format!("           {}", i.trim())));
+    } else {
+        for ingredient in &recipe.structured_ingredients {
+            let amount = ingredient.quantity_text().unwrap_or_default();
+            let code = ingredient
+                .unit
+                .as_deref()
+                .and_then(|u| MEALMASTER_UNITS.iter().find(|(unit, _)| *unit == u).map(|(_, code)| *code));
+            //units meal-master has no code for are kept in front of the name
+            let name = match (&ingredient.unit, code) {
+                (Some(unit), None) => format!("{} {}", unit, ingredient.name),
+                _ => ingredient.name.clone(),
+            };
+            lines.push(format!("{:>7} {:<2} {}", amount, code.unwrap_or(""), name));
+        }
+    }
+    lines.push(String::new());
+    lines.extend(recipe.steps().iter().map(|s| format!("  {}", s)));
+    if let Some(time) = recipe.time_breakdown() {
+        lines.push(String::new());
+        lines.push(format!("  {}", time));
+    }
+    let notes = notes(recipe);
+    if !notes.is_empty() {
+        lines.push(String::new());
+        lines.extend(notes.iter().map(|n| format!("  {}", n)));
+    }
+    lines.push(String::new());
+    lines.push("MMMMM".to_owned());
+    lines.push(String::new());
+    lines.join("\n")
+}
@@ -0,0 +1,119 @@
+use crate::error::{RecipeError, RecipeResult};
+use crate::manifests::Manifest;
+use crate::Recipe;
+use async_trait::async_trait;
+use libp2p::core::upgrade::{read_length_prefixed, write_length_prefixed, ProtocolName};
+use libp2p::futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use libp2p::request_response::RequestResponseCodec;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::str::FromStr;
+
+//largest fetch message accepted from a peer
+const MAX_FETCH_MESSAGE_SIZE: usize = 1024 * 1024;
+//scheme every shareable recipe link starts with
+const LINK_SCHEME: &str = "recipe://";
+
+//what one peer can ask another peer for directly
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FetchRequest {
+    Recipe { id: usize },
+    //a published collection manifest by name
+    Manifest { name: String },
+}
+
+//reply to a fetch request, recipes that don't exist or aren't public and manifests that weren't published come back as None
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FetchResponse {
+    //boxed so manifest responses dont take up the size of a whole recipe, serialized the same either way
+    Recipe(Option<Box<Recipe>>),
+    Manifest(Option<Manifest>),
+}
+
+//protocol name negotiated for direct fetches
+#[derive(Debug, Clone)]
+pub struct FetchProtocol;
+
+impl ProtocolName for FetchProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        b"/recipes/fetch/1.0.0"
+    }
+}
+
+//encodes fetch messages as length prefixed json
+#[derive(Clone)]
+pub struct FetchCodec;
+
+//turns a serde error into the io error the codec has to return
+fn invalid_data(e: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[async_trait]
+impl RequestResponseCodec for FetchCodec {
+    type Protocol = FetchProtocol;
+    type Request = FetchRequest;
+    type Response = FetchResponse;
+
+    async fn read_request<T>(&mut self, _: &FetchProtocol, io: &mut T) -> io::Result<FetchRequest>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = read_length_prefixed(io, MAX_FETCH_MESSAGE_SIZE).await?;
+        serde_json::from_slice(&bytes).map_err(invalid_data)
+    }
+
+    async fn read_response<T>(&mut self, _: &FetchProtocol, io: &mut T) -> io::Result<FetchResponse>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = read_length_prefixed(io, MAX_FETCH_MESSAGE_SIZE).await?;
+        serde_json::from_slice(&bytes).map_err(invalid_data)
+    }
+
+    async fn write_request<T>(&mut self, _: &FetchProtocol, io: &mut T, req: FetchRequest) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let bytes = serde_json::to_vec(&req).map_err(invalid_data)?;
+        write_length_prefixed(io, bytes).await?;
+        io.close().await
+    }
+
+    async fn write_response<T>(&mut self, _: &FetchProtocol, io: &mut T, res: FetchResponse) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let bytes = serde_json::to_vec(&res).map_err(invalid_data)?;
+        write_length_prefixed(io, bytes).await?;
+        io.close().await
+    }
+}
+
+//builds the shareable link for a recipe owned by the given peer
+pub fn share_link(author: &PeerId, recipe_id: usize) -> String {
+    format!("{}{}/{}", LINK_SCHEME, author, recipe_id)
+}
+
+//parses a link made by share_link back into the author and recipe id
+pub fn parse_link(link: &str) -> RecipeResult<(PeerId, usize)> {
+    let invalid = |reason: &str| {
+        RecipeError::Validation(format!(
+            "invalid recipe link {:?}: {} - Format: {}<peer_id>/<recipe_id>",
+            link, reason, LINK_SCHEME
+        ))
+    };
+    let rest = link
+        .trim()
+        .strip_prefix(LINK_SCHEME)
+        .ok_or_else(|| invalid("must start with recipe://"))?;
+    let (peer_id, recipe_id) = rest
+        .split_once('/')
+        .ok_or_else(|| invalid("missing recipe id"))?;
+    let peer_id = PeerId::from_str(peer_id).map_err(|_| invalid("peer id is not valid"))?;
+    let recipe_id = recipe_id
+        .parse::<usize>()
+        .map_err(|_| invalid("recipe id must be a number"))?;
+    Ok((peer_id, recipe_id))
+}
@@ -0,0 +1,100 @@
+use crate::error::{RecipeError, RecipeResult};
+use serde::Serialize;
+
+//formats the node's view of the network can be exported in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    //graphviz, render with e.g. dot -Tsvg
+    Dot,
+    //the nodes and edges as plain data for other tools
+    Json,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = RecipeError;
+
+    fn from_str(s: &str) -> RecipeResult<Self> {
+        match s.to_lowercase().as_str() {
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            other => Err(RecipeError::Validation(format!("unknown graph format {:?}, expected dot or json", other))),
+        }
+    }
+}
+
+//a peer in the graph, anything identify or a connection hasnt told us yet is None
+#[derive(Debug, Serialize)]
+pub struct Node {
+    pub peer_id: String,
+    pub local: bool,
+    pub agent_version: Option<String>,
+    pub protocol_version: Option<String>,
+    pub addrs: Vec<String>,
+}
+
+//how this node stands with a peer
+#[derive(Debug, Serialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    //"connected" or "discovered" for a peer known but not connected to
+    pub state: &'static str,
+    //whether floodsub propagates messages to the peer
+    pub in_view: bool,
+    pub rtt_ms: Option<u128>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+//renders the graph in the given format
+pub fn render(graph: &Graph, format: GraphFormat) -> RecipeResult<String> {
+    match format {
+        GraphFormat::Dot => Ok(to_dot(graph)),
+        GraphFormat::Json => Ok(serde_json::to_string_pretty(graph)?),
+    }
+}
+
+//nodes are labelled with a shortened peer id and their agent, connected edges are solid and the rest dashed
+fn to_dot(graph: &Graph) -> String {
+    let mut dot = String::from("digraph peers {\n    node [shape=box];\n");
+    for node in &graph.nodes {
+        let mut label = short_id(&node.peer_id);
+        if node.local {
+            label.push_str(" (this node)");
+        }
+        if let Some(agent) = &node.agent_version {
+            label.push_str(&format!("\\n{}", agent));
+        }
+        let style = if node.local { ", style=bold" } else { "" };
+        dot.push_str(&format!("    \"{}\" [label=\"{}\"{}];\n", node.peer_id, escape(&label), style));
+    }
+    for edge in &graph.edges {
+        let mut label = edge.state.to_owned();
+        if let Some(rtt) = edge.rtt_ms {
+            label.push_str(&format!(", {} ms", rtt));
+        }
+        if edge.in_view {
+            label.push_str(", in view");
+        }
+        let style = if edge.state == "connected" { "solid" } else { "dashed" };
+        dot.push_str(&format!("    \"{}\" -> \"{}\" [label=\"{}\", style={}];\n", edge.from, edge.to, escape(&label), style));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+//the last characters of a peer id, they are the part that tells peers apart
+fn short_id(peer_id: &str) -> String {
+    let chars: Vec<char> = peer_id.chars().collect();
+    let start = chars.len().saturating_sub(8);
+    format!("…{}", chars[start..].iter().collect::<String>())
+}
+
+//a quote in an agent version would end the dot string early
+fn escape(label: &str) -> String {
+    label.replace('"', "\\\"")
+}
@@ -0,0 +1,46 @@
+use crate::config;
+use crate::digest::fnv1a;
+use crate::error::RecipeResult;
+use std::path::PathBuf;
+
+//content types kept as images and the extension each is saved with, anything else a url serves is refused
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("image/avif", "avif"),
+    ("image/svg+xml", "svg"),
+];
+
+//the extension for a content type like "image/png; charset=binary"
+#[cfg(feature = "http")]
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let media_type = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+    IMAGE_TYPES.iter().find(|(t, _)| *t == media_type).map(|(_, ext)| *ext)
+}
+
+//images are named after a hash of their url, so a recipe whose url changed doesnt show the old picture
+fn stem(url: &str) -> String {
+    format!("{:016x}", fnv1a(url.as_bytes()))
+}
+
+//the copy kept of the image at the url, if it has been fetched
+pub fn cached(url: &str) -> RecipeResult<Option<PathBuf>> {
+    let dir = PathBuf::from(config::get()?.image_dir.clone());
+    Ok(IMAGE_TYPES.iter().map(|(_, ext)| dir.join(format!("{}.{}", stem(url), ext))).find(|p| p.is_file()))
+}
+
+//keeps the downloaded image, written next to its final name first so a failed write never leaves half an image behind
+#[cfg(feature = "http")]
+pub fn store(url: &str, content_type: &str, bytes: &[u8]) -> RecipeResult<PathBuf> {
+    let ext = extension_for(content_type)
+        .ok_or_else(|| crate::error::RecipeError::Validation(format!("{} serves {:?}, not an image", url, content_type)))?;
+    let dir = PathBuf::from(config::get()?.image_dir.clone());
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.{}", stem(url), ext));
+    let partial = path.with_extension(format!("{}.partial", ext));
+    std::fs::write(&partial, bytes)?;
+    std::fs::rename(&partial, &path)?;
+    Ok(path)
+}
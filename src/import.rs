@@ -0,0 +1,487 @@
+use crate::config;
+use crate::error::{RecipeError, RecipeResult};
+use crate::ingredients;
+use crate::migrate;
+use crate::schema;
+use crate::storage::{read_local_recipes, write_local_recipes};
+use crate::validation::{parse_record, validate_recipe};
+use crate::workers;
+use crate::Recipe;
+use log::info;
+use serde::Deserialize;
+use std::collections::HashSet;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+//metadata a cookbook recipe can carry in its yaml front-matter
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FrontMatter {
+    title: String,
+    tags: Vec<String>,
+    category: Option<String>,
+    dietary: Vec<String>,
+    servings: Option<u32>,
+    //what the recipe makes as a weight or volume, e.g. "1 kg"
+    #[serde(rename = "yield")]
+    recipe_yield: Option<String>,
+    prep_minutes: Option<u32>,
+    cook_minutes: Option<u32>,
+}
+
+//how a bulk import went, failures carry the position of the block that was skipped
+pub struct ImportReport {
+    pub imported: Vec<Recipe>,
+    pub failures: Vec<String>,
+    //problems found by schema validation, when there are any nothing was imported
+    pub schema_errors: Vec<String>,
+}
+
+//splits a cookbook into (front-matter, body, line the front-matter starts on) blocks
+//each recipe is "---", yaml, "---", markdown body, and the next recipe starts with "---" again
+fn split_blocks(content: &str) -> Vec<(String, String, usize)> {
+    let mut segments: Vec<(String, usize)> = Vec::new();
+    let mut current = String::new();
+    let mut start = 1;
+    for (index, line) in content.lines().enumerate() {
+        if line.trim() == "---" {
+            segments.push((std::mem::take(&mut current), start));
+            start = index + 2;
+        } else {
+            current.push_str(line);
+            current.push('\n');
+        }
+    }
+    segments.push((current, start));
+    //anything before the first delimiter isn't part of a recipe
+    let segments: Vec<(String, usize)> = segments.into_iter().skip(1).collect();
+    segments
+        .chunks(2)
+        .map(|pair| {
+            let (front, line) = pair[0].clone();
+            let body = pair.get(1).map(|(body, _)| body.clone()).unwrap_or_default();
+            (front, body, line)
+        })
+        .collect()
+}
+
+//pulls the list items under the first heading containing one of the given words
+fn section_items(body: &str, headings: &[&str]) -> Vec<String> {
+    let mut in_section = false;
+    let mut items = Vec::new();
+    for line in body.lines().map(|l| l.trim()) {
+        if let Some(heading) = line.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#').trim().to_lowercase();
+            in_section = headings.iter().any(|h| heading.contains(h));
+            continue;
+        }
+        if !in_section || line.is_empty() {
+            continue;
+        }
+        //bullets and numbered steps both count as items
+        let item = line
+            .strip_prefix("- ")
+            .or_else(|| line.strip_prefix("* "))
+            .or_else(|| line.split_once(". ").filter(|(n, _)| n.parse::<u32>().is_ok()).map(|(_, rest)| rest))
+            .unwrap_or(line);
+        items.push(item.trim().to_owned());
+    }
+    items
+}
+
+//turns one front-matter and body pair into an unsaved recipe
+fn parse_block(front: &str, body: &str) -> RecipeResult<Recipe> {
+    let meta: FrontMatter =
+        serde_yaml::from_str(front).map_err(|e| RecipeError::Validation(format!("invalid front-matter: {}", e)))?;
+    let ingredient_items = section_items(body, &["ingredient"]);
+    let steps = section_items(body, &["instruction", "method", "step", "direction"]);
+    if ingredient_items.is_empty() || steps.is_empty() {
+        return Err(RecipeError::Validation(
+            "body needs an ingredients heading and an instructions heading with items under each".to_owned(),
+        ));
+    }
+    let recipe_yield = meta.recipe_yield.as_deref().map(ingredients::parse_yield).transpose()?;
+    Ok(Recipe {
+        id: 0,
+        name: meta.title,
+        ingredients: ingredient_items.join(", "),
+        instructions: steps.join("\n"),
+        public: false,
+        tags: meta.tags,
+        category: meta.category,
+        dietary: meta.dietary,
+        structured_ingredients: ingredient_items.iter().filter_map(|i| ingredients::parse_ingredient(i)).collect(),
+        servings: meta.servings,
+        recipe_yield,
+        substitutions: Vec::new(),
+        archived: false,
+        prep_minutes: meta.prep_minutes,
+        cook_minutes: meta.cook_minutes,
+        time_minutes: None,
+        topics: Vec::new(),
+        denied_peers: Vec::new(),
+        image_url: None,
+        updated_at: None,
+    })
+}
+
+//adds already validated drafts to local storage with fresh ids in one write
+pub async fn add_recipes(drafts: Vec<Recipe>) -> RecipeResult<Vec<Recipe>> {
+    let mut local_recipes = read_local_recipes().await?;
+    let first_id = local_recipes.iter().map(|r| r.id + 1).max().unwrap_or(0);
+    let mut added = Vec::new();
+    for (id, mut recipe) in (first_id..).zip(drafts) {
+        recipe.id = id;
+        added.push(recipe.clone());
+        local_recipes.push(recipe);
+    }
+    if !added.is_empty() {
+        write_local_recipes(&local_recipes).await?;
+    }
+    Ok(added)
+}
+
+//imports a markdown cookbook with yaml front-matter per recipe, skipping blocks that fail to parse or validate
+pub async fn import_cookbook(path: &str) -> RecipeResult<ImportReport> {
+    import_cookbook_text(&tokio::fs::read_to_string(path).await?).await
+}
+
+//imports cookbook markdown that is already in memory, the same way import_cookbook does a file
+pub async fn import_cookbook_text(content: &str) -> RecipeResult<ImportReport> {
+    let mut drafts = Vec::new();
+    let mut failures = Vec::new();
+    for (index, (front, body, line)) in split_blocks(content).into_iter().enumerate() {
+        match parse_block(&front, &body).and_then(|r| validate_recipe(&r).map(|_| r)) {
+            Ok(recipe) => drafts.push(recipe),
+            Err(e) => failures.push(format!("recipe {} (line {}): {}", index + 1, line, e)),
+        }
+    }
+    Ok(ImportReport {
+        imported: add_recipes(drafts).await?,
+        failures,
+        schema_errors: Vec::new(),
+    })
+}
+
+//recipes added to storage in one write while importing a json file
+const IMPORT_BATCH: usize = 500;
+
+//records read between progress lines while importing a json file
+const PROGRESS_EVERY: usize = 1000;
+
+//splits a json array of records, or a file with one record per line, into the bytes of each record
+//only one record is held at a time, so large files are never read or parsed as a whole
+struct RecordStream {
+    reader: BufReader<File>,
+    array: bool,
+    done: bool,
+}
+
+impl RecordStream {
+    //looks at the first byte to tell an array from one record per line
+    async fn open(path: &str) -> RecipeResult<RecordStream> {
+        let mut reader = BufReader::new(File::open(path).await?);
+        let array = loop {
+            let buf = reader.fill_buf().await?;
+            match buf.first() {
+                Some(b) if b.is_ascii_whitespace() => reader.consume(1),
+                Some(b'[') => {
+                    reader.consume(1);
+                    break true;
+                }
+                _ => break false,
+            }
+        };
+        Ok(RecordStream { reader, array, done: false })
+    }
+
+    //the next record, None once the file is done
+    async fn next(&mut self) -> RecipeResult<Option<Vec<u8>>> {
+        if self.done {
+            return Ok(None);
+        }
+        if !self.array {
+            return self.next_line().await;
+        }
+        let mut record = Vec::new();
+        //brackets are only counted outside of strings
+        let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+        loop {
+            let buf = self.reader.fill_buf().await?;
+            if buf.is_empty() {
+                self.done = true;
+                return Err(RecipeError::Validation("the json array is never closed".to_owned()));
+            }
+            let mut used = 0;
+            let mut end_of_array = None;
+            for &b in buf {
+                used += 1;
+                if in_string {
+                    record.push(b);
+                    match b {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => in_string = false,
+                        _ => {}
+                    }
+                    continue;
+                }
+                match b {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' if depth > 0 => depth -= 1,
+                    b']' => {
+                        end_of_array = Some(true);
+                        break;
+                    }
+                    b',' if depth == 0 => {
+                        end_of_array = Some(false);
+                        break;
+                    }
+                    _ => {}
+                }
+                record.push(b);
+            }
+            self.reader.consume(used);
+            if let Some(end) = end_of_array {
+                self.done = end;
+                //an empty array has no record before its closing bracket
+                if end && record.iter().all(|b| b.is_ascii_whitespace()) {
+                    return Ok(None);
+                }
+                return Ok(Some(record));
+            }
+        }
+    }
+
+    //the next non-blank line
+    async fn next_line(&mut self) -> RecipeResult<Option<Vec<u8>>> {
+        loop {
+            let mut line = Vec::new();
+            if self.reader.read_until(b'\n', &mut line).await? == 0 {
+                self.done = true;
+                return Ok(None);
+            }
+            if !line.iter().all(|b| b.is_ascii_whitespace()) {
+                return Ok(Some(line));
+            }
+        }
+    }
+}
+
+//checks every record against the schema, records that arent valid json are left for the import to skip
+async fn schema_problems(path: &str) -> RecipeResult<Vec<String>> {
+    let mut records = RecordStream::open(path).await?;
+    let mut problems = Vec::new();
+    let mut index = 0;
+    while let Some(record) = records.next().await? {
+        if let Ok(value) = serde_json::from_slice::<serde_json::Value>(&record) {
+            problems.extend(schema::validate_record(&value, index));
+        }
+        index += 1;
+    }
+    Ok(problems)
+}
+
+//imports a json array of recipes, or one recipe per line, reading and adding them a batch at a time
+//the records of a batch are checked on import_workers threads, failures keep the index of their record
+//with validate the whole file is checked against the schema first and nothing is written if it fails
+//records that arent valid json or fail to deserialize or validate are skipped on their own
+pub async fn import_json(path: &str, validate: bool) -> RecipeResult<ImportReport> {
+    if validate {
+        let problems = schema_problems(path).await?;
+        if !problems.is_empty() {
+            return Ok(ImportReport {
+                imported: Vec::new(),
+                failures: Vec::new(),
+                schema_errors: problems,
+            });
+        }
+    }
+    let config = config::get()?;
+    let workers = if config.import_workers == 0 { workers::available() } else { config.import_workers };
+    let mut records = RecordStream::open(path).await?;
+    let mut imported = Vec::new();
+    let mut failures = Vec::new();
+    let mut batch = Vec::new();
+    let mut index = 0;
+    loop {
+        let record = records.next().await?;
+        let done = record.is_none();
+        if let Some(record) = record {
+            batch.push((index, record));
+            index += 1;
+        }
+        if batch.len() < IMPORT_BATCH && !done {
+            continue;
+        }
+        //records are parsed and validated on the workers, then added in file order so ids dont depend on the workers
+        let max_bytes = config.max_recipe_bytes;
+        let records_read = std::mem::take(&mut batch);
+        let checked = tokio::task::spawn_blocking(move || {
+            workers::map_ordered(records_read, workers, |(index, record)| (index, parse_record(&record, max_bytes)))
+        })
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        let mut drafts = Vec::new();
+        for (index, parsed) in checked {
+            match parsed {
+                Ok(recipe) => drafts.push(recipe),
+                Err(e) => failures.push(format!("recipe[{}]: {}", index, e)),
+            }
+        }
+        imported.extend(add_recipes(drafts).await?);
+        if done {
+            break;
+        }
+        if index % PROGRESS_EVERY == 0 {
+            info!("Read {} records from {}, {} imported and {} skipped so far", index, path, imported.len(), failures.len());
+        }
+    }
+    Ok(ImportReport {
+        imported,
+        failures,
+        schema_errors: Vec::new(),
+    })
+}
+
+//how merging another storage file went, nothing is written on a dry run
+#[derive(Default)]
+pub struct MergeReport {
+    //recipes added under the id they had in the other file
+    pub added: Vec<Recipe>,
+    //recipes that replaced an older local recipe with the same id, as they are after the merge
+    pub updated: Vec<Recipe>,
+    //recipes whose id was taken by a different local recipe, both are kept and these got a new id
+    pub renumbered: Vec<(usize, Recipe)>,
+    //recipes the active collection already has, under any id
+    pub duplicates: Vec<Recipe>,
+    //recipes the active collection has a newer copy of under the same id
+    pub outdated: Vec<Recipe>,
+    pub failures: Vec<String>,
+}
+
+//what a recipe holds, leaving out its id, who it is shared with and when it changed so the same recipe kept on two machines compares equal
+//updated_at is cleared since each machine stamps its own copy when it writes it
+pub fn content_key(recipe: &Recipe) -> RecipeResult<String> {
+    let mut content = recipe.clone();
+    content.id = 0;
+    content.public = false;
+    content.archived = false;
+    content.topics.clear();
+    content.denied_peers.clear();
+    content.updated_at = None;
+    Ok(serde_json::to_string(&content)?)
+}
+
+//merges the recipes of another storage file, of any schema version, into the active collection in one write
+//merged recipes start out private, same as imports
+pub async fn merge_file(path: &str, dry_run: bool) -> RecipeResult<MergeReport> {
+    let (_, incoming) = migrate::parse(&tokio::fs::read(path).await?)?;
+    let mut local_recipes = read_local_recipes().await?;
+    let report = merge(&mut local_recipes, incoming)?;
+    let changed = !report.added.is_empty() || !report.updated.is_empty() || !report.renumbered.is_empty();
+    if changed && !dry_run {
+        write_local_recipes(&local_recipes).await?;
+    }
+    Ok(report)
+}
+
+//merges incoming recipes into local ones, the same content under any id is only kept once
+//on the same id the one with the later updated_at wins, a replaced recipe keeps who the local one was shared with
+//when either of them has no updated_at there is no telling which is newer, so both are kept and the incoming one gets a new id
+fn merge(local_recipes: &mut Vec<Recipe>, incoming: Vec<Recipe>) -> RecipeResult<MergeReport> {
+    let mut seen = local_recipes.iter().map(content_key).collect::<RecipeResult<HashSet<String>>>()?;
+    let mut taken: HashSet<usize> = local_recipes.iter().map(|r| r.id).collect();
+    let mut report = MergeReport::default();
+    let mut colliding = Vec::new();
+    for (index, mut recipe) in incoming.into_iter().enumerate() {
+        recipe.public = false;
+        if let Err(e) = validate_recipe(&recipe) {
+            report.failures.push(format!("recipe[{}] {:?}: {}", index, recipe.name, e));
+            continue;
+        }
+        if !seen.insert(content_key(&recipe)?) {
+            report.duplicates.push(recipe);
+        } else if taken.insert(recipe.id) {
+            local_recipes.push(recipe.clone());
+            report.added.push(recipe);
+        } else {
+            //an id taken by a recipe added from this file earlier on isnt a local one, that is renumbered too
+            let local = local_recipes.iter_mut().find(|r| r.id == recipe.id && !report.added.iter().any(|a| a.id == r.id));
+            match (local, recipe.updated_at) {
+                (Some(local), Some(updated_at)) if local.updated_at.is_some_and(|at| updated_at > at) => {
+                    recipe.public = local.public;
+                    recipe.topics = local.topics.clone();
+                    recipe.denied_peers = local.denied_peers.clone();
+                    *local = recipe.clone();
+                    report.updated.push(recipe);
+                }
+                (Some(local), Some(_)) if local.updated_at.is_some() => report.outdated.push(recipe),
+                _ => colliding.push(recipe),
+            }
+        }
+    }
+    //new ids come after every id kept, so none of them can clash with a recipe added later in the file
+    let first_id = taken.iter().map(|id| id + 1).max().unwrap_or(0);
+    for (id, mut recipe) in (first_id..).zip(colliding) {
+        let old_id = std::mem::replace(&mut recipe.id, id);
+        local_recipes.push(recipe.clone());
+        report.renumbered.push((old_id, recipe));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(id: usize, name: &str, updated_at: Option<u64>) -> Recipe {
+        let mut recipe = crate::recipe::parse_recipe_args(&format!("{}|flour, water|bake", name)).unwrap();
+        recipe.id = id;
+        recipe.updated_at = updated_at;
+        recipe
+    }
+
+    fn names(recipes: &[Recipe]) -> Vec<(usize, &str)> {
+        recipes.iter().map(|r| (r.id, r.name.as_str())).collect()
+    }
+
+    #[test]
+    fn the_newer_copy_of_a_recipe_wins() {
+        let mut shared = recipe(0, "Bread", Some(100));
+        shared.public = true;
+        let mut local = vec![shared, recipe(1, "Soup", Some(300))];
+        let report = merge(&mut local, vec![recipe(0, "Rye bread", Some(200)), recipe(1, "Old soup", Some(200))]).unwrap();
+        assert_eq!(names(&report.updated), [(0, "Rye bread")]);
+        assert_eq!(names(&report.outdated), [(1, "Old soup")]);
+        assert_eq!(names(&local), [(0, "Rye bread"), (1, "Soup")]);
+        //a replaced recipe stays shared the way the local one was
+        assert!(local[0].public);
+        assert_eq!(local[0].updated_at, Some(200));
+    }
+
+    #[test]
+    fn undated_id_clashes_keep_both_recipes() {
+        let mut local = vec![recipe(0, "Bread", None), recipe(1, "Soup", Some(100))];
+        let report = merge(&mut local, vec![recipe(0, "Cake", Some(50)), recipe(1, "Stew", None), recipe(2, "Pie", None)]).unwrap();
+        assert_eq!(names(&report.added), [(2, "Pie")]);
+        assert_eq!(report.renumbered.iter().map(|(old, r)| (*old, r.id, r.name.as_str())).collect::<Vec<_>>(), [(0, 3, "Cake"), (1, 4, "Stew")]);
+        assert!(report.updated.is_empty() && report.outdated.is_empty());
+    }
+
+    #[test]
+    fn the_same_content_is_a_duplicate_whenever_it_changed() {
+        let mut local = vec![recipe(0, "Bread", Some(100))];
+        let report = merge(&mut local, vec![recipe(5, "Bread", Some(900))]).unwrap();
+        assert_eq!(names(&report.duplicates), [(5, "Bread")]);
+        assert_eq!(names(&local), [(0, "Bread")]);
+    }
+
+    #[test]
+    fn content_keys_ignore_when_a_recipe_changed() {
+        let (early, late, undated) = (recipe(0, "Bread", Some(100)), recipe(0, "Bread", Some(900)), recipe(0, "Bread", None));
+        assert_eq!(content_key(&early).unwrap(), content_key(&late).unwrap());
+        assert_eq!(content_key(&early).unwrap(), content_key(&undated).unwrap());
+    }
+}
@@ -0,0 +1,418 @@
+use crate::error::{RecipeError, RecipeResult};
+use serde::{Deserialize, Serialize};
+
+//units recognised when parsing an ingredient line, anything else is treated as part of the name
+const UNITS: &[&str] = &[
+    "g", "kg", "mg", "ml", "l", "tsp", "tbsp", "cup", "cups", "oz", "lb", "lbs", "pinch", "clove", "cloves",
+    "slice", "slices", "can", "cans",
+];
+
+//unicode fractions recipes are written with and their values
+const FRACTIONS: &[(char, f64)] = &[
+    ('½', 1.0 / 2.0),
+    ('⅓', 1.0 / 3.0),
+    ('⅔', 2.0 / 3.0),
+    ('¼', 1.0 / 4.0),
+    ('¾', 3.0 / 4.0),
+    ('⅕', 1.0 / 5.0),
+    ('⅖', 2.0 / 5.0),
+    ('⅗', 3.0 / 5.0),
+    ('⅘', 4.0 / 5.0),
+    ('⅙', 1.0 / 6.0),
+    ('⅚', 5.0 / 6.0),
+    ('⅛', 1.0 / 8.0),
+    ('⅜', 3.0 / 8.0),
+    ('⅝', 5.0 / 8.0),
+    ('⅞', 7.0 / 8.0),
+];
+
+//words between the two ends of a range written with spaces, "2 - 3" or "2 to 3"
+const RANGE_WORDS: &[&str] = &["-", "–", "to"];
+
+//a single ingredient with its amount split out so it can be searched and scaled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ingredient {
+    pub name: String,
+    //the low end for a range like "2-3 eggs", so peers that dont know ranges still read a quantity
+    #[serde(default)]
+    pub quantity: Option<f64>,
+    //the high end of a range, None for a single quantity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity_max: Option<f64>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    //section of the recipe the ingredient belongs to, e.g. "For the dough", recipes without sections have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+//consecutive ingredients of the same section, ingredients outside any section form an unnamed group
+#[derive(Debug)]
+pub struct IngredientGroup<'a> {
+    pub name: Option<&'a str>,
+    pub items: Vec<&'a Ingredient>,
+}
+
+//the ingredients split into their sections in the order they were written, a flat list is one unnamed group
+pub fn groups(ingredients: &[Ingredient]) -> Vec<IngredientGroup<'_>> {
+    let mut groups: Vec<IngredientGroup> = Vec::new();
+    for ingredient in ingredients {
+        let name = ingredient.group.as_deref();
+        match groups.last_mut() {
+            Some(group) if group.name == name => group.items.push(ingredient),
+            _ => groups.push(IngredientGroup { name, items: vec![ingredient] }),
+        }
+    }
+    groups
+}
+
+//parses one ingredient like "2 cups flour", "1 1/2 cups milk", "½ tsp salt", "2-3 eggs" or "salt"
+pub fn parse_ingredient(line: &str) -> Option<Ingredient> {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+    let (quantity, quantity_max) = match parse_quantity(&words) {
+        Some((low, high, used)) => {
+            words.drain(..used);
+            (Some(low), high)
+        }
+        None => (None, None),
+    };
+    let unit = match words.first() {
+        //a unit is only a unit if something is left over to be the name
+        Some(w) if words.len() > 1 && UNITS.contains(&w.to_lowercase().as_str()) => Some(words.remove(0).to_lowercase()),
+        _ => None,
+    };
+    if words.is_empty() {
+        return None;
+    }
+    Some(Ingredient {
+        name: words.join(" "),
+        quantity,
+        quantity_max,
+        unit,
+        group: None,
+    })
+}
+
+//the quantity the words start with, its high end if it is a range and how many words it took
+//"1 1/2", "1½", "½", "0.5", "2-3", "1/2-1" and "2 to 3" are understood, a range whose ends are the wrong way round isnt a quantity
+fn parse_quantity(words: &[&str]) -> Option<(f64, Option<f64>, usize)> {
+    //both ends in one word, a leading minus isnt a range
+    if let Some((low, high)) = words[0].char_indices().skip(1).find(|(_, c)| *c == '-' || *c == '–').map(|(i, c)| (&words[0][..i], &words[0][i + c.len_utf8()..])) {
+        let (low, high) = (parse_number(low)?, parse_number(high)?);
+        return range(low, high, 1);
+    }
+    let (low, used) = parse_amount(words)?;
+    if words.get(used).is_some_and(|w| RANGE_WORDS.contains(w)) {
+        if let Some((high, more)) = parse_amount(&words[used + 1..]) {
+            return range(low, high, used + 1 + more);
+        }
+    }
+    Some((low, None, used))
+}
+
+fn range(low: f64, high: f64, used: usize) -> Option<(f64, Option<f64>, usize)> {
+    match low.partial_cmp(&high)? {
+        std::cmp::Ordering::Less => Some((low, Some(high), used)),
+        std::cmp::Ordering::Equal => Some((low, None, used)),
+        std::cmp::Ordering::Greater => None,
+    }
+}
+
+//a number with the fraction the next word may add to it, "1 1/2" is one and a half and takes two words
+fn parse_amount(words: &[&str]) -> Option<(f64, usize)> {
+    let first = words.first()?;
+    let whole = parse_number(first)?;
+    let fraction = words.get(1).filter(|_| first.chars().all(|c| c.is_ascii_digit())).and_then(|w| parse_fraction(w));
+    match fraction {
+        Some(fraction) => Some((whole + fraction, 2)),
+        None => Some((whole, 1)),
+    }
+}
+
+//a plain or decimal number, a fraction like "1/2" or "½", or a whole number with a unicode fraction after it like "1½"
+fn parse_number(word: &str) -> Option<f64> {
+    let number = match word.parse::<f64>() {
+        Ok(number) => number,
+        Err(_) => match word.chars().last().and_then(unicode_fraction) {
+            Some(fraction) => {
+                let whole = &word[..word.len() - word.chars().last()?.len_utf8()];
+                match whole {
+                    "" => fraction,
+                    whole if whole.chars().all(|c| c.is_ascii_digit()) => whole.parse::<f64>().ok()? + fraction,
+                    _ => return None,
+                }
+            }
+            None => slash_fraction(word)?,
+        },
+    };
+    (number.is_finite() && number >= 0.0).then_some(number)
+}
+
+//a fraction below one that can follow a whole number, "1/2" or "½"
+fn parse_fraction(word: &str) -> Option<f64> {
+    let mut chars = word.chars();
+    let fraction = match (chars.next(), chars.next()) {
+        (Some(c), None) => unicode_fraction(c)?,
+        _ => slash_fraction(word)?,
+    };
+    (fraction < 1.0).then_some(fraction)
+}
+
+fn unicode_fraction(c: char) -> Option<f64> {
+    FRACTIONS.iter().find(|(f, _)| *f == c).map(|(_, value)| *value)
+}
+
+fn slash_fraction(word: &str) -> Option<f64> {
+    let (numerator, denominator) = word.split_once('/')?;
+    let (numerator, denominator) = (numerator.parse::<u32>().ok()?, denominator.parse::<u32>().ok()?);
+    (denominator > 0).then(|| f64::from(numerator) / f64::from(denominator))
+}
+
+//parses a comma separated ingredients argument into structured ingredients
+//sections are separated by semicolons and named before a colon, e.g. "For the dough: 500 g flour, 7 g yeast; For the filling: 200 g cheese"
+pub fn parse_ingredients(list: &str) -> Vec<Ingredient> {
+    let mut ingredients = Vec::new();
+    for section in list.split(';') {
+        let (group, items) = match section.split_once(':') {
+            Some((name, items)) if !name.contains(',') => (Some(name.trim().to_owned()).filter(|n| !n.is_empty()), items),
+            _ => (None, section),
+        };
+        ingredients.extend(items.split(',').filter_map(parse_ingredient).map(|i| Ingredient { group: group.clone(), ..i }));
+    }
+    ingredients
+}
+
+impl std::fmt::Display for Ingredient {
+    //writes the ingredient back in the "2 cups flour" form it was parsed from
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(quantity) = self.quantity_text() {
+            write!(f, "{} ", quantity)?;
+        }
+        if let Some(unit) = &self.unit {
+            write!(f, "{} ", unit)?;
+        }
+        write!(f, "{}", self.name)
+    }
+}
+
+//what a recipe makes when that is a weight or volume rather than a number of servings, e.g. 1 kg of dough
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Yield {
+    pub amount: f64,
+    pub unit: String,
+}
+
+impl std::fmt::Display for Yield {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.amount, self.unit)
+    }
+}
+
+//units a yield can be converted between, along with their size in the smallest unit of their kind
+const YIELD_UNITS: &[(&str, &str, f64)] = &[
+    ("mg", "weight", 0.001),
+    ("g", "weight", 1.0),
+    ("kg", "weight", 1000.0),
+    ("ml", "volume", 1.0),
+    ("l", "volume", 1000.0),
+];
+
+//parses a yield like "1 kg", "500g" or "12 rolls", units outside the conversion table only scale against themselves
+pub fn parse_yield(text: &str) -> RecipeResult<Yield> {
+    let text = text.trim();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let amount = amount
+        .parse::<f64>()
+        .ok()
+        .filter(|a| *a > 0.0)
+        .ok_or_else(|| RecipeError::Validation(format!("invalid yield {:?} - Format: <amount> <unit>, e.g. 1 kg", text)))?;
+    let unit = unit.trim().to_lowercase();
+    if unit.is_empty() {
+        return Err(RecipeError::Validation(format!("yield {:?} is missing a unit, use servings for plain counts", text)));
+    }
+    Ok(Yield { amount, unit })
+}
+
+//how much a recipe yielding from has to be multiplied by to yield to, converting between units of the same kind
+pub fn yield_factor(from: &Yield, to: &Yield) -> RecipeResult<f64> {
+    if from.unit == to.unit {
+        return Ok(to.amount / from.amount);
+    }
+    let lookup = |unit: &str| YIELD_UNITS.iter().find(|(u, _, _)| *u == unit);
+    match (lookup(&from.unit), lookup(&to.unit)) {
+        (Some((_, from_kind, from_size)), Some((_, to_kind, to_size))) if from_kind == to_kind => {
+            Ok((to.amount * to_size) / (from.amount * from_size))
+        }
+        _ => Err(RecipeError::Validation(format!(
+            "recipe yields {} which cant be scaled to {}",
+            from, to
+        ))),
+    }
+}
+
+impl Ingredient {
+    //the same ingredient with its quantity multiplied, rounded to two decimals so output stays readable
+    //both ends of a range are scaled, "2-3 eggs" doubled is "4-6 eggs"
+    pub fn scaled(&self, factor: f64) -> Ingredient {
+        let scale = |q: f64| (q * factor * 100.0).round() / 100.0;
+        Ingredient {
+            quantity: self.quantity.map(scale),
+            quantity_max: self.quantity_max.map(scale),
+            ..self.clone()
+        }
+    }
+
+    //the quantity totals and nutrition are worked out from, the middle of a range so "2-3 eggs" counts as 2.5
+    pub fn typical_quantity(&self) -> Option<f64> {
+        let low = self.quantity?;
+        Some(self.quantity_max.map_or(low, |high| (low + high) / 2.0))
+    }
+
+    //"1.5", or "2-3" for a range
+    pub fn quantity_text(&self) -> Option<String> {
+        let low = self.quantity?;
+        Some(match self.quantity_max {
+            Some(high) => format!("{}-{}", low, high),
+            None => low.to_string(),
+        })
+    }
+}
+
+//an ingredient that can be swapped for another, e.g. butter -> olive oil
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Substitution {
+    pub original: String,
+    pub alternative: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl std::fmt::Display for Substitution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {}", self.original, self.alternative)?;
+        if let Some(notes) = &self.notes {
+            write!(f, " ({})", notes)?;
+        }
+        Ok(())
+    }
+}
+
+//parses semicolon separated substitutions like "butter -> olive oil (use less); milk -> oat milk"
+pub fn parse_substitutions(list: &str) -> RecipeResult<Vec<Substitution>> {
+    list.split(';')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            let invalid = || RecipeError::Validation(format!("invalid substitution {:?} - Format: original -> alternative [(notes)]", entry));
+            let (original, rest) = entry.split_once("->").ok_or_else(invalid)?;
+            let (alternative, notes) = match rest.trim().strip_suffix(')').and_then(|r| r.split_once('(')) {
+                Some((alternative, notes)) => (alternative, Some(notes.trim().to_owned()).filter(|n| !n.is_empty())),
+                None => (rest, None),
+            };
+            let (original, alternative) = (original.trim(), alternative.trim());
+            if original.is_empty() || alternative.is_empty() {
+                return Err(invalid());
+            }
+            Ok(Substitution {
+                original: original.to_owned(),
+                alternative: alternative.to_owned(),
+                notes,
+            })
+        })
+        .collect()
+}
+
+//how often one ingredient is used across recipes, quantities are only summed within the same unit
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngredientUsage {
+    pub name: String,
+    pub unit: Option<String>,
+    //times it appears, an ingredient listed twice in one recipe counts twice
+    pub uses: usize,
+    //sum of the quantities given with ranges counted at their middle, None if no recipe gave one
+    pub total: Option<f64>,
+}
+
+//tallies ingredients by name and unit, most used first and ties by name
+//names are matched ignoring case so "Flour" and "flour" count together
+pub fn usage<'a>(lists: impl Iterator<Item = &'a [Ingredient]>) -> Vec<IngredientUsage> {
+    let mut tally: Vec<IngredientUsage> = Vec::new();
+    for ingredient in lists.flatten() {
+        let name = ingredient.name.to_lowercase();
+        match tally.iter_mut().find(|u| u.name == name && u.unit == ingredient.unit) {
+            Some(entry) => {
+                entry.uses += 1;
+                if let Some(quantity) = ingredient.typical_quantity() {
+                    entry.total = Some(entry.total.unwrap_or(0.0) + quantity);
+                }
+            }
+            None => tally.push(IngredientUsage {
+                name,
+                unit: ingredient.unit.clone(),
+                uses: 1,
+                total: ingredient.typical_quantity(),
+            }),
+        }
+    }
+    tally.sort_by(|a, b| b.uses.cmp(&a.uses).then_with(|| a.name.cmp(&b.name)));
+    tally
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //quantity, high end of a range, unit and name of a parsed line
+    fn parsed(line: &str) -> (Option<f64>, Option<f64>, Option<String>, String) {
+        let i = parse_ingredient(line).unwrap();
+        (i.quantity, i.quantity_max, i.unit, i.name)
+    }
+
+    #[test]
+    fn mixed_numbers_unicode_fractions_and_decimals_are_quantities() {
+        assert_eq!(parsed("1 1/2 cups milk"), (Some(1.5), None, Some("cups".to_owned()), "milk".to_owned()));
+        assert_eq!(parsed("½ tsp salt"), (Some(0.5), None, Some("tsp".to_owned()), "salt".to_owned()));
+        assert_eq!(parsed("1½ cups flour"), (Some(1.5), None, Some("cups".to_owned()), "flour".to_owned()));
+        assert_eq!(parsed("0.25 l water"), (Some(0.25), None, Some("l".to_owned()), "water".to_owned()));
+        assert_eq!(parsed("3/4 cup sugar").0, Some(0.75));
+    }
+
+    #[test]
+    fn ranges_keep_both_ends() {
+        assert_eq!(parsed("2-3 eggs"), (Some(2.0), Some(3.0), None, "eggs".to_owned()));
+        assert_eq!(parsed("2 to 3 eggs"), (Some(2.0), Some(3.0), None, "eggs".to_owned()));
+        assert_eq!(parsed("1/2-1 tsp chili"), (Some(0.5), Some(1.0), Some("tsp".to_owned()), "chili".to_owned()));
+        //ends the wrong way round arent a quantity, so they stay part of the name
+        assert_eq!(parsed("3-2 eggs"), (None, None, None, "3-2 eggs".to_owned()));
+    }
+
+    #[test]
+    fn ranges_scale_at_both_ends_and_total_at_their_middle() {
+        let eggs = parse_ingredient("2-3 eggs").unwrap();
+        assert_eq!(eggs.typical_quantity(), Some(2.5));
+        assert_eq!(eggs.scaled(2.0).quantity_text().as_deref(), Some("4-6"));
+        assert_eq!(parse_ingredient("salt").unwrap().typical_quantity(), None);
+    }
+
+    #[test]
+    fn lines_without_a_name_are_not_ingredients() {
+        assert!(parse_ingredient("2 cups").is_some_and(|i| i.unit.is_none() && i.name == "cups"));
+        assert!(parse_ingredient("2").is_none());
+        assert!(parse_ingredient("   ").is_none());
+    }
+
+    #[test]
+    fn yields_convert_between_units_of_the_same_kind() {
+        let kilo = parse_yield("1 kg").unwrap();
+        assert_eq!(kilo, Yield { amount: 1.0, unit: "kg".to_owned() });
+        assert_eq!(yield_factor(&kilo, &parse_yield("500g").unwrap()).unwrap(), 0.5);
+        assert_eq!(yield_factor(&parse_yield("12 rolls").unwrap(), &parse_yield("18 rolls").unwrap()).unwrap(), 1.5);
+        assert!(yield_factor(&kilo, &parse_yield("1 l").unwrap()).is_err());
+        assert!(parse_yield("0 kg").is_err());
+        assert!(parse_yield("12").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use rand::Rng;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
+
+//returns the base duration moved randomly by up to percent of itself in either direction
+pub fn jittered(base: Duration, percent: u8) -> Duration {
+    let spread = f64::from(percent.min(100)) / 100.0;
+    if spread == 0.0 {
+        return base;
+    }
+    let factor = 1.0 + rand::thread_rng().gen_range(-spread..=spread);
+    base.mul_f64(factor)
+}
+
+//a periodic timer whose every period is jittered so nodes started together drift apart instead of firing in step
+pub struct JitteredInterval {
+    base: Duration,
+    percent: u8,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl JitteredInterval {
+    pub fn new(base: Duration, percent: u8) -> JitteredInterval {
+        let sleep = Box::pin(tokio::time::sleep(jittered(base, percent)));
+        JitteredInterval { base, percent, sleep }
+    }
+
+    //waits for the current period to end and starts the next one, safe to use in select
+    pub async fn tick(&mut self) {
+        self.sleep.as_mut().await;
+        let next = jittered(self.base, self.percent);
+        self.sleep.as_mut().reset(Instant::now() + next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn jittered_periods_stay_within_the_configured_percentage() {
+        let (low, high) = (BASE.mul_f64(0.8), BASE.mul_f64(1.2));
+        let periods: Vec<Duration> = (0..1000).map(|_| jittered(BASE, 20)).collect();
+        assert!(periods.iter().all(|p| (low..=high).contains(p)), "a period left {:?}..={:?}", low, high);
+        //the point is to spread nodes out, so the periods must not all come out the same
+        assert!(periods.iter().any(|p| *p != periods[0]));
+    }
+
+    #[test]
+    fn no_jitter_keeps_the_base_period() {
+        assert!((0..100).all(|_| jittered(BASE, 0) == BASE));
+    }
+
+    #[test]
+    fn percentages_over_100_are_capped() {
+        assert!((0..1000).all(|_| jittered(BASE, u8::MAX) <= BASE * 2));
+    }
+
+    #[tokio::test]
+    async fn ticks_wait_at_least_the_low_end_of_the_period() {
+        let base = Duration::from_millis(40);
+        let mut interval = JitteredInterval::new(base, 50);
+        for _ in 0..3 {
+            let started = Instant::now();
+            interval.tick().await;
+            assert!(started.elapsed() >= base / 2);
+        }
+    }
+}
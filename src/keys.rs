@@ -0,0 +1,44 @@
+use crate::error::{RecipeError, RecipeResult};
+use libp2p::identity::{ed25519, Keypair};
+
+//env var holding a hex encoded 32 byte seed, used when no --peer-id-from file is given
+const SEED_ENV_VAR: &str = "PEER_ID_SEED";
+
+//the node keypair, derived from a seed when one is given so the peer id is the same on every run
+//a seed comes from --peer-id-from <file> (32 raw bytes or 64 hex characters) or from PEER_ID_SEED
+pub fn load_keypair() -> RecipeResult<Keypair> {
+    let args: Vec<String> = std::env::args().collect();
+    let seed = match args.iter().position(|a| a == "--peer-id-from") {
+        Some(i) => {
+            let path = args
+                .get(i + 1)
+                .ok_or_else(|| RecipeError::Validation("missing file - Format: --peer-id-from <file>".to_owned()))?;
+            let content = std::fs::read(path)
+                .map_err(|e| RecipeError::Validation(format!("cannot read seed file {}: {}", path, e)))?;
+            parse_seed(&content).map_err(|e| RecipeError::Validation(format!("invalid seed in {}: {}", path, e)))?
+        }
+        None => match std::env::var(SEED_ENV_VAR) {
+            Ok(hex) => parse_seed(hex.as_bytes()).map_err(|e| RecipeError::Validation(format!("invalid {}: {}", SEED_ENV_VAR, e)))?,
+            Err(_) => return Ok(Keypair::generate_ed25519()),
+        },
+    };
+    let secret = ed25519::SecretKey::from_bytes(seed).map_err(|e| RecipeError::Validation(format!("invalid seed: {}", e)))?;
+    Ok(Keypair::Ed25519(secret.into()))
+}
+
+//accepts the seed either as 32 raw bytes or as 64 hex characters, surrounding whitespace is ignored for hex
+fn parse_seed(content: &[u8]) -> Result<[u8; 32], String> {
+    if let Ok(raw) = <[u8; 32]>::try_from(content) {
+        return Ok(raw);
+    }
+    let hex = std::str::from_utf8(content).map_err(|_| "expected 32 bytes or 64 hex characters".to_owned())?.trim();
+    if !hex.is_ascii() || hex.len() != 64 {
+        return Err(format!("expected 32 bytes or 64 hex characters, found {} characters", hex.chars().count()));
+    }
+    let mut seed = [0u8; 32];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("{:?} is not a hex byte", &hex[i * 2..i * 2 + 2]))?;
+    }
+    Ok(seed)
+}
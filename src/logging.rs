@@ -0,0 +1,48 @@
+use crate::config::Config;
+use libp2p::PeerId;
+use log::Level;
+use pretty_env_logger::env_logger::WriteStyle;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//target network events are logged under, so they can be filtered with RUST_LOG=P2PRecipe::network=debug
+const NETWORK_TARGET: &str = "P2PRecipe::network";
+
+//whether output should be plain, --no-color or a non-empty NO_COLOR turn color off (https://no-color.org)
+pub fn color_disabled(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--no-color") || std::env::var("NO_COLOR").is_ok_and(|v| !v.is_empty())
+}
+
+//sets up the logger from the config, RUST_LOG overrides the configured level when it is set
+//every line carries a timestamp in both formats, colors are only used when stderr is a terminal and not disabled
+//cards, tables and every other output are plain ascii already so the logger is the only thing to turn color off in
+pub fn init(config: &Config, no_color: bool) {
+    let mut builder = pretty_env_logger::formatted_timed_builder();
+    builder.write_style(if no_color { WriteStyle::Never } else { WriteStyle::Auto });
+    builder.parse_filters(&config.log_level);
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    //one json object per line for log collectors
+    if config.log_format == "json" {
+        builder.format(|buf, record| {
+            let ts = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let line = serde_json::json!({
+                "ts": ts,
+                "level": record.level().to_string(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+    builder.init();
+}
+
+//logs something a peer did as "[event] peer=<peer id> details" so events from one peer or of one kind can be grepped for
+pub fn network_event(level: Level, event: &str, peer: &PeerId, details: std::fmt::Arguments) {
+    log::log!(target: NETWORK_TARGET, level, "[{}] peer={} {}", event, peer, details);
+}
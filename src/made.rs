@@ -0,0 +1,59 @@
+use crate::error::RecipeResult;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+//file path for how often recipes were made, kept apart from the recipes so cached ones can be counted too
+const MADE_FILE_PATH: &str = "./made.json";
+
+//how often the user made somebody's recipe, never shared with peers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MadeCount {
+    //peer id of the recipe's author
+    pub author: String,
+    pub recipe_id: usize,
+    pub times_made: u32,
+    //seconds since the unix epoch
+    pub last_made: u64,
+}
+
+//logic for reading counts, no file yet just means nothing was made yet
+async fn read_counts() -> RecipeResult<Vec<MadeCount>> {
+    match fs::read(MADE_FILE_PATH).await {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//counts one more time the recipe was made and returns the updated count
+pub async fn record_made(author: &str, recipe_id: usize) -> RecipeResult<MadeCount> {
+    let mut counts = read_counts().await?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let index = match counts.iter().position(|c| c.author == author && c.recipe_id == recipe_id) {
+        Some(index) => index,
+        None => {
+            counts.push(MadeCount {
+                author: author.to_owned(),
+                recipe_id,
+                times_made: 0,
+                last_made: now,
+            });
+            counts.len() - 1
+        }
+    };
+    let count = &mut counts[index];
+    count.times_made += 1;
+    count.last_made = now;
+    let updated = count.clone();
+    fs::write(MADE_FILE_PATH, serde_json::to_string(&counts)?).await?;
+    Ok(updated)
+}
+
+//every count for one author's recipes
+pub async fn counts_for(author: &str) -> RecipeResult<Vec<MadeCount>> {
+    Ok(read_counts().await?.into_iter().filter(|c| c.author == author).collect())
+}
@@ -1,100 +1,573 @@
 extern crate core;
 
+mod archive;
+mod audit;
+mod broadcast;
+mod cache;
+mod card;
+mod checksum;
+mod config;
+mod cook;
+mod daemon;
+mod dedup;
+mod dialer;
+mod digest;
+mod diff;
+mod discovery;
+mod doctor;
+mod error;
+mod export;
+mod fetch;
+mod graph;
+mod images;
+mod import;
+mod ingredients;
+mod jitter;
+mod keys;
+mod logging;
+mod made;
+mod manifests;
+mod messages;
+mod migrate;
+mod notes;
+mod nutrition;
+mod patch;
+mod prompt;
+mod readiness;
+mod recipe;
+mod requests;
+mod schema;
+mod seed;
+mod since;
+mod storage;
+mod table;
+mod undo;
+mod validation;
+mod view;
+mod watchdog;
+mod wire;
+mod workers;
+#[cfg(feature = "http")]
+mod web;
+
 //dependencies
 use libp2p::{
-    core::upgrade,
+    core::{connection::ListenerId, upgrade},
     floodsub::{Floodsub, FloodsubEvent, Topic},
     futures::StreamExt,
+    identify::{Identify, IdentifyConfig, IdentifyEvent, IdentifyInfo},
     identity,
     mdns::{Mdns, MdnsEvent},
     mplex,
+    multiaddr::Protocol,
     noise::{Keypair, X25519Spec},
-    swarm::{NetworkBehaviourEventProcess, Swarm, SwarmBuilder},
+    ping::{Ping, PingConfig, PingEvent, PingSuccess},
+    relay::{self, Relay, RelayConfig},
+    request_response::{
+        ProtocolSupport, RequestId, RequestResponse, RequestResponseConfig, RequestResponseEvent, RequestResponseMessage,
+        ResponseChannel,
+    },
+    swarm::{toggle::Toggle, NetworkBehaviourEventProcess, Swarm, SwarmBuilder, SwarmEvent},
     tcp::TokioTcpConfig,
-    NetworkBehaviour, PeerId, Transport,
+    Multiaddr, NetworkBehaviour, PeerId, Transport,
 };
-use log::{error, info};
+use log::{error, info, warn, Level};
+use logging::network_event;
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, };
-use tokio::{fs, io::AsyncBufReadExt, sync::mpsc};
-
-//file path for recipes
-const STORAGE_FILE_PATH:&str = "./recipes.json";
-//creates Result type with box which doesnt use any heap memory if T is zero but allocates any variables onto the heap
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
-//generates keys
-static KEYS: Lazy<identity::Keypair> = Lazy::new(identity::Keypair::generate_ed25519);
+use serde::Serialize;
+use std::{borrow::Cow, collections::{HashMap, HashSet, VecDeque}, iter, sync::Arc, time::{Duration, Instant}};
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+use broadcast::BroadcastQueue;
+use dedup::DedupCache;
+use dialer::Dialer;
+use digest::{AvailabilityDigest, DigestIndex};
+use cache::PatchOutcome;
+use patch::RecipePatch;
+use discovery::{Discovery, DiscoveryEvent, MdnsDiscovery, StaticDiscovery};
+use error::{RecipeError, RecipeResult};
+use fetch::{FetchCodec, FetchProtocol, FetchRequest, FetchResponse};
+use ingredients::Yield;
+use jitter::JitteredInterval;
+use manifests::{Manifest, ManifestFetch};
+use messages::{ListMode, ListRequest, ListResponse, LocateQuery, LocateTarget, ManifestAnnouncement, NameMatch, NameMatches, NameQuery, RecipeAnnouncement};
+use readiness::Readiness;
+use recipe::{parse_recipe_args, split_list, unix_now, Recipe, Recipes, DEFAULT_TOPIC, RECIPE_ARGS_FORMAT};
+use requests::PendingRequests;
+use storage::{read_local_recipes, write_local_recipes};
+use wire::MessageKind;
+use view::{Admission, PeerView};
+use watchdog::{Watchdog, WatchdogAction};
+
+//generates keys, or derives them from a seed for a fixed peer id
+static KEYS: Lazy<identity::Keypair> = Lazy::new(|| match keys::load_keypair() {
+    Ok(keys) => keys,
+    Err(e) => {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+});
 //creates peer id
 static PEER_ID: Lazy<PeerId> = Lazy::new(|| PeerId::from(KEYS.public()));
+//the peer id as it appears in messages, kept so incoming ones can be checked against it without formatting it each time
+static PEER_ID_TEXT: Lazy<String> = Lazy::new(|| PEER_ID.to_string());
 //allows for subscriptions to specific peers??
-static TOPIC: Lazy<Topic> = Lazy::new(|| Topic::new("recipes"));
-//creates recipes type out of a list of the recipe type
-type Recipes = Vec<Recipe>;
+static TOPIC: Lazy<Topic> = Lazy::new(|| Topic::new(DEFAULT_TOPIC));
+//the transport only offers noise and mplex so these are always what a connection negotiates
+const SECURITY_PROTOCOL: &str = "/noise";
+const MUXER_PROTOCOL: &str = "/mplex/6.7.0";
+//wire protocol version announced through identify, peers with a different major version speak an incompatible format
+const PROTOCOL_VERSION: &str = "/recipes/1.0.0";
+//round trip times kept per peer for ls p
+const RECENT_PINGS: usize = 5;
+//announcements kept for when a peer is there to receive them, the oldest is dropped beyond this
+const MAX_HELD_ANNOUNCEMENTS: usize = 100;
 
-//defining structs
-#[derive(Debug, Serialize, Deserialize)]
-struct Recipe {
-    id: usize,
-    name: String,
-    ingredients: String,
-    instructions: String,
-    public: bool,
+//major version out of a protocol version like "/recipes/1.2.0"
+fn protocol_major(version: &str) -> Option<u64> {
+    version.strip_prefix("/recipes/")?.split('.').next()?.parse().ok()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-enum ListMode {
-    ALL,
-    One(String),
+//what an outgoing fetch was for when it belongs to fetching a collection manifest
+enum ManifestRequest {
+    Manifest,
+    Entry { author: PeerId, manifest: String, id: usize },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ListRequest {
-    mode: ListMode,
+enum EventType {
+    //answered on the topic the request came in on
+    Response(Topic, ListResponse),
+    NameMatches(NameMatches),
+    //stdin closed, ctrl-c was pressed or the watchdog gave up, carries the exit code
+    Shutdown(i32),
+    //boxed as the response channel is much bigger than the other variants
+    FetchResponse(Box<(ResponseChannel<FetchResponse>, FetchResponse)>),
+    Input(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ListResponse {
-    mode: ListMode,
-    data: Recipes,
-    receiver: String,
+//shuts the node down the same way ctrl-c does when one of the answer channels closes
+fn channel_closed(channel: &str) -> EventType {
+    error!("{}", RecipeError::Network(format!("{} channel closed, shutting down", channel)));
+    EventType::Shutdown(1)
 }
 
-enum EventType {
-    Response(ListResponse),
-    Input(String),
+//the event for what came out of one of the answer channels, None being that every sender for it is gone
+fn received<T>(message: Option<T>, channel: &str, event: impl FnOnce(T) -> EventType) -> EventType {
+    message.map_or_else(|| channel_closed(channel), event)
+}
+
+//the channels responder tasks hand their answers back to the swarm on
+struct Answers {
+    responses: mpsc::UnboundedReceiver<(Topic, ListResponse)>,
+    names: mpsc::UnboundedReceiver<NameMatches>,
+    fetches: mpsc::UnboundedReceiver<(ResponseChannel<FetchResponse>, FetchResponse)>,
+}
+
+impl Answers {
+    //the next answer from any of them, a closed channel means the behaviour holding its sender is gone and nothing more can be answered
+    async fn next(&mut self) -> EventType {
+        tokio::select! {
+            response = self.responses.recv() => received(response, "response", |(topic, response)| EventType::Response(topic, response)),
+            matches = self.names.recv() => received(matches, "name matches", EventType::NameMatches),
+            fetched = self.fetches.recv() => received(fetched, "fetch response", |fetched| EventType::FetchResponse(Box::new(fetched))),
+        }
+    }
+}
+
+//an announcement on its way out, periodic ones are replaced by the next round rather than held
+#[derive(PartialEq)]
+struct Outgoing {
+    topic: Topic,
+    payload: Vec<u8>,
+    periodic: bool,
+}
+
+//how an announcement went, read out after what was announced
+enum Announced {
+    //published, this many peers on the topic receive it
+    Sent(usize),
+    //waiting for the publish cooldown, with this many announcements in the queue
+    Queued(usize),
+    //no peer on the topic is connected, it goes out once one subscribes
+    Held,
+    //no peer on the topic is connected and it isnt kept, with hold_announcements off or sent again every interval anyway
+    Unheard,
+}
+
+impl std::fmt::Display for Announced {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Announced::Sent(1) => write!(f, "sent to 1 peer"),
+            Announced::Sent(peers) => write!(f, "sent to {} peers", peers),
+            Announced::Queued(queued) => write!(f, "queued behind the publish cooldown, {} announcements waiting", queued),
+            Announced::Held => write!(f, "held, no peers are connected yet, it goes out once one is"),
+            Announced::Unheard => write!(f, "no peers are connected, nobody received it"),
+        }
+    }
 }
 
 #[derive(NetworkBehaviour)]
 struct RecipeBehaviour{
     floodsub: Floodsub,
-    mdns: Mdns,
+    //only runs when mdns discovery is configured and enable_mdns is on
+    mdns: Toggle<Mdns>,
+    //only enabled when relay addresses are configured
+    relay: Toggle<Relay>,
+    //direct one to one requests for a specific recipe
+    fetch: RequestResponse<FetchCodec>,
+    //exchanges agent version and supported protocols with connected peers
+    identify: Identify,
+    //measures round trip times to connected peers
+    ping: Ping,
+    #[behaviour(ignore)]
+    response_sender: mpsc::UnboundedSender<(Topic, ListResponse)>,
+    //sends answers to name queries back to the event loop to be published
+    #[behaviour(ignore)]
+    name_sender: mpsc::UnboundedSender<NameMatches>,
+    //answers to fetch requests once the local recipes have been read
+    #[behaviour(ignore)]
+    fetch_sender: mpsc::UnboundedSender<(ResponseChannel<FetchResponse>, FetchResponse)>,
+    //cached recipes a patch couldnt be applied to, by author and id, fetched whole from the event loop
+    #[behaviour(ignore)]
+    stale_sender: mpsc::UnboundedSender<(PeerId, usize)>,
+    //recently seen floodsub messages, repeats are dropped before deserializing
+    #[behaviour(ignore)]
+    seen_messages: DedupCache,
+    //whether published messages carry a checksum, received ones are checked either way
+    #[behaviour(ignore)]
+    message_checksums: bool,
+    #[behaviour(ignore)]
+    responders: ResponderLimit,
+    //forwards mdns events to the mdns discovery stream when it is the configured discovery
+    #[behaviour(ignore)]
+    mdns_events: Option<mpsc::UnboundedSender<DiscoveryEvent>>,
+    //peers found by discovery and the addresses they were found at
+    #[behaviour(ignore)]
+    known_peers: HashMap<PeerId, HashSet<Multiaddr>>,
+    //outgoing fetches that are part of fetching a collection manifest
+    #[behaviour(ignore)]
+    manifest_requests: HashMap<RequestId, ManifestRequest>,
+    //collection manifests whose recipes are still being fetched, by author and manifest name
     #[behaviour(ignore)]
-    response_sender: mpsc::UnboundedSender<ListResponse>,
+    manifest_fetches: HashMap<(PeerId, String), ManifestFetch>,
+    //what each active listener was asked to listen on
+    #[behaviour(ignore)]
+    listeners: HashMap<ListenerId, Multiaddr>,
+    //addresses whose listener closed on its own, started again by the watchdog
+    #[behaviour(ignore)]
+    lost_listeners: Vec<Multiaddr>,
+    //escalates repeated listener failures
+    #[behaviour(ignore)]
+    watchdog: Watchdog,
+    //peers running an incompatible protocol version whose messages are ignored, only filled when reject_incompatible_peers is on
+    #[behaviour(ignore)]
+    incompatible_peers: HashSet<PeerId>,
+    //what each peer reported about itself through identify
+    #[behaviour(ignore)]
+    peer_info: HashMap<PeerId, IdentifyInfo>,
+    //outbound dials to known peers, limited in number and retried with backoff
+    #[behaviour(ignore)]
+    dialer: Dialer,
+    //peers messages are propagated to, capped at max_view_peers
+    #[behaviour(ignore)]
+    view: PeerView,
+    //announcements waiting for the publish cooldown to pass, with the topic each goes out on
+    #[behaviour(ignore)]
+    broadcasts: BroadcastQueue<Outgoing>,
+    //announcements no peer would have received, sent once a peer subscribes to their topic
+    #[behaviour(ignore)]
+    held: VecDeque<Outgoing>,
+    #[behaviour(ignore)]
+    hold_announcements: bool,
+    //topics this node is subscribed to, the default one included
+    #[behaviour(ignore)]
+    topics: HashSet<String>,
+    //peers that told us they are subscribed to a topic, by topic, only they receive anything published on it
+    #[behaviour(ignore)]
+    subscribed: HashMap<String, HashSet<PeerId>>,
+    //seconds since the unix epoch a peer was last discovered, connected to or heard from
+    #[behaviour(ignore)]
+    last_seen: HashMap<PeerId, u64>,
+    //broadcast requests still collecting responses
+    #[behaviour(ignore)]
+    pending_requests: PendingRequests,
+    //relayed addresses other peers can currently reach this node on
+    #[behaviour(ignore)]
+    relay_reservations: HashSet<Multiaddr>,
+    //when the startup grace period ends, requests from peers are ignored until then
+    #[behaviour(ignore)]
+    ready_at: Instant,
+    //the last few round trip times to each peer, newest last
+    #[behaviour(ignore)]
+    ping_rtts: HashMap<PeerId, VecDeque<Duration>>,
+    //peers peer ping is waiting to hear the next round trip of
+    #[behaviour(ignore)]
+    ping_waiters: HashSet<PeerId>,
+    //which peers serve which recipes according to the digests they broadcast
+    #[behaviour(ignore)]
+    digests: DigestIndex,
+    //peers discovery found or lost since the last discovery summary
+    #[behaviour(ignore)]
+    newly_discovered: HashSet<PeerId>,
+    #[behaviour(ignore)]
+    newly_expired: HashSet<PeerId>,
+    //the only peers anything is exchanged with, None when trusted_peers isnt set and every peer is trusted
+    #[behaviour(ignore)]
+    trusted: Option<HashSet<PeerId>>,
+    //untrusted peers disconnected from so far
+    #[behaviour(ignore)]
+    refused_peers: HashSet<PeerId>,
+}
+
+impl RecipeBehaviour {
+    //takes the next step of fetching a collection manifest, response is None when the fetch failed
+    fn continue_manifest_fetch(&mut self, peer: PeerId, request: ManifestRequest, response: Option<FetchResponse>) {
+        match (request, response) {
+            (ManifestRequest::Manifest, Some(FetchResponse::Manifest(Some(manifest)))) => {
+                info!("Fetching collection {} from {} ({} recipes)", manifest.name, peer, manifest.entries.len());
+                for entry in &manifest.entries {
+                    let request_id = self.fetch.send_request(&peer, FetchRequest::Recipe { id: entry.id });
+                    let request = ManifestRequest::Entry { author: peer, manifest: manifest.name.clone(), id: entry.id };
+                    self.manifest_requests.insert(request_id, request);
+                }
+                let key = (peer, manifest.name.clone());
+                self.manifest_fetches.insert(key.clone(), ManifestFetch::new(manifest));
+                self.finish_manifest_fetch(key);
+            }
+            (ManifestRequest::Manifest, Some(FetchResponse::Manifest(None))) => {
+                info!("{} has not published a collection by that name", peer)
+            }
+            //failures are already logged by the caller
+            (ManifestRequest::Manifest, _) => {}
+            (ManifestRequest::Entry { author, manifest, id }, response) => {
+                let recipe = match response {
+                    Some(FetchResponse::Recipe(recipe)) => recipe.map(|r| *r),
+                    _ => None,
+                };
+                let key = (author, manifest);
+                if let Some(progress) = self.manifest_fetches.get_mut(&key) {
+                    progress.record(id, recipe);
+                }
+                self.finish_manifest_fetch(key);
+            }
+        }
+    }
+
+    //reports a manifest fetch once every entry has been answered, missing entries dont fail the rest
+    fn finish_manifest_fetch(&mut self, key: (PeerId, String)) {
+        if !self.manifest_fetches.get(&key).is_some_and(|p| p.is_done()) {
+            return;
+        }
+        let progress = self.manifest_fetches.remove(&key).expect("fetch is tracked");
+        let (author, _) = key;
+        info!(
+            "Fetched collection {} from {}: {} recipes, {} missing",
+            progress.name,
+            author,
+            progress.fetched.len(),
+            progress.missing.len()
+        );
+        progress.ordered().iter().enumerate().for_each(|(i, r)| info!("{}. {} [{}]", i + 1, r.name, r.id));
+        progress.missing.iter().for_each(|e| info!("missing: {} [{}]", e.name, e.id));
+        cache_remote_recipes(author.to_string(), progress.fetched, None);
+    }
+
+    //a permit to answer a request, none means too many are already being answered and this one is dropped
+    fn responder_permit(&mut self, peer: &PeerId) -> Option<OwnedSemaphorePermit> {
+        self.responders.permit(peer)
+    }
+
+    //whether the startup grace period is over and requests from peers are answered
+    fn is_ready(&self) -> bool {
+        Instant::now() >= self.ready_at
+    }
+
+    //announces something to every peer, held back while the publish cooldown since the last announcement is running
+    fn announce(&mut self, topic: Topic, payload: Vec<u8>) -> Announced {
+        self.send_or_queue(Outgoing { topic, payload, periodic: false })
+    }
+
+    //announces something sent again every interval, it is never held since the next round replaces it
+    fn announce_periodic(&mut self, topic: Topic, payload: Vec<u8>) -> Announced {
+        self.send_or_queue(Outgoing { topic, payload, periodic: true })
+    }
+
+    fn send_or_queue(&mut self, outgoing: Outgoing) -> Announced {
+        match self.broadcasts.send_or_queue(outgoing) {
+            Some(outgoing) => self.deliver(outgoing),
+            None => Announced::Queued(self.broadcasts.queued()),
+        }
+    }
+
+    //publishes an announcement whose cooldown is over, one no peer would receive is held for later if hold_announcements is on
+    fn deliver(&mut self, outgoing: Outgoing) -> Announced {
+        match self.receivers(&outgoing.topic) {
+            0 if self.hold_announcements && !outgoing.periodic => {
+                if self.held.len() == MAX_HELD_ANNOUNCEMENTS {
+                    self.held.pop_front();
+                    warn!("more than {} announcements held, dropped the oldest", MAX_HELD_ANNOUNCEMENTS);
+                }
+                self.held.push_back(outgoing);
+                Announced::Held
+            }
+            0 => Announced::Unheard,
+            receivers => {
+                self.publish(outgoing.topic, outgoing.payload);
+                Announced::Sent(receivers)
+            }
+        }
+    }
+
+    //announces what was held for a topic once a peer on it is there to receive it
+    fn release_held(&mut self, topic: &str) {
+        let (release, keep): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut self.held).into_iter().partition(|o| o.topic.id() == topic);
+        self.held = keep;
+        for outgoing in release {
+            let announced = self.send_or_queue(outgoing);
+            info!("Sending a held announcement on {}: {}", topic, announced);
+        }
+    }
+
+    //peers a message published on the topic reaches, floodsub only sends to peers in the view that subscribed to it
+    fn receivers(&self, topic: &Topic) -> usize {
+        self.subscribed.get(topic.id()).map_or(0, |peers| peers.iter().filter(|p| self.view.contains(p)).count())
+    }
+
+    //publishes straight away, with a checksum appended when message_checksums is on
+    fn publish(&mut self, topic: Topic, payload: impl AsRef<[u8]>) {
+        let payload = payload.as_ref();
+        if self.message_checksums {
+            self.floodsub.publish(topic, checksum::seal(payload));
+        } else {
+            self.floodsub.publish(topic, payload);
+        }
+    }
+
+    //stops dialing, fetching from and propagating to a peer, a left out peer takes its place in the view
+    fn forget_peer(&mut self, peer: &PeerId) {
+        for addr in self.known_peers.remove(peer).unwrap_or_default() {
+            self.fetch.remove_address(peer, &addr);
+        }
+        self.dialer.forget(peer);
+        self.floodsub.remove_node_from_partial_view(peer);
+        let last_seen = &self.last_seen;
+        if let Some(promoted) = self.view.remove(peer, |p| last_seen.get(p).copied().unwrap_or(0)) {
+            self.floodsub.add_node_to_partial_view(promoted);
+            network_event(Level::Debug, "view-joined", &promoted, format_args!("took the place of {}", peer));
+        }
+    }
+
+    //every peer currently known through discovery
+    fn discovered_peers(&self) -> HashSet<PeerId> {
+        self.known_peers.keys().copied().collect()
+    }
+
+    //tells a waiting peer ping the peer cant be reached, nothing is logged when no one is waiting
+    fn ping_unreachable(&mut self, peer: &PeerId, reason: &str) {
+        if self.ping_waiters.remove(peer) {
+            info!("{}", RecipeError::Network(format!("ping to {} failed: {}", peer, reason)));
+        }
+    }
+
+    //the newest round trip time to a peer and the average of the recent ones
+    fn ping_summary(&self, peer: &PeerId) -> Option<(Duration, Duration)> {
+        let rtts = self.ping_rtts.get(peer)?;
+        let last = *rtts.back()?;
+        Some((last, rtts.iter().sum::<Duration>() / rtts.len() as u32))
+    }
+
+    //discovered peers subscribed to a topic, the only ones a broadcast request on it reaches
+    fn subscribed_peers(&self, topic: &str) -> HashSet<PeerId> {
+        let subscribed = self.subscribed.get(topic);
+        self.known_peers.keys().filter(|p| subscribed.is_some_and(|s| s.contains(p))).copied().collect()
+    }
+
+    fn is_trusted(&self, peer: &PeerId) -> bool {
+        is_trusted_by(self.trusted.as_ref(), peer)
+    }
+
+    //logs how many peers discovery knows and how that changed since the last summary, nothing when it didnt change
+    fn log_discovery_summary(&mut self, window: Duration) {
+        if self.newly_discovered.is_empty() && self.newly_expired.is_empty() {
+            return;
+        }
+        info!(
+            "Discovered {} peers, {} new and {} gone in the last {}s",
+            self.known_peers.len(),
+            self.newly_discovered.len(),
+            self.newly_expired.len(),
+            window.as_secs()
+        );
+        self.newly_discovered.clear();
+        self.newly_expired.clear();
+    }
+
+    //every topic this node is on with how many discovered peers share it, default topic first
+    fn topic_summary(&self) -> String {
+        let mut topics: Vec<&String> = self.topics.iter().collect();
+        topics.sort_by_key(|t| (t.as_str() != DEFAULT_TOPIC, t.as_str()));
+        topics.iter().map(|t| format!("{} ({} peers)", t, self.subscribed_peers(t).len())).collect::<Vec<_>>().join(", ")
+    }
+}
+
+//relay behaviour doesnt produce any events for the application
+impl NetworkBehaviourEventProcess<()> for RecipeBehaviour{
+    fn inject_event(&mut self, _event: ()) {}
 }
 
 //network behaviour defines what bytes and where to send them from the local node for MDNS event
 impl NetworkBehaviourEventProcess<MdnsEvent> for RecipeBehaviour{
-    //defines how the message is send to the network behaviour from the network handler
+    //mdns is just one discovery source, its events are forwarded to the mdns discovery stream
     fn inject_event(&mut self, event: MdnsEvent) {
-        match event {
+        let sender = match &self.mdns_events {
+            Some(sender) => sender,
+            None => return,
+        };
+        let events: Vec<DiscoveryEvent> = match event {
             //triggered when a new peer is discovered on the network
-            MdnsEvent::Discovered(discovered_list)=>{
-                //for every peer in the multi address in discovered list
-                for(peer, _addr) in discovered_list{
-                    //adds node to the list of nodes to propagate messages to.
-                    self.floodsub.add_node_to_partial_view(peer);
+            MdnsEvent::Discovered(discovered_list) => discovered_list.map(|(peer, addr)| DiscoveryEvent::Discovered(peer, addr)).collect(),
+            //triggered when the records time to live expires and the address hasn’t been refreshed and is removed from the list
+            MdnsEvent::Expired(expired_list) => expired_list.map(|(peer, addr)| DiscoveryEvent::Expired(peer, addr)).collect(),
+        };
+        for event in events {
+            if sender.send(event).is_err() {
+                error!("mdns discovery stream is gone, dropping event");
+            }
+        }
+    }
+}
+
+impl RecipeBehaviour {
+    //applies a discovery event from whichever discovery mechanism is configured
+    fn apply_discovery(&mut self, event: DiscoveryEvent) {
+        match event {
+            //untrusted peers are never dialed, so they never count as discovered either
+            DiscoveryEvent::Discovered(peer, addr) if !self.is_trusted(&peer) => {
+                network_event(Level::Debug, "discovered", &peer, format_args!("at {}, not trusted so not dialed", addr));
+            }
+            DiscoveryEvent::Discovered(peer, addr) => {
+                network_event(Level::Debug, "discovered", &peer, format_args!("at {}", addr));
+                if !self.known_peers.contains_key(&peer) {
+                    self.newly_discovered.insert(peer);
                 }
+                self.last_seen.insert(peer, unix_now());
+                //the peer is added to the nodes to propagate messages to once the dialer got a connection to it
+                self.dialer.enqueue(peer);
+                //remembers where the peer is so it can be dialed and recipes can be fetched from it directly
+                self.fetch.add_address(&peer, addr.clone());
+                self.known_peers.entry(peer).or_default().insert(addr);
             }
-            //triggered when the records time to live expires and the address hasn’t been refreshed and is removed from the list
-            MdnsEvent::Expired(expired_list)=>{
-                //for every peer in the multi address in expired list
-                for(peer, _addr) in expired_list{
-                    //true if the given PeerId is in the list of nodes discovered through mDNS
-                    if !self.mdns.has_node(&peer){
-                        //removes node from the list of nodes to propagate messages to.
-                        self.floodsub.remove_node_from_partial_view(&peer);
-                    }
+            DiscoveryEvent::Expired(peer, addr) => {
+                network_event(Level::Debug, "expired", &peer, format_args!("at {}", addr));
+                self.fetch.remove_address(&peer, &addr);
+                let remaining = self.known_peers.get_mut(&peer).map(|addrs| {
+                    addrs.remove(&addr);
+                    addrs.len()
+                });
+                //only once no address of the peer is left it is removed from the list of nodes to propagate messages to.
+                if remaining == Some(0) {
+                    self.newly_expired.insert(peer);
+                    self.forget_peer(&peer);
                 }
             }
         }
@@ -105,62 +578,438 @@ impl NetworkBehaviourEventProcess<MdnsEvent> for RecipeBehaviour{
 impl NetworkBehaviourEventProcess<FloodsubEvent> for RecipeBehaviour{
     //defines how the message is send to the network behaviour from the network handler
     fn inject_event(&mut self, event: FloodsubEvent) {
-        if let FloodsubEvent::Message(msg) = event {
-            //case for a response
-            if let Ok(resp) = serde_json::from_slice::<ListResponse>(&msg.data){
-                //checks if its indeed for local machine
-                if resp.receiver == PEER_ID.to_string(){
-                    //output
-                    info!("Response from: {}",msg.source);
-                    //iterates and outputs the data
-                    resp.data.iter().for_each(|r| info!("{:?}",r))
-                }
-            }
-            //case for request
-            else if let Ok(req) = serde_json::from_slice::<ListResponse>(&msg.data) {
-                //match statement to determine the mode
-                match req.mode {
-                    //mode all
-                    ListMode::ALL => {
-                        //outputs requests made
-                        info!("Received ALL req: {:?} from {:?}",req,msg.source);
-                        //responds with local messages
-                        respond_with_public_recipes(
-                            self.response_sender.clone(),
-                            msg.source.to_string(),
-                        );
+        match event {
+            //subscriptions to topics this node isnt on are tracked too, so subscribing later knows who is there
+            FloodsubEvent::Subscribed { peer_id, topic } => {
+                network_event(Level::Debug, "subscribed", &peer_id, format_args!("to {}", topic.id()));
+                self.subscribed.entry(topic.id().to_owned()).or_default().insert(peer_id);
+                if self.view.contains(&peer_id) {
+                    self.release_held(topic.id());
+                }
+            }
+            FloodsubEvent::Unsubscribed { peer_id, topic } => {
+                network_event(Level::Debug, "unsubscribed", &peer_id, format_args!("from {}", topic.id()));
+                if let Some(peers) = self.subscribed.get_mut(topic.id()) {
+                    peers.remove(&peer_id);
+                }
+            }
+            FloodsubEvent::Message(msg) => {
+                self.last_seen.insert(msg.source, unix_now());
+                //floodsub can still hand over messages an untrusted peer sent before it was disconnected
+                if !self.is_trusted(&msg.source) {
+                    network_event(Level::Debug, "message-ignored", &msg.source, format_args!("not a trusted peer"));
+                    return;
+                }
+                //peers are only known to be incompatible once identify has run, anything earlier is still handled
+                if self.incompatible_peers.contains(&msg.source) {
+                    network_event(Level::Debug, "message-ignored", &msg.source, format_args!("incompatible protocol version"));
+                    return;
+                }
+                //a checksum that doesnt match means the message was damaged on the way, nothing in it can be relied on
+                let data = match checksum::check(&msg.data) {
+                    checksum::Checked::Intact(data) => Cow::Owned(data),
+                    checksum::Checked::Unsealed(data) => Cow::Borrowed(data),
+                    checksum::Checked::Corrupt => {
+                        network_event(Level::Warn, "message-corrupt", &msg.source, format_args!("checksum mismatch, {} bytes dropped", msg.data.len()));
+                        return;
                     }
-                    //mode one
-                    ListMode::One(ref peer_id) => {
-                        //checks if request is for local machine
-                        if peer_id == &PEER_ID.to_string(){
-                            //outputs requests
-                            info!("Received req: {:?} from {:?}",req,msg.source);
-                            respond_with_public_recipes(
-                                self.response_sender.clone(),
-                                msg.source.to_string(),
-                            );
+                };
+                //the same content arriving again within the window has already been handled
+                if !self.seen_messages.is_new(&data) {
+                    return;
+                }
+                //one deserializer is picked by peeking at the fields, answers meant for other peers are dropped unparsed
+                let kind = match wire::classify(&data, &PEER_ID_TEXT) {
+                    Some(kind) => kind,
+                    None => return,
+                };
+                //floodsub only hands over messages on topics this node is on, requests are answered on the topic they came in on
+                let topic = msg.topics.iter().map(|t| t.id()).find(|t| self.topics.contains(*t)).unwrap_or(DEFAULT_TOPIC).to_owned();
+                //right after startup listeners and peers may not be up yet, answers sent then would be incomplete
+                if matches!(kind, MessageKind::ListRequest | MessageKind::NameQuery | MessageKind::LocateQuery) && !self.is_ready() {
+                    network_event(Level::Debug, "request-ignored", &msg.source, format_args!("still in the startup grace period"));
+                    return;
+                }
+                match kind {
+                    //case for a response
+                    MessageKind::ListResponse => if let Ok(resp) = serde_json::from_slice::<ListResponse>(&data) {
+                        //drops recipes outside of the users interests before anything else sees them
+                        let interests = match config::get() {
+                            Ok(c) => c.interests.clone(),
+                            Err(e) => {
+                                error!("error reading interests, keeping all recipes: {}", e);
+                                Vec::new()
+                            }
+                        };
+                        //partial recipes cant be checked against interests or cached, they are only shown
+                        if !resp.projected.is_empty() {
+                            let ids: Vec<usize> = resp.projected.iter().filter_map(|r| r.get("id")?.as_u64()).map(|id| id as usize).collect();
+                            if !self.pending_requests.record(resp.request_id, msg.source, &ids) {
+                                network_event(Level::Debug, "list-response", &msg.source, format_args!("ignored, arrived late"));
+                                return;
+                            }
+                            network_event(Level::Info, "list-response", &msg.source, format_args!("{} partial recipes", ids.len()));
+                            resp.projected.iter().for_each(|r| info!("{}", serde_json::Value::Object(r.clone())));
+                            return;
+                        }
+                        let total = resp.data.len();
+                        let wanted: Recipes = resp.data.into_iter().filter(|r| r.matches_interests(&interests)).collect();
+                        //responses arriving after their collection window closed are ignored
+                        let ids: Vec<usize> = wanted.iter().map(|r| r.id).collect();
+                        if !self.pending_requests.record(resp.request_id, msg.source, &ids) {
+                            network_event(Level::Debug, "list-response", &msg.source, format_args!("ignored, arrived late"));
+                            return;
+                        }
+                        //output
+                        network_event(Level::Info, "list-response", &msg.source, format_args!("{} recipes", wanted.len()));
+                        if wanted.len() < total {
+                            info!("{} recipes filtered out by interests", total - wanted.len());
+                        }
+                        //iterates and outputs the data
+                        wanted.iter().for_each(|r| info!("{:?}",r));
+                        cache_remote_recipes(msg.source.to_string(), wanted, Some(topic));
+                    },
+                    //case for matches to a name query
+                    MessageKind::NameMatches => if let Ok(resp) = serde_json::from_slice::<NameMatches>(&data) {
+                        let ids: Vec<usize> = resp.matches.iter().map(|m| m.id).collect();
+                        if !self.pending_requests.record(Some(resp.request_id), msg.source, &ids) {
+                            network_event(Level::Debug, "name-matches", &msg.source, format_args!("ignored, arrived late"));
+                            return;
                         }
+                        network_event(Level::Info, "name-matches", &msg.source, format_args!("{} matches", resp.matches.len()));
+                        for m in &resp.matches {
+                            info!("{} [{}] from {}, fetch with: open {}", m.name, m.id, msg.source, fetch::share_link(&msg.source, m.id));
+                        }
+                    },
+                    //case for a recipe published as it was created
+                    MessageKind::RecipeAnnouncement => if let Ok(announcement) = serde_json::from_slice::<RecipeAnnouncement>(&data) {
+                        let recipe = announcement.published;
+                        network_event(Level::Info, "recipe-published", &msg.source, format_args!("{} [{}] on {}", recipe.name, recipe.id, topic));
+                        info!("fetch with: open {}", fetch::share_link(&msg.source, recipe.id));
+                    },
+                    //case for an edit to a published recipe, peers can only patch their own recipes
+                    MessageKind::RecipePatch => if let Ok(patch) = serde_json::from_slice::<RecipePatch>(&data) {
+                        if patch.author != msg.source.to_string() {
+                            network_event(Level::Warn, "recipe-patch", &msg.source, format_args!("ignored, recipe {} belongs to {}", patch.id, patch.author));
+                            return;
+                        }
+                        let fields: Vec<&str> = patch.changed_fields.keys().map(|k| k.as_str()).collect();
+                        network_event(Level::Info, "recipe-patch", &msg.source, format_args!("recipe {} changed {}", patch.id, fields.join(", ")));
+                        apply_remote_patch(self.stale_sender.clone(), msg.source, patch);
+                    },
+                    //case for a newly published collection manifest
+                    MessageKind::ManifestAnnouncement => if let Ok(announcement) = serde_json::from_slice::<ManifestAnnouncement>(&data) {
+                        let manifest = announcement.manifest;
+                        network_event(
+                            Level::Info,
+                            "collection-published",
+                            &msg.source,
+                            format_args!("{} with {} recipes", manifest.name, manifest.entries.len()),
+                        );
+                        info!("fetch with: collection fetch {} {}", msg.source, manifest.name);
+                    },
+                    //case for a locate query
+                    MessageKind::LocateQuery => if let Ok(query) = serde_json::from_slice::<LocateQuery>(&data) {
+                        network_event(Level::Info, "locate-query", &msg.source, format_args!("{:?}", query.locate));
+                        if let Some(permit) = self.responder_permit(&msg.source) {
+                            let target = query.locate;
+                            respond_with_name_matches(self.name_sender.clone(), msg.source.to_string(), query.request_id, permit, move |r| {
+                                target.matches(r)
+                            });
+                        }
+                    },
+                    //case for a digest page of the recipes a peer serves
+                    MessageKind::AvailabilityDigest => if let Ok(digest) = serde_json::from_slice::<AvailabilityDigest>(&data) {
+                        if let Some(entries) = self.digests.record(msg.source, digest) {
+                            network_event(Level::Debug, "digest", &msg.source, format_args!("serves {} recipes", entries.len()));
+                            refetch_changed(self.stale_sender.clone(), msg.source, entries.to_vec());
+                        }
+                    },
+                    //case for a name query
+                    MessageKind::NameQuery => if let Ok(query) = serde_json::from_slice::<NameQuery>(&data) {
+                        network_event(Level::Info, "name-query", &msg.source, format_args!("{:?}", query.name));
+                        if let Some(permit) = self.responder_permit(&msg.source) {
+                            let wanted = query.name.to_lowercase();
+                            respond_with_name_matches(self.name_sender.clone(), msg.source.to_string(), query.request_id, permit, move |r| {
+                                r.name.to_lowercase().contains(&wanted)
+                            });
+                        }
+                    },
+                    //case for request
+                    MessageKind::ListRequest => if let Ok(req) = serde_json::from_slice::<ListRequest>(&data) {
+                        //match statement to determine the mode
+                        match req.mode {
+                            //mode all
+                            ListMode::ALL => {
+                                //outputs requests made
+                                network_event(Level::Info, "list-request", &msg.source, format_args!("{:?}", req));
+                                //responds with local messages
+                                if let Some(permit) = self.responder_permit(&msg.source) {
+                                    respond_with_public_recipes(
+                                        self.response_sender.clone(),
+                                        msg.source.to_string(),
+                                        req.request_id,
+                                        topic.clone(),
+                                        req.fields.clone(),
+                                        permit,
+                                    );
+                                }
+                            }
+                            //mode one
+                            ListMode::One(ref peer_id) => {
+                                //checks if request is for local machine
+                                if *peer_id == *PEER_ID_TEXT {
+                                    //outputs requests
+                                    network_event(Level::Info, "list-request", &msg.source, format_args!("{:?}", req));
+                                    if let Some(permit) = self.responder_permit(&msg.source) {
+                                        respond_with_public_recipes(
+                                            self.response_sender.clone(),
+                                            msg.source.to_string(),
+                                            req.request_id,
+                                            topic,
+                                            req.fields.clone(),
+                                            permit,
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
+
+//network behaviour for what peers tell us about themselves
+impl NetworkBehaviourEventProcess<IdentifyEvent> for RecipeBehaviour{
+    fn inject_event(&mut self, event: IdentifyEvent) {
+        match event {
+            IdentifyEvent::Received { peer_id, info } => {
+                network_event(
+                    Level::Debug,
+                    "identify",
+                    &peer_id,
+                    format_args!("agent {}, protocols {}", info.agent_version, info.protocols.join(", ")),
+                );
+                //minor and patch differences stay compatible, only the major version has to match
+                let compatible = protocol_major(&info.protocol_version).is_some_and(|major| Some(major) == protocol_major(PROTOCOL_VERSION));
+                if compatible {
+                    self.incompatible_peers.remove(&peer_id);
+                } else {
+                    let reject = config::get().map(|c| c.reject_incompatible_peers).unwrap_or(false);
+                    network_event(
+                        Level::Warn,
+                        "identify",
+                        &peer_id,
+                        format_args!(
+                            "runs protocol {} ({}) which is incompatible with ours {}{}",
+                            info.protocol_version,
+                        info.agent_version,
+                        PROTOCOL_VERSION,
+                            if reject { ", ignoring its messages" } else { "" }
+                        ),
+                    );
+                    if reject {
+                        self.incompatible_peers.insert(peer_id);
+                    }
+                }
+                self.peer_info.insert(peer_id, info);
+            }
+            IdentifyEvent::Error { peer_id, error } => {
+                network_event(Level::Debug, "identify", &peer_id, format_args!("failed: {:?}", error))
+            }
+            IdentifyEvent::Sent { .. } | IdentifyEvent::Pushed { .. } => {}
+        }
+    }
+}
+
+//network behaviour for round trip pings to connected peers
+impl NetworkBehaviourEventProcess<PingEvent> for RecipeBehaviour{
+    fn inject_event(&mut self, event: PingEvent) {
+        match event.result {
+            Ok(PingSuccess::Ping { rtt }) => {
+                network_event(Level::Debug, "ping", &event.peer, format_args!("round trip {} ms", rtt.as_millis()));
+                let rtts = self.ping_rtts.entry(event.peer).or_default();
+                rtts.push_back(rtt);
+                while rtts.len() > RECENT_PINGS {
+                    rtts.pop_front();
+                }
+                if self.ping_waiters.remove(&event.peer) {
+                    info!("Reply from {} in {} ms", event.peer, rtt.as_millis());
+                }
+            }
+            Ok(PingSuccess::Pong) => {}
+            Err(failure) => {
+                network_event(Level::Debug, "ping", &event.peer, format_args!("failed: {}", failure));
+                self.ping_unreachable(&event.peer, &failure.to_string());
+            }
+        }
+    }
+}
+
+//network behaviour for direct fetches between two peers
+impl NetworkBehaviourEventProcess<RequestResponseEvent<FetchRequest, FetchResponse>> for RecipeBehaviour{
+    fn inject_event(&mut self, event: RequestResponseEvent<FetchRequest, FetchResponse>) {
+        match event {
+            RequestResponseEvent::Message { peer, message } => match message {
+                //another peer wants one of our recipes
+                RequestResponseMessage::Request { .. } if !self.is_trusted(&peer) => {
+                    network_event(Level::Debug, "fetch-request", &peer, format_args!("ignored, not a trusted peer"));
+                }
+                RequestResponseMessage::Request { .. } if self.incompatible_peers.contains(&peer) => {
+                    network_event(Level::Debug, "fetch-request", &peer, format_args!("ignored, incompatible protocol version"));
+                }
+                RequestResponseMessage::Request { request, channel, .. } => {
+                    network_event(Level::Info, "fetch-request", &peer, format_args!("{:?}", request));
+                    //a dropped channel tells the requesting peer the fetch failed
+                    if let Some(permit) = self.responder_permit(&peer) {
+                        respond_to_fetch(self.fetch_sender.clone(), peer.to_string(), request, channel, permit);
+                    }
+                }
+                //a peer answered a fetch that is part of fetching a collection manifest
+                RequestResponseMessage::Response { request_id, response } if self.manifest_requests.contains_key(&request_id) => {
+                    let request = self.manifest_requests.remove(&request_id).expect("request is tracked");
+                    self.continue_manifest_fetch(peer, request, Some(response));
+                }
+                //a peer answered one of our fetches
+                RequestResponseMessage::Response { response, .. } => match response {
+                    FetchResponse::Recipe(Some(recipe)) => {
+                        network_event(Level::Info, "fetch-response", &peer, format_args!("recipe {}", recipe.id));
+                        info!("{:?}", recipe);
+                        cache_remote_recipes(peer.to_string(), vec![*recipe], None);
                     }
+                    FetchResponse::Recipe(None) => {
+                        network_event(Level::Info, "fetch-response", &peer, format_args!("no public recipe with that id"))
+                    }
+                    FetchResponse::Manifest(_) => {
+                        network_event(Level::Debug, "fetch-response", &peer, format_args!("ignored, manifest was not requested"))
+                    }
+                },
+            },
+            RequestResponseEvent::OutboundFailure { peer, error, request_id } => {
+                network_event(Level::Error, "fetch-failed", &peer, format_args!("{}", RecipeError::Network(format!("{:?}", error))));
+                //a failed entry counts as missing rather than holding up the rest of the manifest
+                if let Some(request) = self.manifest_requests.remove(&request_id) {
+                    self.continue_manifest_fetch(peer, request, None);
                 }
             }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                network_event(Level::Error, "fetch-answer-failed", &peer, format_args!("{}", RecipeError::Network(format!("{:?}", error))))
+            }
+            RequestResponseEvent::ResponseSent { .. } => {}
         }
     }
 }
 
 #[tokio::main]
 async fn main() {
+    //settings needed to build the swarm, read once at startup
+    //a config that cant be resolved, like one naming an unset env var, stops the node before it uses the wrong paths
+    let startup_config = config::get().unwrap_or_else(|e| {
+        eprintln!("error reading config: {}", e);
+        std::process::exit(1);
+    });
+    let args: Vec<String> = std::env::args().collect();
     //initializes logger
-    pretty_env_logger::init();
+    logging::init(&startup_config, logging::color_disabled(&args));
+    if let Some(profile) = config::active_profile() {
+        info!("Using profile: {}", profile);
+    }
+    //daemon start runs the node in the background with the rest of the arguments, daemon stop shuts it down again
+    if args.get(1).map(String::as_str) == Some("daemon") {
+        let result = match args.get(2).map(String::as_str) {
+            Some("start") => daemon::start(&args[3..]),
+            Some("stop") => daemon::stop(),
+            _ => Err(RecipeError::Validation("Format: daemon start [options] | daemon stop".to_owned())),
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     info!("Peer ID: {}",PEER_ID.clone());
+    //--collection <name> picks the collection to work on, otherwise the default one is used
+    if let Some(name) = args.iter().position(|a| a == "--collection").and_then(|i| args.get(i + 1)) {
+        if let Err(e) = storage::switch_collection(name).await {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    //doctor checks the setup instead of starting the node, exiting non-zero if something critical is wrong
+    if args.get(1).map(String::as_str) == Some("doctor") {
+        std::process::exit(if doctor::run(&startup_config).await { 0 } else { 1 });
+    }
+    //validate-all audits every stored recipe instead of starting the node, exiting non-zero if any fail
+    if args.get(1).map(String::as_str) == Some("validate-all") {
+        let fix = args.iter().any(|a| a == "--fix");
+        let passed = storage::validate_all(fix).await.unwrap_or_else(|e| {
+            error!("error validating recipes: {}", e);
+            false
+        });
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    //checks the storage file before anything relies on it
+    let repair = args.iter().any(|a| a == "--repair");
+    match storage::self_check(repair).await {
+        Ok(()) => {}
+        //a read-only storage doesnt stop the node, recipes are served and changes wait in memory for a save
+        Err(e @ RecipeError::Storage { .. }) => warn!("{}", e),
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    //a first run starts with an empty collection, new users are pointed at examples to try commands on
+    if prompt::is_interactive() && storage::read_shared_recipes().await.is_ok_and(|r| r.is_empty()) {
+        info!("No recipes yet, seed adds a few examples to try ls r, find and publish r on");
+    }
+    refresh_tag_completion().await;
+    //offline mode manages local recipes without building the swarm, nothing listens, dials or is discovered
+    if startup_config.offline || args.iter().any(|a| a == "--offline") {
+        info!("Offline mode, network commands are disabled");
+        run_offline(&startup_config).await;
+        return;
+    }
     //creates channel for communication within the application
-    let (response_sender, mut response_rcv) = mpsc::unbounded_channel();
+    let (response_sender, responses) = mpsc::unbounded_channel();
+    let (name_sender, names) = mpsc::unbounded_channel();
+    let (fetch_sender, fetches) = mpsc::unbounded_channel();
+    let mut answers = Answers { responses, names, fetches };
+    let (stale_sender, mut stale_rcv) = mpsc::unbounded_channel();
     //keypair for the noise protocol
     let auth_keys = Keypair::<X25519Spec>::new().into_authentic(&KEYS).expect("Can create auth keys");
 
+    //relay addresses to reserve a slot on so peers behind NAT can reach this node, off unless configured
+    let relay_addresses = startup_config.relay_addresses.clone();
+    let dedup_window = Duration::from_secs(startup_config.dedup_window_secs);
+    //handlers keep a connection open this long after its last use
+    let idle_timeout = Duration::from_secs(startup_config.idle_connection_timeout_secs);
+    //dials that take longer are aborted by the transport and retried by the dialer
+    let dial_timeout = Duration::from_secs(startup_config.dial_timeout_secs.max(1));
+    let allowlist = parse_peer_ids(&startup_config.allowlisted_peers, "allowlisted");
+    let trusted = trust_list(&startup_config.trusted_peers);
+    //topics that arent valid names are skipped rather than stopping the node
+    let mut topics: HashSet<String> = iter::once(DEFAULT_TOPIC.to_owned()).collect();
+    for topic in &startup_config.topics {
+        match validate_topic(topic) {
+            Ok(()) => {
+                topics.insert(topic.clone());
+            }
+            Err(e) => error!("ignoring topic from the config, {}", e),
+        }
+    }
+    //wraps tcp so addresses ending in p2p-circuit are dialed and listened on through a relay
+    let relay_config = RelayConfig {
+        connection_idle_timeout: idle_timeout,
+        ..RelayConfig::default()
+    };
+    let (relay_transport, relay_behaviour) = relay::new_transport_and_behaviour(relay_config, TokioTcpConfig::new());
+
     //Creates transport which is a feature of the lib p2p framework
-    let transport = TokioTcpConfig::new()
+    let transport = relay_transport
         //Upgrades version of the transport once connection is established as Version 1 of the multi-stream-select protocol is the version that interacts with the noise protocol
         //in short handles protocol negotiation
         .upgrade(upgrade::Version::V1)
@@ -168,21 +1017,98 @@ async fn main() {
         .authenticate(libp2p::noise::NoiseConfig::xx(auth_keys).into_authenticated())
         //multiplex transport negotiates multiple sub-streams and/or connections on the authenticated transport
         .multiplex(mplex::MplexConfig::new())
+        .outbound_timeout(dial_timeout)
         //boxed allows only output and error types to be captured
         .boxed();
 
+    //picks the discovery mechanism, mdns forwards from the behaviour while static peers come straight from config
+    let (mdns_discovery, mdns_events) = MdnsDiscovery::new();
+    let use_mdns = startup_config.discovery == "mdns" && startup_config.enable_mdns;
+    let mut discovery: Box<dyn Discovery> = match startup_config.discovery.as_str() {
+        "static" => match StaticDiscovery::from_addresses(&startup_config.static_peers) {
+            Ok(discovery) => Box::new(discovery),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        "mdns" => Box::new(mdns_discovery),
+        other => {
+            error!("unknown discovery {:?}, expected mdns or static", other);
+            std::process::exit(1);
+        }
+    };
+
     //dictates network behaviour
     let mut behaviour = RecipeBehaviour {
-        floodsub: Floodsub::new(PEER_ID.clone()),
+        floodsub: Floodsub::new(*PEER_ID),
         //mdns protocol automatically discovers peers and adds them too the network
-        mdns: Mdns::new(Default::default()).await.expect("can create mdns"),
+        mdns: Toggle::from(if use_mdns {
+            Some(Mdns::new(Default::default()).await.expect("can create mdns"))
+        } else {
+            None
+        }),
+        relay: Toggle::from((!relay_addresses.is_empty()).then_some(relay_behaviour)),
+        fetch: RequestResponse::new(
+            FetchCodec,
+            iter::once((FetchProtocol, ProtocolSupport::Full)),
+            {
+                let mut config = RequestResponseConfig::default();
+                config.set_connection_keep_alive(idle_timeout);
+                config
+            },
+        ),
+        identify: Identify::new(
+            IdentifyConfig::new(PROTOCOL_VERSION.to_owned(), KEYS.public())
+                .with_agent_version(format!("p2p-recipe/{}", env!("CARGO_PKG_VERSION"))),
+        ),
+        ping: Ping::new(PingConfig::new().with_interval(Duration::from_secs(startup_config.ping_interval_secs.max(1)))),
         response_sender,
+        name_sender,
+        fetch_sender,
+        stale_sender,
+        responders: ResponderLimit::new(startup_config.max_concurrent_responders),
+        mdns_events: use_mdns.then_some(mdns_events),
+        known_peers: HashMap::new(),
+        manifest_requests: HashMap::new(),
+        manifest_fetches: HashMap::new(),
+        listeners: HashMap::new(),
+        lost_listeners: Vec::new(),
+        watchdog: Watchdog::new(
+            startup_config.listener_failure_threshold,
+            Duration::from_secs(startup_config.listener_failure_window_secs),
+            startup_config.max_listener_restarts,
+        ),
+        incompatible_peers: HashSet::new(),
+        peer_info: HashMap::new(),
+        last_seen: HashMap::new(),
+        subscribed: HashMap::new(),
+        topics,
+        dialer: Dialer::new(startup_config.max_concurrent_dials, dial_timeout),
+        view: PeerView::new(startup_config.max_view_peers, allowlist),
+        broadcasts: BroadcastQueue::new(Duration::from_millis(startup_config.publish_cooldown_ms)),
+        held: VecDeque::new(),
+        hold_announcements: startup_config.hold_announcements,
+        seen_messages: DedupCache::new(dedup_window),
+        message_checksums: startup_config.message_checksums,
+        pending_requests: PendingRequests::default(),
+        relay_reservations: HashSet::new(),
+        ready_at: Instant::now() + Duration::from_millis(startup_config.startup_grace_ms),
+        ping_rtts: HashMap::new(),
+        ping_waiters: HashSet::new(),
+        digests: DigestIndex::default(),
+        newly_discovered: HashSet::new(),
+        newly_expired: HashSet::new(),
+        trusted,
+        refused_peers: HashSet::new(),
     };
 
-    behaviour.floodsub.subscribe(TOPIC.clone());
+    for topic in &behaviour.topics {
+        behaviour.floodsub.subscribe(Topic::new(topic.clone()));
+    }
 
     //manages connections created using transport and executes using the network behaviour
-    let mut swarm = SwarmBuilder::new(transport, behaviour, PEER_ID.clone())
+    let mut swarm = SwarmBuilder::new(transport, behaviour, *PEER_ID)
         //executor tell swarm to use the tokio runtime
         .executor(Box::new(|fut| {
             tokio::spawn(fut);
@@ -190,220 +1116,2805 @@ async fn main() {
         .build();
 
     //starts the swarm
-    Swarm::listen_on(
-        //lets os pick decide a port
+    start_listener(
+        //port 0 lets os pick decide a port
         &mut swarm,
-        "/ip4/0.0.0.0/tcp/0".parse().expect("Can get local socket"),
+        format!("/ip4/0.0.0.0/tcp/{}", startup_config.listen_port).parse().expect("Can get local socket"),
     ).expect("Can start swarm");
 
-    //allows the async reader to read the lines one by one
-    let mut stdin = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    //listening on a relayed address makes the relay hold a reservation for this node
+    for addr in &relay_addresses {
+        match addr.parse::<Multiaddr>() {
+            Ok(addr) => {
+                let circuit = addr.with(Protocol::P2pCircuit);
+                if let Err(e) = start_listener(&mut swarm, circuit) {
+                    error!("{}", e);
+                }
+            }
+            Err(e) => error!("invalid relay address {}: {}", addr, e),
+        }
+    }
+
+    //lines typed at the prompt, or piped in when stdin isnt a terminal
+    //a detached node reads no commands, its input stays open so only daemon stop or a signal ends it
+    let (_detached_input, mut input) = if daemon::is_detached() {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Some(sender), receiver)
+    } else {
+        (None, prompt::spawn_input(startup_config.prompt.clone()))
+    };
+    if daemon::is_detached() {
+        wait_for_listeners(&mut swarm).await;
+        daemon::report_ready(&PEER_ID, swarm.listeners());
+    }
+
+    //expired entries in the dedup cache are cleaned up once per window
+    //every periodic task is jittered so nodes started together dont all fire at once
+    let jitter = startup_config.timer_jitter_percent;
+    let mut dedup_prune = JitteredInterval::new(dedup_window.max(Duration::from_secs(1)), jitter);
+
+    //waiting dials are started as soon as a slot is free
+    let mut dial_check = JitteredInterval::new(Duration::from_millis(250), jitter);
 
+    //collection windows are checked often enough that summaries appear promptly
+    let mut request_check = JitteredInterval::new(Duration::from_millis(500), jitter);
+
+    //discovery changes are summarized once per interval rather than logged for every peer
+    let discovery_summary = Duration::from_secs(startup_config.discovery_summary_secs);
+    let mut discovery_summary_check = JitteredInterval::new(discovery_summary.max(Duration::from_secs(1)), jitter);
+
+    //digests of the recipes served to everyone go out on their own interval, 0 never sends them
+    let digest_interval = Duration::from_secs(startup_config.digest_interval_secs);
+    let mut digest_check = JitteredInterval::new(digest_interval.max(Duration::from_secs(1)), jitter);
+
+    //buffered recipe writes are checked twice per debounce interval, nothing is ever buffered when autosave is off
+    let autosave_check_ms = match startup_config.storage_autosave_ms {
+        0 => 1000,
+        ms => (ms / 2).max(10),
+    };
+    let mut autosave_check = JitteredInterval::new(Duration::from_millis(autosave_check_ms), jitter);
+
+    //known peers that stopped working are forgotten on their own when peer_prune_interval_secs is set
+    let prune_interval = Duration::from_secs(startup_config.peer_prune_interval_secs);
+    let mut prune_check = JitteredInterval::new(prune_interval.max(Duration::from_secs(1)), jitter);
+
+    //the readiness file follows listeners and peers coming and going, checked every second
+    let mut readiness = Readiness::new(&startup_config.readiness_file, startup_config.readiness_requires_peer);
+    let mut readiness_check = JitteredInterval::new(Duration::from_secs(1), jitter);
+
+    //piped input that ended still gets the answers to what it asked for, the node stops once every request window closes
+    let mut input_open = true;
     //event loop processes events from the swarm by listening through stdin
-    loop{
+    let exit_code = loop{
+        //queued announcements go out as soon as the cooldown allows
+        let next_broadcast = swarm.behaviour().broadcasts.next_due();
         let evt = {
             //select macro waits for several async processes and handles the first one that finishes
             tokio::select!{
-                line = stdin.next_line() => Some(EventType::Input(line.expect("can get line").expect("can read line from stdin"))),
-                event = swarm.next() =>{
-                    info!("Unhandled Swarm event: {:?}",event);
+                //input ending ends the session the same way ctrl-c does, after any open requests when it was piped
+                line = input.recv(), if input_open => match line {
+                    Some(line) => Some(EventType::Input(line)),
+                    None if prompt::is_interactive() || swarm.behaviour().pending_requests.is_empty() => Some(EventType::Shutdown(0)),
+                    None => {
+                        input_open = false;
+                        info!("Input ended, waiting for open requests to be answered: {}", swarm.behaviour().pending_requests.outstanding().join(", "));
+                        None
+                    }
+                },
+                _ = shutdown_signal() => Some(EventType::Shutdown(0)),
+                event = swarm.next() => event.and_then(|event| handle_swarm_event(&mut swarm, event)).map(EventType::Shutdown),
+                answer = answers.next() => Some(answer),
+                Some(event) = discovery.next() => {
+                    swarm.behaviour_mut().apply_discovery(event);
+                    None
+                },
+                _ = request_check.tick() => {
+                    for request in swarm.behaviour_mut().pending_requests.take_expired() {
+                        let (recipes, peers) = request.summary();
+                        info!("{}: received {} recipes from {} peers", request.description, recipes, peers);
+                        let missing = request.missing();
+                        if !missing.is_empty() {
+                            info!("no response from {} peers: {}", missing.len(), missing.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "));
+                        }
+                    }
+                    (!input_open && swarm.behaviour().pending_requests.is_empty()).then_some(EventType::Shutdown(0))
+                },
+                _ = autosave_check.tick() => {
+                    if let Err(e) = storage::flush_if_due().await {
+                        error!("error saving recipes, will retry: {}", e);
+                    }
+                    None
+                },
+                _ = tokio::time::sleep_until(next_broadcast.unwrap_or_else(std::time::Instant::now).into()), if next_broadcast.is_some() => {
+                    if let Some(outgoing) = swarm.behaviour_mut().broadcasts.pop_due() {
+                        let (topic, periodic) = (outgoing.topic.id().to_owned(), outgoing.periodic);
+                        let announced = swarm.behaviour_mut().deliver(outgoing);
+                        let level = if periodic { Level::Debug } else { Level::Info };
+                        log::log!(level, "Sending a queued announcement on {}: {}", topic, announced);
+                    }
+                    None
+                },
+                _ = dial_check.tick() => {
+                    pump_dials(&mut swarm);
+                    None
+                },
+                _ = prune_check.tick(), if !prune_interval.is_zero() => {
+                    let pruned = prune_peers(&mut swarm);
+                    if !pruned.is_empty() {
+                        info!("Pruned {} peers: {}", pruned.len(), pruned.iter().map(|(p, why)| format!("{} ({})", p, why)).collect::<Vec<_>>().join(", "));
+                    }
+                    None
+                },
+                _ = readiness_check.tick() => {
+                    let listening = swarm.listeners().next().is_some();
+                    readiness.update(&PEER_ID, listening, swarm.behaviour().is_ready(), swarm.network_info().num_peers());
+                    None
+                },
+                _ = discovery_summary_check.tick(), if !discovery_summary.is_zero() => {
+                    swarm.behaviour_mut().log_discovery_summary(discovery_summary);
+                    None
+                },
+                _ = dedup_prune.tick() => {
+                    swarm.behaviour_mut().seen_messages.prune();
+                    swarm.behaviour_mut().digests.prune();
+                    None
+                },
+                _ = digest_check.tick(), if !digest_interval.is_zero() => {
+                    broadcast_digest(&mut swarm).await;
+                    None
+                },
+                Some((author, id)) = stale_rcv.recv() => {
+                    network_event(Level::Info, "recipe-refetch", &author, format_args!("cached recipe {} is out of date", id));
+                    swarm.behaviour_mut().fetch.send_request(&author, FetchRequest::Recipe { id });
                     None
                 },
-                response = response_rcv.recv() => Some(EventType::Response(response.expect("Response exists"))),
             }
         };
         //commands (user interaction)
         if let Some(event) = evt {
             //match statement checks if it is an input or response event
             match event {
-                EventType::Response(_resp) => {}
+                //publishes responses once the local recipes have been read
+                EventType::Response(topic, resp) => {
+                    if let Some(json) = serialize_response(resp) {
+                        swarm.behaviour_mut().publish(topic, json.as_bytes());
+                    }
+                }
+                //a response that cant be serialized is skipped rather than taking the node down
+                EventType::NameMatches(matches) => match serde_json::to_string(&matches) {
+                    Ok(json) => swarm.behaviour_mut().publish(TOPIC.clone(), json.as_bytes()),
+                    Err(e) => error!("{}", RecipeError::Serde(e)),
+                },
+                EventType::FetchResponse(fetched) => {
+                    let (channel, response) = *fetched;
+                    if swarm.behaviour_mut().fetch.send_response(channel, response).is_err() {
+                        error!("{}", RecipeError::Network("fetch requester disconnected before the answer was sent".to_owned()));
+                    }
+                }
+                //buffered recipes are always written before exiting
+                EventType::Shutdown(code) => {
+                    if let Err(e) = storage::flush().await {
+                        error!("error saving recipes on shutdown: {}", e);
+                    }
+                    info!("Shutting down");
+                    break code;
+                }
                 //if its a input event match again to verify the command
-                EventType::Input(line) => match line.as_str() {
-                    "ls p" => handle_list_peers(&mut swarm).await,
-                    cmd if cmd.starts_with("ls r") => handle_list_recipes(cmd, &mut swarm).await,
-                    cmd if cmd.starts_with("create r") => handle_create_recipes(cmd).await,
-                    cmd if cmd.starts_with("publish r") => handle_publish_recipes(cmd).await,
-                    _ => panic!("Unknown command"),
+                EventType::Input(line) => {
+                    handle_input(&line, Some(&mut swarm), discovery.name()).await;
+                    refresh_tag_completion().await;
                 }
             }
         }
+    };
+    readiness.clear();
+    daemon::release_pid_file();
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+}
+//waits until the listener reports its addresses, giving up after a few seconds so a node that cant listen still reports
+async fn wait_for_listeners(swarm: &mut Swarm<RecipeBehaviour>) {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    //every interface is reported separately, reports for the rest follow the first one closely
+    let mut settle = None;
+    loop {
+        let until = settle.unwrap_or(deadline).min(deadline);
+        tokio::select! {
+            Some(event) = swarm.next() => {
+                handle_swarm_event(swarm, event);
+                if settle.is_none() && swarm.listeners().next().is_some() {
+                    settle = Some(tokio::time::Instant::now() + Duration::from_millis(200));
+                }
+            }
+            _ = tokio::time::sleep_until(until) => return,
+        }
+    }
+}
+//ctrl-c, or on unix the SIGTERM daemon stop sends
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            },
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+//logic for reading commands against local storage only, until input ends or ctrl-c is pressed
+async fn run_offline(config: &config::Config) {
+    let mut input = prompt::spawn_input(config.prompt.clone());
+    let autosave_ms = match config.storage_autosave_ms {
+        0 => 1000,
+        ms => (ms / 2).max(10),
+    };
+    let mut autosave_check = JitteredInterval::new(Duration::from_millis(autosave_ms), config.timer_jitter_percent);
+    loop {
+        tokio::select! {
+            line = input.recv() => match line {
+                Some(line) => {
+                    handle_input(&line, None, "offline").await;
+                    refresh_tag_completion().await;
+                }
+                None => break,
+            },
+            _ = shutdown_signal() => break,
+            _ = autosave_check.tick() => {
+                if let Err(e) = storage::flush_if_due().await {
+                    error!("error saving recipes, will retry: {}", e);
+                }
+            }
+        }
+    }
+    //buffered recipes are always written before exiting
+    if let Err(e) = storage::flush().await {
+        error!("error saving recipes on shutdown: {}", e);
+    }
+    info!("Shutting down");
+}
+//logic for running a command typed at the prompt, swarm is None in offline mode
+async fn handle_input(line: &str, swarm: Option<&mut Swarm<RecipeBehaviour>>, discovery: &str) {
+    //a line answering a confirmation question isnt a command of its own
+    if let Some(command) = prompt::take_confirmation() {
+        return match line.trim() {
+            "y" | "yes" => handle_delete_recipe(&command).await,
+            _ => info!("Cancelled, nothing was deleted"),
+        };
+    }
+    if cook::is_cooking() {
+        return cook::handle_line(line);
+    }
+    //an empty line asks for nothing, anything that isnt a command gets pointed at the list
+    if line.trim().is_empty() {
+        return;
+    }
+    if !prompt::is_command(line) {
+        return info!("Unknown command {:?}, type help for the list", line);
+    }
+    match line {
+        "help" => info!("Commands: {}", prompt::command_list()),
+        //commands that only use the network for part of what they do
+        cmd if cmd.starts_with("ls r") => handle_list_recipes(cmd, swarm).await,
+        cmd if cmd.starts_with("create r") => handle_create_recipes(cmd, swarm).await,
+        cmd if cmd.starts_with("collection") => handle_collection(cmd, swarm).await,
+        cmd if cmd.starts_with("edit r") => handle_edit_recipe(cmd, swarm).await,
+        "undo" => handle_undo(swarm).await,
+        cmd if cmd.starts_with("tag ") => handle_tag(cmd, swarm).await,
+        //commands that only touch local state work the same offline
+        cmd if cmd.starts_with("import r") => handle_import_recipes(cmd).await,
+        cmd if cmd.starts_with("merge") => handle_merge(cmd).await,
+        cmd if cmd.starts_with("archive r") => handle_archive_recipe(cmd, true).await,
+        cmd if cmd.starts_with("unarchive r") => handle_archive_recipe(cmd, false).await,
+        cmd if cmd.starts_with("archive ") => handle_archive(cmd).await,
+        cmd if cmd.starts_with("deny r") => handle_deny_recipe(cmd, true).await,
+        cmd if cmd.starts_with("allow r") => handle_deny_recipe(cmd, false).await,
+        cmd if cmd.starts_with("delete r") => handle_delete_recipe(cmd).await,
+        cmd if cmd.starts_with("interest") => handle_interest(cmd).await,
+        cmd if cmd.starts_with("note") => handle_note(cmd).await,
+        cmd if cmd.starts_with("made") => handle_made(cmd).await,
+        cmd if cmd.starts_with("show r") => handle_show_recipe(cmd).await,
+        "save" => handle_save().await,
+        "seed" => handle_seed().await,
+        cmd if cmd.starts_with("config set") => handle_config_set(cmd).await,
+        cmd if cmd.starts_with("replay") => handle_replay(cmd).await,
+        cmd if cmd.starts_with("find ") => handle_find_recipes(cmd).await,
+        cmd if cmd.starts_with("diff r") => handle_diff_recipes(cmd).await,
+        cmd if cmd.starts_with("card r") => handle_recipe_card(cmd).await,
+        cmd if cmd.starts_with("cook r") => handle_cook_recipe(cmd).await,
+        cmd if cmd.starts_with("export r") => handle_export_recipe(cmd).await,
+        cmd if cmd.starts_with("image fetch r") => handle_image_fetch(cmd).await,
+        cmd if cmd.starts_with("scale r") => handle_scale_recipe(cmd).await,
+        cmd if cmd.starts_with("nutrition r") => handle_nutrition(cmd).await,
+        cmd if cmd.starts_with("yield r") => handle_set_yield(cmd).await,
+        cmd if cmd.starts_with("pin r") => handle_pin_recipe(cmd, true).await,
+        cmd if cmd.starts_with("unpin r") => handle_pin_recipe(cmd, false).await,
+        "pinned list" => handle_list_pinned().await,
+        cmd if cmd.starts_with("report ingredients") => handle_ingredient_report(cmd).await,
+        cmd if cmd.starts_with("cache") => handle_cache(cmd).await,
+        cmd if cmd.starts_with("share r") => handle_share_recipe(cmd).await,
+        cmd => match swarm {
+            Some(swarm) => handle_network_command(cmd, swarm, discovery).await,
+            None if NETWORK_COMMANDS.iter().any(|c| cmd.starts_with(c)) => info!("{}", RecipeError::Offline(cmd.to_owned())),
+            None => info!("Unknown command {:?}, type help for the list", cmd),
+        },
+    }
+}
+//commands that need the swarm, kept in step with handle_network_command so offline mode can tell them from unknown ones
+const NETWORK_COMMANDS: &[&str] = &["ls p", "publish r", "relay status", "requests", "status", "peer protocols", "peer ping", "peer stats", "peer prune", "graph export", "find-remote", "locate", "open", "topic"];
+//logic for running a command that needs the network
+async fn handle_network_command(cmd: &str, swarm: &mut Swarm<RecipeBehaviour>, discovery: &str) {
+    match cmd {
+        "ls p" => handle_list_peers(swarm).await,
+        cmd if cmd.starts_with("ls p --format") => handle_peer_table(cmd, swarm),
+        cmd if cmd.starts_with("publish r") => handle_publish_recipes(cmd, swarm).await,
+        "relay status" => handle_relay_status(swarm),
+        "requests" => handle_list_requests(swarm),
+        "status" => handle_status(swarm, discovery),
+        cmd if cmd.starts_with("peer protocols") => handle_peer_protocols(cmd, swarm),
+        cmd if cmd.starts_with("peer ping") => handle_peer_ping(cmd, swarm),
+        cmd if cmd.starts_with("peer stats") => handle_peer_stats(cmd, swarm),
+        "peer prune" => handle_peer_prune(swarm),
+        cmd if cmd.starts_with("graph export") => handle_graph_export(cmd, swarm).await,
+        cmd if cmd.starts_with("find-remote") => handle_find_remote(cmd, swarm),
+        cmd if cmd.starts_with("locate") => handle_locate(cmd, swarm),
+        cmd if cmd.starts_with("open") => handle_open_link(cmd, swarm).await,
+        cmd if cmd.starts_with("topic") => handle_topic(cmd, swarm),
+        cmd => info!("Unknown command {:?}, type help for the list", cmd),
+    }
+}
+//logic for events coming from the swarm itself rather than a behaviour
+//returns an exit code when the node should shut down
+fn handle_swarm_event<E: std::fmt::Debug>(swarm: &mut Swarm<RecipeBehaviour>, event: SwarmEvent<(), E>) -> Option<i32> {
+    //relayed addresses are the ones passing through a p2p-circuit
+    let is_relayed = |addr: &Multiaddr| addr.iter().any(|p| p == Protocol::P2pCircuit);
+    match event {
+        SwarmEvent::NewListenAddr { address, .. } if is_relayed(&address) => {
+            info!("Relay reservation active: {}", address);
+            swarm.behaviour_mut().relay_reservations.insert(address);
+        }
+        SwarmEvent::ExpiredListenAddr { address, .. } if is_relayed(&address) => {
+            info!("Relay reservation expired: {}", address);
+            swarm.behaviour_mut().relay_reservations.remove(&address);
+        }
+        //noise has proven the peer holds the key its id is derived from, so an untrusted one is cut off straight away
+        //banning would refuse it before this but closing a banned connection panics request-response in this libp2p version
+        //floodsub on the other side redials straight away, so only the first attempt is a warning
+        SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } if !swarm.behaviour().is_trusted(&peer_id) => {
+            let level = if swarm.behaviour_mut().refused_peers.insert(peer_id) { Level::Warn } else { Level::Debug };
+            network_event(level, "untrusted-peer", &peer_id, format_args!("connected from {}, disconnecting", endpoint.get_remote_address()));
+            let _ = swarm.disconnect_peer_id(peer_id);
+        }
+        SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
+            swarm.behaviour_mut().last_seen.insert(peer_id, unix_now());
+            peer_connected(swarm, peer_id);
+            network_event(
+                Level::Debug,
+                "connection-established",
+                &peer_id,
+                format_args!("at {} (security {}, muxer {})", endpoint.get_remote_address(), SECURITY_PROTOCOL, MUXER_PROTOCOL),
+            );
+        }
+        SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
+            swarm.behaviour_mut().last_seen.insert(peer_id, unix_now());
+            network_event(Level::Debug, "connection-closed", &peer_id, format_args!("{} connections left", num_established));
+            //nothing is known about a peer once every connection to it is gone
+            //floodsub forgets the peer's subscriptions too, without telling us
+            if num_established == 0 {
+                swarm.behaviour_mut().peer_info.remove(&peer_id);
+                swarm.behaviour_mut().ping_unreachable(&peer_id, "disconnected before it replied");
+                swarm.behaviour_mut().subscribed.values_mut().for_each(|peers| {
+                    peers.remove(&peer_id);
+                });
+            }
+        }
+        //every address of a known peer failed, the dialer tries again later
+        SwarmEvent::UnreachableAddr { peer_id, error, attempts_remaining: 0, .. } if swarm.behaviour().known_peers.contains_key(&peer_id) => {
+            let delay = swarm.behaviour_mut().dialer.failed(peer_id);
+            network_event(Level::Warn, "dial-failed", &peer_id, format_args!("{}, retrying in {}s", error, delay.as_secs()));
+            swarm.behaviour_mut().ping_unreachable(&peer_id, &format!("unreachable, {}", error));
+        }
+        SwarmEvent::ListenerError { listener_id, error } => {
+            error!("{}", RecipeError::Network(format!("listener {:?} failed: {}", listener_id, error)));
+            return check_watchdog(swarm);
+        }
+        SwarmEvent::ListenerClosed { listener_id, addresses, reason } => {
+            for address in &addresses {
+                swarm.behaviour_mut().relay_reservations.remove(address);
+            }
+            //listeners the watchdog removed itself are expected to close
+            let target = swarm.behaviour_mut().listeners.remove(&listener_id)?;
+            error!("{}", RecipeError::Network(format!("listener on {} closed: {:?}", target, reason)));
+            swarm.behaviour_mut().lost_listeners.push(target);
+            return check_watchdog(swarm);
+        }
+        event => info!("Unhandled Swarm event: {:?}",event),
+    }
+    None
+}
+//peer ids from the config, entries that arent peer ids are skipped rather than stopping the node
+fn parse_peer_ids(entries: &[String], what: &str) -> HashSet<PeerId> {
+    entries
+        .iter()
+        .filter_map(|p| match p.parse() {
+            Ok(peer) => Some(peer),
+            Err(_) => {
+                error!("ignoring {} peer {:?}, it is not a peer id", what, p);
+                None
+            }
+        })
+        .collect()
+}
+//the peers trusted_peers lets in, None when it isnt set and every peer is trusted
+//a trust list that ended up empty because no entry was a peer id still shuts everyone out
+fn trust_list(entries: &[String]) -> Option<HashSet<PeerId>> {
+    (!entries.is_empty()).then(|| parse_peer_ids(entries, "trusted"))
+}
+fn is_trusted_by(trusted: Option<&HashSet<PeerId>>, peer: &PeerId) -> bool {
+    trusted.is_none_or(|t| t.contains(peer))
+}
+//logic for adding a newly connected known peer to the nodes messages are propagated to
+fn peer_connected(swarm: &mut Swarm<RecipeBehaviour>, peer: PeerId) {
+    let behaviour = swarm.behaviour_mut();
+    behaviour.dialer.connected(&peer);
+    if !behaviour.known_peers.contains_key(&peer) {
+        return;
+    }
+    let last_seen = &behaviour.last_seen;
+    match behaviour.view.admit(peer, |p| last_seen.get(p).copied().unwrap_or(0)) {
+        Admission::Added { evicted } => {
+            behaviour.floodsub.add_node_to_partial_view(peer);
+            for evicted in evicted {
+                behaviour.floodsub.remove_node_from_partial_view(&evicted);
+                network_event(Level::Debug, "view-evicted", &evicted, format_args!("least recently active, made room for allowlisted {}", peer));
+            }
+        }
+        Admission::LeftOut => network_event(
+            Level::Info,
+            "view-full",
+            &peer,
+            format_args!("partial view is full at {} peers, not propagating to it", behaviour.view.max()),
+        ),
+        Admission::Unchanged => {}
+    }
+}
+//logic for starting the dials the dialer has room for, dials over the timeout are counted as failed
+fn pump_dials(swarm: &mut Swarm<RecipeBehaviour>) {
+    for peer in swarm.behaviour_mut().dialer.take_timed_out() {
+        let delay = swarm.behaviour_mut().dialer.failed(peer);
+        network_event(Level::Warn, "dial-timeout", &peer, format_args!("no connection in time, retrying in {}s", delay.as_secs()));
+        swarm.behaviour_mut().ping_unreachable(&peer, "unreachable, no connection in time");
+    }
+    for peer in swarm.behaviour_mut().dialer.next_dials() {
+        //peers that connected to us in the meantime dont need dialing
+        if swarm.is_connected(&peer) {
+            peer_connected(swarm, peer);
+            continue;
+        }
+        network_event(Level::Debug, "dialing", &peer, format_args!("{} known addresses", swarm.behaviour().known_peers.get(&peer).map_or(0, |a| a.len())));
+        if let Err(e) = swarm.dial(&peer) {
+            let delay = swarm.behaviour_mut().dialer.failed(peer);
+            network_event(Level::Warn, "dial-failed", &peer, format_args!("{:?}, retrying in {}s", e, delay.as_secs()));
+            swarm.behaviour_mut().ping_unreachable(&peer, &format!("unreachable, {:?}", e));
+        }
+    }
+}
+//logic for starting a listener and remembering what it was asked to listen on so it can be started again
+fn start_listener(swarm: &mut Swarm<RecipeBehaviour>, address: Multiaddr) -> RecipeResult<()> {
+    let id = swarm
+        .listen_on(address.clone())
+        .map_err(|e| RecipeError::Network(format!("error listening on {}: {}", address, e)))?;
+    swarm.behaviour_mut().listeners.insert(id, address);
+    Ok(())
+}
+//logic for escalating listener failures, returns an exit code once setting listeners up again didnt help
+fn check_watchdog(swarm: &mut Swarm<RecipeBehaviour>) -> Option<i32> {
+    let deaf = swarm.behaviour().listeners.is_empty();
+    match swarm.behaviour_mut().watchdog.record_failure(deaf) {
+        WatchdogAction::Continue => None,
+        WatchdogAction::Relisten => {
+            let attempt = swarm.behaviour().watchdog.restarts();
+            warn!("watchdog: listeners keep failing, setting them up again (attempt {})", attempt);
+            //every listener is torn down and started fresh, not just the ones that closed
+            let current: Vec<(ListenerId, Multiaddr)> = swarm.behaviour_mut().listeners.drain().collect();
+            let mut targets: Vec<Multiaddr> = swarm.behaviour_mut().lost_listeners.drain(..).collect();
+            for (id, address) in current {
+                let _ = swarm.remove_listener(id);
+                targets.push(address);
+            }
+            for address in targets {
+                if let Err(e) = start_listener(swarm, address.clone()) {
+                    error!("{}", e);
+                    swarm.behaviour_mut().lost_listeners.push(address);
+                }
+            }
+            None
+        }
+        WatchdogAction::Exit => {
+            error!(
+                "watchdog: listeners still failing after {} restarts, exiting so a supervisor can restart the node",
+                swarm.behaviour().watchdog.restarts()
+            );
+            Some(1)
+        }
+    }
+}
+//logic for showing what protocols a connected peer supports
+fn handle_peer_protocols(cmd: &str, swarm: &Swarm<RecipeBehaviour>) {
+    let peer_id = cmd.strip_prefix("peer protocols").unwrap_or_default().trim();
+    let peer = match peer_id.parse::<PeerId>() {
+        Ok(peer) => peer,
+        Err(_) => {
+            info!("invalid peer id {:?} - Format: peer protocols <peer_id>", peer_id);
+            return;
+        }
+    };
+    match swarm.behaviour().peer_info.get(&peer) {
+        Some(info) => {
+            info!("Peer {} ({}, {})", peer, info.agent_version, info.protocol_version);
+            info!("Connection: security {}, muxer {}", SECURITY_PROTOCOL, MUXER_PROTOCOL);
+            info.protocols.iter().for_each(|p| info!("- {}", p));
+        }
+        None => info!("nothing known about {}, it has to be connected first", peer),
+    }
+}
+//logic for showing how dials to a peer went and how long until it is retried
+fn handle_peer_stats(cmd: &str, swarm: &Swarm<RecipeBehaviour>) {
+    let peer_id = cmd.strip_prefix("peer stats").unwrap_or_default().trim();
+    let peer = match peer_id.parse::<PeerId>() {
+        Ok(peer) => peer,
+        Err(_) => {
+            info!("invalid peer id {:?} - Format: peer stats <peer_id>", peer_id);
+            return;
+        }
+    };
+    let dialer = &swarm.behaviour().dialer;
+    let stats = match dialer.stats(&peer) {
+        Some(stats) => stats,
+        None => {
+            info!("no dials to {} recorded, peers are only dialed once they are discovered", peer);
+            return;
+        }
+    };
+    info!("Peer {} ({})", peer, if swarm.is_connected(&peer) { "connected" } else { "not connected" });
+    info!(
+        "Dials: {} attempts, {} succeeded, {} failed, {}",
+        stats.attempts,
+        stats.successes,
+        stats.failures,
+        stats.success_rate().map_or("none finished yet".to_owned(), |rate| format!("{:.0}% success", rate))
+    );
+    match dialer.backoff(&peer) {
+        Some((failures, left)) => info!("Backoff: {} failures in a row, next retry in {}s", failures, left.as_secs()),
+        None => info!("Backoff: none"),
+    }
+}
+//logic for forgetting known peers that keep failing to connect or havent been seen in a long time, peer prune
+fn handle_peer_prune(swarm: &mut Swarm<RecipeBehaviour>) {
+    let pruned = prune_peers(swarm);
+    pruned.iter().for_each(|(peer, why)| info!("Forgot {}, {}", peer, why));
+    info!("Pruned {} peers, {} still known", pruned.len(), swarm.behaviour().known_peers.len());
+}
+//forgets known peers that arent connected and either failed peer_prune_failures dials in a row or were last seen over peer_prune_ttl_secs ago
+//allowlisted peers are never pruned, returns each pruned peer with the reason
+fn prune_peers(swarm: &mut Swarm<RecipeBehaviour>) -> Vec<(PeerId, String)> {
+    let config = match config::get() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("error reading config: {}", e);
+            return Vec::new();
+        }
+    };
+    let now = unix_now();
+    let behaviour = swarm.behaviour();
+    let pruned: Vec<(PeerId, String)> = behaviour
+        .known_peers
+        .keys()
+        .filter(|p| !swarm.is_connected(p))
+        .filter_map(|p| {
+            let failures = behaviour.dialer.backoff(p).map_or(0, |(failures, _)| failures);
+            let unseen = behaviour.last_seen.get(p).map(|seen| now.saturating_sub(*seen));
+            prune_reason(behaviour.view.is_allowlisted(p), failures, unseen, &config).map(|why| (*p, why))
+        })
+        .collect();
+    pruned.iter().for_each(|(peer, _)| swarm.behaviour_mut().forget_peer(peer));
+    pruned
+}
+//why a known peer that failed failures dials in a row and was last seen unseen seconds ago is pruned, None if it is kept
+fn prune_reason(allowlisted: bool, failures: u32, unseen: Option<u64>, config: &config::Config) -> Option<String> {
+    if allowlisted {
+        None
+    } else if config.peer_prune_failures > 0 && failures >= config.peer_prune_failures {
+        Some(format!("{} failed dials in a row", failures))
+    } else if config.peer_prune_ttl_secs > 0 && unseen.is_some_and(|secs| secs > config.peer_prune_ttl_secs) {
+        Some(format!("last seen {}s ago", unseen.unwrap_or_default()))
+    } else {
+        None
+    }
+}
+//logic for writing this node's view of the network to a file, as dot unless the path ends in .json or a format is given
+async fn handle_graph_export(cmd: &str, swarm: &Swarm<RecipeBehaviour>) {
+    let args: Vec<&str> = cmd.strip_prefix("graph export").unwrap_or_default().split_whitespace().collect();
+    let (path, format) = match args.as_slice() {
+        [path] if path.ends_with(".json") => (*path, "json"),
+        [path] => (*path, "dot"),
+        [path, "--format", format] => (*path, *format),
+        _ => {
+            info!("missing arguments - Format: graph export <path> [--format dot|json]");
+            return;
+        }
+    };
+    let format = match format.parse::<graph::GraphFormat>() {
+        Ok(format) => format,
+        Err(e) => {
+            info!("{}", e);
+            return;
+        }
+    };
+    let behaviour = swarm.behaviour();
+    let mut peers: Vec<PeerId> = behaviour.discovered_peers().into_iter().chain(behaviour.peer_info.keys().copied()).collect();
+    peers.sort_by_key(|p| p.to_string());
+    peers.dedup();
+    let local = graph::Node {
+        peer_id: PEER_ID.to_string(),
+        local: true,
+        agent_version: Some(format!("p2p-recipe/{}", env!("CARGO_PKG_VERSION"))),
+        protocol_version: Some(PROTOCOL_VERSION.to_owned()),
+        addrs: swarm.listeners().map(|a| a.to_string()).collect(),
+    };
+    let mut nodes = vec![local];
+    let mut edges = Vec::new();
+    for peer in &peers {
+        let info = behaviour.peer_info.get(peer);
+        let mut addrs: Vec<String> = behaviour.known_peers.get(peer).into_iter().flatten().map(|a| a.to_string()).collect();
+        addrs.sort();
+        nodes.push(graph::Node {
+            peer_id: peer.to_string(),
+            local: false,
+            agent_version: info.map(|i| i.agent_version.clone()),
+            protocol_version: info.map(|i| i.protocol_version.clone()),
+            addrs,
+        });
+        edges.push(graph::Edge {
+            from: PEER_ID.to_string(),
+            to: peer.to_string(),
+            state: if swarm.is_connected(peer) { "connected" } else { "discovered" },
+            in_view: behaviour.view.contains(peer),
+            rtt_ms: behaviour.ping_summary(peer).map(|(last, _)| last.as_millis()),
+        });
+    }
+    let graph = graph::Graph { nodes, edges };
+    let content = match graph::render(&graph, format) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("error exporting the peer graph: {}", e);
+            return;
+        }
+    };
+    match tokio::fs::write(path, content).await {
+        Ok(_) => info!("Exported the peer graph ({} peers) to {}", peers.len(), path),
+        Err(e) => error!("error writing {}: {}", path, e),
+    }
+}
+//logic for reporting the next round trip to a peer, peers that arent connected are dialed first
+fn handle_peer_ping(cmd: &str, swarm: &mut Swarm<RecipeBehaviour>) {
+    let peer_id = cmd.strip_prefix("peer ping").unwrap_or_default().trim();
+    let peer = match peer_id.parse::<PeerId>() {
+        Ok(peer) => peer,
+        Err(_) => {
+            info!("invalid peer id {:?} - Format: peer ping <peer_id>", peer_id);
+            return;
+        }
+    };
+    if let Some((last, average)) = swarm.behaviour().ping_summary(&peer) {
+        info!("Last round trip to {} took {} ms, {} ms on average", peer, last.as_millis(), average.as_millis());
+    }
+    if swarm.is_connected(&peer) {
+        info!("Pinging {}, the next round trip is reported when it completes", peer);
+    } else if swarm.behaviour().known_peers.contains_key(&peer) {
+        info!("Dialing {} to ping it", peer);
+        swarm.behaviour_mut().dialer.enqueue(peer);
+    } else {
+        info!("{}", RecipeError::Network(format!("ping to {} failed: not connected and no known address", peer)));
+        return;
+    }
+    swarm.behaviour_mut().ping_waiters.insert(peer);
+}
+//logic for showing an overview of the node and its effective settings
+fn handle_status(swarm: &Swarm<RecipeBehaviour>, discovery: &str) {
+    let config = match config::get() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("error reading config: {}", e);
+            return;
+        }
+    };
+    info!("Peer ID: {}", PEER_ID.clone());
+    swarm.listeners().for_each(|a| info!("Listening on: {}", a));
+    info!("Discovered peers: {} (via {})", swarm.behaviour().discovered_peers().len(), discovery);
+    match &swarm.behaviour().trusted {
+        Some(trusted) => info!("Trusted peers: {} listed, everyone else is disconnected", trusted.len()),
+        None => info!("Trusted peers: everyone, trusted_peers isnt set"),
+    }
+    info!("Idle connection timeout: {}s", config.idle_connection_timeout_secs);
+    if let Ok(path) = storage::storage_path() {
+        info!("Collection: {} ({})", storage::active_collection().unwrap_or_default(), path);
+    }
+    info!("Topics: {}", swarm.behaviour().topic_summary());
+    match swarm.behaviour().ready_at.checked_duration_since(Instant::now()) {
+        Some(left) => info!("Readiness: starting up, answering peers in {}ms", left.as_millis()),
+        None => info!("Readiness: answering peers"),
+    }
+    info!("Queued broadcasts: {}, held until a peer is connected: {}", swarm.behaviour().broadcasts.queued(), swarm.behaviour().held.len());
+    let (digest_peers, digest_recipes) = swarm.behaviour().digests.summary();
+    info!("Digests: {} recipes from {} peers", digest_recipes, digest_peers);
+    let dialer = &swarm.behaviour().dialer;
+    let (dialing, waiting) = dialer.pending();
+    info!(
+        "Dials: up to {} at once with a {}s timeout, {} in flight, {} waiting",
+        dialer.max_concurrent(),
+        dialer.timeout().as_secs(),
+        dialing,
+        waiting
+    );
+    let view = &swarm.behaviour().view;
+    info!(
+        "Partial view: {} of {} peers, {} left out",
+        view.size(),
+        if view.max() == 0 { "unbounded".to_owned() } else { view.max().to_string() },
+        view.left_out()
+    );
+    let cache = cache::stats();
+    let hit_rate = cache.hit_rate().map(|r| format!("{:.0}%", r * 100.0)).unwrap_or_else(|| "n/a".to_owned());
+    info!(
+        "Cache: {} of {} recipes, hit rate {} ({} hits, {} misses)",
+        cache.entries,
+        if config.cache_max_entries == 0 { "unbounded".to_owned() } else { config.cache_max_entries.to_string() },
+        hit_rate,
+        cache.hits,
+        cache.misses
+    );
+}
+//logic for showing broadcast requests that are still collecting responses
+fn handle_list_requests(swarm: &Swarm<RecipeBehaviour>) {
+    let outstanding = swarm.behaviour().pending_requests.outstanding();
+    info!("Outstanding requests ({})", outstanding.len());
+    outstanding.iter().for_each(|r| info!("{}", r));
+}
+//logic for showing configured relays and active reservations
+fn handle_relay_status(swarm: &Swarm<RecipeBehaviour>) {
+    let relays = config::get().map(|c| c.relay_addresses.clone()).unwrap_or_default();
+    if relays.is_empty() {
+        info!("Relay disabled, add relay_addresses to {} to enable it", config::CONFIG_FILE_PATH);
+        return;
+    }
+    info!("Configured relays: {}", relays.join(", "));
+    let reservations = &swarm.behaviour().relay_reservations;
+    info!("Active reservations ({})", reservations.len());
+    reservations.iter().for_each(|r| info!("{}", r));
+}
+//logic for listing peers
+async fn handle_list_peers(swarm: &mut Swarm<RecipeBehaviour>){
+    info!("Discovered peers:");
+    //discovery shows all discovered nodes, the hash set data structure prevents duplicate values
+    let unique_peers = swarm.behaviour().discovered_peers();
+    //iterates through the hashset and displays the peers
+    unique_peers.iter().for_each(|p| match swarm.behaviour().ping_summary(p) {
+        Some((last, average)) => info!("{} (round trip {} ms, {} ms on average)", p, last.as_millis(), average.as_millis()),
+        None => info!("{}",p),
+    });
+
+}
+//one row of ls p --format, optional values are None until identify or a connection filled them in
+#[derive(Serialize)]
+struct PeerRow {
+    peer_id: String,
+    addrs: Vec<String>,
+    connected: bool,
+    agent_version: Option<String>,
+    last_seen: Option<u64>,
+    //newest round trip time in milliseconds
+    rtt_ms: Option<u128>,
+}
+//logic for listing peers with everything known about them as json or an aligned table
+fn handle_peer_table(cmd: &str, swarm: &Swarm<RecipeBehaviour>) {
+    let format = cmd.strip_prefix("ls p --format").unwrap_or_default().trim();
+    let behaviour = swarm.behaviour();
+    //discovered peers and peers connected to without being discovered, e.g. through a relay
+    let mut peers: Vec<PeerId> = behaviour.discovered_peers().into_iter().chain(behaviour.peer_info.keys().copied()).collect();
+    peers.sort_by_key(|p| p.to_string());
+    peers.dedup();
+    let rows: Vec<PeerRow> = peers
+        .iter()
+        .map(|peer| {
+            let mut addrs: Vec<String> = behaviour.known_peers.get(peer).into_iter().flatten().map(|a| a.to_string()).collect();
+            addrs.sort();
+            PeerRow {
+                peer_id: peer.to_string(),
+                addrs,
+                connected: swarm.is_connected(peer),
+                agent_version: behaviour.peer_info.get(peer).map(|i| i.agent_version.clone()),
+                last_seen: behaviour.last_seen.get(peer).copied(),
+                rtt_ms: behaviour.ping_summary(peer).map(|(last, _)| last.as_millis()),
+            }
+        })
+        .collect();
+    match format {
+        "json" => info!("{}", serde_json::to_string(&rows).expect("can jsonify peers")),
+        "table" => {
+            let mut table = vec![["PEER ID", "ADDRS", "CONNECTED", "AGENT", "LAST SEEN", "RTT"].map(String::from)];
+            table.extend(rows.into_iter().map(|r| {
+                [
+                    r.peer_id,
+                    if r.addrs.is_empty() { "-".to_owned() } else { r.addrs.join(",") },
+                    if r.connected { "yes" } else { "no" }.to_owned(),
+                    r.agent_version.unwrap_or_else(|| "-".to_owned()),
+                    r.last_seen.map(ago).unwrap_or_else(|| "-".to_owned()),
+                    r.rtt_ms.map(|ms| format!("{} ms", ms)).unwrap_or_else(|| "-".to_owned()),
+                ]
+            }));
+            let widths: Vec<usize> = (0..6).map(|c| table.iter().map(|row| row[c].chars().count()).max().unwrap_or(0)).collect();
+            for row in table {
+                let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, w)| format!("{:<w$}", cell, w = w)).collect();
+                info!("{}", cells.join("  ").trim_end());
+            }
+        }
+        _ => info!("unknown format {:?} - Format: ls p --format json|table", format),
+    }
+}
+
+//logic for handling recipe creation
+//create r --publish, or auto_publish for the active collection, shares the recipe straight away once it passed validation and was saved
+//create r --private keeps it to this node whatever the config says
+//offline the recipe is still made public, peers see it once the node is back online
+async fn handle_create_recipes(cmd :&str, swarm: Option<&mut Swarm<RecipeBehaviour>>){
+    //removes the command from the string
+    if let Some(rest) = cmd.strip_prefix("create r"){
+        let trimmed = rest.trim_start();
+        let (choice, rest) = match (trimmed.strip_prefix("--publish"), trimmed.strip_prefix("--private")) {
+            (Some(rest), _) => (Some(true), rest),
+            (_, Some(rest)) => (Some(false), rest),
+            _ => (None, rest),
+        };
+        let publish = choice.unwrap_or_else(|| {
+            let collection = storage::active_collection().unwrap_or_default();
+            config::get().map(|c| c.publishes_by_default(&collection)).unwrap_or(false)
+        });
+        //matches on the error category so a bad storage file doesnt read like a bad command
+        match parse_recipe_args(rest).map(|draft| Recipe { public: publish, ..draft }) {
+            Ok(draft) => match create_new_recipe(draft).await {
+                Ok(recipe) if recipe.public => match swarm {
+                    Some(swarm) => announce_recipe(&recipe, DEFAULT_TOPIC, swarm),
+                    None => info!("Recipe {} is public, peers can see it once the node is online", recipe.id),
+                },
+                Ok(_) => {}
+                Err(RecipeError::Validation(e)) => info!("{}", e),
+                Err(RecipeError::Serde(e)) => error!("storage file is corrupt, recipe not created: {}", e),
+                Err(e) => error!("error creating recipe: {}", e),
+            },
+            Err(e) => info!("{}", e),
+        }
+    }
+}
+//logic for telling peers on a topic about a newly published recipe
+fn announce_recipe(recipe: &Recipe, topic: &str, swarm: &mut Swarm<RecipeBehaviour>) {
+    let announcement = RecipeAnnouncement {
+        published: NameMatch {
+            id: recipe.id,
+            name: recipe.name.clone(),
+        },
+    };
+    let json = serde_json::to_string(&announcement).expect("can jsonify announcement");
+    let announced = swarm.behaviour_mut().announce(Topic::new(topic), json.into_bytes());
+    info!("Publishing recipe {} on {}: {}", recipe.id, topic, announced);
+}
+//logic for creating a recipe
+async fn create_new_recipe(mut recipe: Recipe) -> RecipeResult<Recipe>{
+    validation::validate_recipe(&recipe)?;
+    //Creates a list of recipe structs
+    let mut local_recipes = read_local_recipes().await?;
+    //loops trough local recipes and assigns appropriate id
+    //if no local recipes gives id of 0
+    recipe.id = match local_recipes.iter().max_by_key(|r| r.id){
+        Some(v) => v.id +1,
+        None => 0,
+    };
+    //pushes new recipe to local recipe content
+    local_recipes.push(recipe.clone());
+
+    write_local_recipes(&local_recipes).await?;
+    //feedback to the user
+    info!("Created Recipe:");
+    info!("Name: {:?}", recipe.name);
+    info!("Ingredients: {:?}",recipe.ingredients);
+    info!("Instructions: {:?}",recipe.instructions);
+    Ok(recipe)
+}
+//logic for handling bulk imports from a file
+async fn handle_import_recipes(cmd: &str) {
+    let args = cmd.strip_prefix("import r").unwrap_or_default();
+    let (path, result) = if let Some(path) = flag_values(args, "--cookbook").pop() {
+        let result = import::import_cookbook(&path).await;
+        (path, result)
+    } else if let Some(path) = flag_values(args, "--json").pop() {
+        let validate = !args.split_whitespace().any(|a| a == "--no-validate");
+        let result = import::import_json(&path, validate).await;
+        (path, result)
+    } else if let Some(url) = flag_values(args, "--url").pop() {
+        #[cfg(feature = "http")]
+        let result = web::import_url(&url).await;
+        //without the feature there is nothing to fetch pages with
+        #[cfg(not(feature = "http"))]
+        let result = Err(RecipeError::Validation("import r --url needs the http feature, rebuild with: cargo build --features http".to_owned()));
+        (url, result)
+    } else {
+        info!("missing file - Format: import r --cookbook <path.md> | --json <path.json or .jsonl> [--no-validate] | --url <url>");
+        return;
+    };
+    match result {
+        Ok(report) if !report.schema_errors.is_empty() => {
+            report.schema_errors.iter().for_each(|e| info!("{}", e));
+            info!("Nothing imported from {}, fix the file or import it with --no-validate", path);
+        }
+        Ok(report) => {
+            report.failures.iter().for_each(|f| info!("skipped {}", f));
+            info!("Imported {} recipes from {}, {} skipped", report.imported.len(), path, report.failures.len());
+            report.imported.iter().for_each(|r| info!("{} [{}]", r.name, r.id));
+        }
+        Err(e) => error!("error importing {}: {}", path, e),
+    }
+}
+//logic for merging another storage file into the active collection
+async fn handle_merge(cmd: &str) {
+    let args: Vec<&str> = cmd.strip_prefix("merge").unwrap_or_default().split_whitespace().collect();
+    let (path, dry_run) = match args.as_slice() {
+        [path] => (*path, false),
+        [path, "--dry-run"] | ["--dry-run", path] => (*path, true),
+        _ => {
+            info!("missing file - Format: merge <other.json> [--dry-run]");
+            return;
+        }
+    };
+    match import::merge_file(path, dry_run).await {
+        Ok(report) => {
+            report.failures.iter().for_each(|f| info!("skipped {}", f));
+            let (verb, update) = if dry_run { ("Would add", "Would update") } else { ("Added", "Updated") };
+            report.added.iter().for_each(|r| info!("{} {} [{}]", verb, r.name, r.id));
+            report.updated.iter().for_each(|r| info!("{} {} [{}], the merged copy is newer", update, r.name, r.id));
+            report.renumbered.iter().for_each(|(old, r)| info!("{} {} [{}], id {} is taken by a different recipe", verb, r.name, r.id, old));
+            report.duplicates.iter().for_each(|r| info!("Already have {} [{}]", r.name, r.id));
+            report.outdated.iter().for_each(|r| info!("Already have a newer {} [{}]", r.name, r.id));
+            info!(
+                "{} {} recipes from {}, {} under a new id, {} {}, {} already here, {} older than the local copy, {} invalid{}",
+                verb,
+                report.added.len() + report.renumbered.len(),
+                path,
+                report.renumbered.len(),
+                report.updated.len(),
+                if dry_run { "would be updated" } else { "updated" },
+                report.duplicates.len(),
+                report.outdated.len(),
+                report.failures.len(),
+                if dry_run { ", nothing was written" } else { "" }
+            );
+        }
+        Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+        Err(e) => error!("error merging {}: {}", path, e),
+    }
+}
+//logic for handling recipe edits
+//edits to a public recipe are sent to peers as a patch of the fields that changed, offline they catch up on the next one
+async fn handle_edit_recipe(cmd: &str, swarm: Option<&mut Swarm<RecipeBehaviour>>) {
+    if let Some(rest) = cmd.strip_prefix("edit r") {
+        //id comes first and the rest uses the same format as create r
+        let (id, fields) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+        let id = match id.parse::<usize>() {
+            Ok(id) => id,
+            Err(e) => {
+                info!("Invalid id {}, {} - Format: edit r <id> {}", id, e, RECIPE_ARGS_FORMAT);
+                return;
+            }
+        };
+        let result = match parse_recipe_args(fields) {
+            Ok(draft) => edit_recipe(id, draft).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok((before, after)) => {
+                info!("Updated recipe {}: {:?}", id, after);
+                if let (true, Some(swarm)) = (after.public, swarm) {
+                    announce_patch(&before, &after, swarm);
+                }
+            }
+            Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+            Err(e) => error!("error editing recipe {}: {}", id, e),
+        }
+    }
+}
+//logic for replacing the contents of a recipe while keeping its id and visibility
+//returns the recipe as it was before the edit and after it
+async fn edit_recipe(id: usize, draft: Recipe) -> RecipeResult<(Recipe, Recipe)> {
+    let mut local_recipes = read_local_recipes().await?;
+    let recipe = local_recipes.iter_mut().find(|r| r.id == id).ok_or(RecipeError::NotFound { id })?;
+    let draft = draft.carrying_over(recipe);
+    validation::validate_recipe(&draft)?;
+    let before = std::mem::replace(recipe, draft.clone());
+    write_local_recipes(&local_recipes).await?;
+    undo::record(undo::ChangeKind::Edited, before.clone())?;
+    Ok((before, draft))
+}
+//logic for telling peers which fields of a published recipe changed
+fn announce_patch(before: &Recipe, after: &Recipe, swarm: &mut Swarm<RecipeBehaviour>) {
+    //compared as peers hold them, so changing who a recipe is withheld from sends nothing
+    let (before, after) = (&before.clone().shared(), &after.clone().shared());
+    let patch = patch::changed_fields(before, after).and_then(|changed_fields| {
+        Ok(RecipePatch {
+            author: PEER_ID.to_string(),
+            id: after.id,
+            base: patch::version(before)?,
+            changed_fields,
+        })
+    });
+    let patch = match patch {
+        Ok(patch) if patch.changed_fields.is_empty() => return,
+        Ok(patch) => patch,
+        Err(e) => {
+            error!("error sending the changes to recipe {}: {}", after.id, e);
+            return;
+        }
+    };
+    let json = serde_json::to_string(&patch).expect("can jsonify patch");
+    let announced = swarm.behaviour_mut().announce(TOPIC.clone(), json.into_bytes());
+    info!("Sending the changes to recipe {}: {}", after.id, announced);
+}
+//logic for renaming tags across every local recipe, tag rename <old> <new> or tag merge <a> <b> <into>
+//tags are matched ignoring case, published recipes that changed are sent to peers as patches
+async fn handle_tag(cmd: &str, swarm: Option<&mut Swarm<RecipeBehaviour>>) {
+    if let Some(args) = cmd.strip_prefix("tag apply") {
+        return handle_tag_apply(args, swarm).await;
+    }
+    let args: Vec<&str> = cmd.split_whitespace().skip(1).collect();
+    let (from, into) = match args.as_slice() {
+        ["rename", old, new] => (vec![*old], *new),
+        ["merge", a, b, into] => (vec![*a, *b], *into),
+        ["suggest"] => return handle_tag_suggest("").await,
+        ["suggest", prefix] => return handle_tag_suggest(prefix).await,
+        _ => {
+            info!("Format: tag rename <old> <new>, tag merge <a> <b> <into>, tag apply <tag> --search <query> or tag suggest <prefix>");
+            return;
+        }
+    };
+    match retag_recipes(&from, into).await {
+        Ok(changed) => {
+            info!("Renamed tag {} to {}, {} recipes changed", from.join(" and "), into, changed.len());
+            if let Some(swarm) = swarm {
+                changed.iter().filter(|(_, after)| after.public).for_each(|(before, after)| announce_patch(before, after, swarm));
+            }
+        }
+        Err(e) => error!("error renaming tag {}: {}", from.join(" and "), e),
+    }
+}
+//logic for adding a tag to every local recipe matching a search, or taking it off them with --remove
+//--search matches any text in the recipe and --ingredient its ingredients, given together or repeated a recipe has to match all of them
+async fn handle_tag_apply(args: &str, swarm: Option<&mut Swarm<RecipeBehaviour>>) {
+    const FORMAT: &str = "Format: tag apply <tag> [--search <query>] [--ingredient <name>...] [--remove]";
+    let tag = args.split("--").next().unwrap_or_default().trim();
+    let queries = flag_values(args, "--search");
+    let ingredients = flag_values(args, "--ingredient");
+    let remove = args.split_whitespace().any(|a| a == "--remove");
+    if tag.is_empty() {
+        info!("missing tag - {}", FORMAT);
+        return;
+    }
+    if queries.is_empty() && ingredients.is_empty() {
+        info!("missing --search or --ingredient, tagging every recipe isnt done by accident - {}", FORMAT);
+        return;
+    }
+    let matches = |r: &Recipe| queries.iter().all(|q| r.matches_text(q)) && ingredients.iter().all(|i| r.find_ingredient(i).is_some());
+    match apply_tag(tag, remove, matches).await {
+        Ok((matched, changed)) => {
+            let unchanged = matched - changed.len();
+            if remove {
+                info!("Removed tag {} from {} recipes, {} of the {} matching didnt have it", tag, changed.len(), unchanged, matched);
+            } else {
+                info!("Tagged {} recipes with {}, {} of the {} matching already had it", changed.len(), tag, unchanged, matched);
+            }
+            if let Some(swarm) = swarm {
+                changed.iter().filter(|(_, after)| after.public).for_each(|(before, after)| announce_patch(before, after, swarm));
+            }
+        }
+        Err(e) => error!("error applying tag {}: {}", tag, e),
+    }
+}
+//adds the tag to the matching recipes that dont have it yet in any case, or removes it from them
+//returns how many recipes matched and the ones that changed as they were before and after, written in one go
+async fn apply_tag<F: Fn(&Recipe) -> bool>(tag: &str, remove: bool, matches: F) -> RecipeResult<(usize, Vec<(Recipe, Recipe)>)> {
+    let mut local_recipes = read_local_recipes().await?;
+    let mut matched = 0;
+    let mut changed = Vec::new();
+    for recipe in local_recipes.iter_mut().filter(|r| matches(r)) {
+        matched += 1;
+        let has = recipe.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        if has != remove {
+            continue;
+        }
+        let before = recipe.clone();
+        if remove {
+            recipe.tags.retain(|t| !t.eq_ignore_ascii_case(tag));
+        } else {
+            recipe.tags.push(tag.to_owned());
+        }
+        changed.push((before, recipe.clone()));
+    }
+    if !changed.is_empty() {
+        write_local_recipes(&local_recipes).await?;
+    }
+    Ok((matched, changed))
+}
+//logic for listing the existing tags starting with a prefix and how many recipes use each
+async fn handle_tag_suggest(prefix: &str) {
+    match read_local_recipes().await {
+        Ok(recipes) => {
+            let wanted = prefix.to_lowercase();
+            let matching: Vec<(String, usize)> = tag_counts(&recipes).into_iter().filter(|(tag, _)| tag.to_lowercase().starts_with(&wanted)).collect();
+            if matching.is_empty() {
+                info!("No tags start with {:?}", prefix);
+            }
+            matching.iter().for_each(|(tag, count)| info!("{} ({} recipes)", tag, count));
+        }
+        Err(e) => error!("error fetching local recipes: {}", e),
+    }
+}
+//every tag in use with how many recipes have it, most used first, tags differing only in case count as one
+//spelled the way the first recipe with it spells it
+fn tag_counts(recipes: &[Recipe]) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for tag in recipes.iter().flat_map(|r| r.tags.iter()) {
+        match counts.iter_mut().find(|(t, _)| t.eq_ignore_ascii_case(tag)) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((tag.clone(), 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.to_lowercase().cmp(&b.0.to_lowercase())));
+    counts
+}
+//keeps the tags tab completion offers in step with the recipes, only a terminal has completion
+async fn refresh_tag_completion() {
+    if !prompt::is_interactive() {
+        return;
+    }
+    match storage::read_shared_recipes().await {
+        Ok(recipes) => prompt::set_known_tags(tag_counts(&recipes).into_iter().map(|(tag, _)| tag).collect()),
+        Err(e) => error!("error reading tags for completion: {}", e),
+    }
+}
+//logic for swapping tags for another one, a recipe ending up with the same tag twice keeps it once where it first appeared
+//returns every recipe that changed as it was before and after
+async fn retag_recipes(from: &[&str], into: &str) -> RecipeResult<Vec<(Recipe, Recipe)>> {
+    let mut local_recipes = read_local_recipes().await?;
+    let mut changed = Vec::new();
+    for recipe in local_recipes.iter_mut() {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &recipe.tags {
+            let tag = if from.iter().any(|f| f.eq_ignore_ascii_case(tag)) { into } else { tag.as_str() };
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.to_owned());
+            }
+        }
+        if tags != recipe.tags {
+            let before = recipe.clone();
+            recipe.tags = tags;
+            changed.push((before, recipe.clone()));
+        }
+    }
+    if !changed.is_empty() {
+        write_local_recipes(&local_recipes).await?;
+    }
+    Ok(changed)
+}
+//logic for writing buffered recipes to disk right away
+async fn handle_save() {
+    match storage::flush().await {
+        Ok(true) => info!("Saved recipes"),
+        Ok(false) => info!("Nothing to save"),
+        Err(e) => error!("error saving recipes: {}", e),
+    }
+}
+//logic for changing one config key, e.g. pointing storage_file somewhere writable before running save
+//settings only read at startup, like listen_port, take effect on the next start
+async fn handle_config_set(cmd: &str) {
+    let (key, value) = match cmd.strip_prefix("config set").map(str::trim).and_then(|rest| rest.split_once(' ')) {
+        Some((key, value)) => (key, value.trim()),
+        None => {
+            info!("missing key or value - Format: config set <key> <value>");
+            return;
+        }
+    };
+    match config::set(key, value).await {
+        Ok(_) => info!("Set {} to {}", key, value),
+        Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+        //the change is made before the file is written, so it still holds until the node restarts
+        Err(e) => error!("error saving the config, {} is only changed until restart: {}", key, e),
+    }
+}
+//logic for handling the collection commands
+//collections are local only, peers are always answered from whichever collection is active
+async fn handle_collection(cmd: &str, swarm: Option<&mut Swarm<RecipeBehaviour>>) {
+    let args: Vec<&str> = cmd.split_whitespace().skip(1).collect();
+    match (args.as_slice(), swarm) {
+        (["publish", _, ..] | ["fetch", ..], None) => info!("{}", RecipeError::Offline(cmd.to_owned())),
+        (["publish", name, ids @ ..], Some(swarm)) if ids.len() <= 1 => publish_manifest(name, ids.first().copied(), swarm).await,
+        (["fetch", peer_id, name], Some(swarm)) => match peer_id.parse::<PeerId>() {
+            Ok(peer) => {
+                let request_id = swarm.behaviour_mut().fetch.send_request(&peer, FetchRequest::Manifest { name: name.to_string() });
+                swarm.behaviour_mut().manifest_requests.insert(request_id, ManifestRequest::Manifest);
+                info!("Fetching collection manifest {} from {}", name, peer);
+            }
+            Err(_) => info!("invalid peer id {:?} - Format: collection fetch <peer_id> <name>", peer_id),
+        },
+        (["list"], _) => match (storage::collections(), storage::active_collection()) {
+            (Ok(names), Ok(active)) => {
+                info!("Collections ({})", names.len());
+                names.iter().for_each(|n| info!("{} {}", if *n == active { "*" } else { " " }, n));
+            }
+            (Err(e), _) | (_, Err(e)) => error!("error listing collections: {}", e),
+        },
+        (["create", name], _) => match storage::create_collection(name).await {
+            Ok(()) => info!("Created collection {}, switch to it with: collection switch {}", name, name),
+            Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+            Err(e) => error!("error creating collection {}: {}", name, e),
+        },
+        (["switch", name], _) => match storage::switch_collection(name).await {
+            Ok(()) => info!("Switched to collection {}", name),
+            Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+            Err(e) => error!("error switching to collection {}: {}", name, e),
+        },
+        _ => info!("unknown collection command - Format: collection list|create <name>|switch <name>|publish <name> [<id1,id2,...>]|fetch <peer_id> <name>"),
+    }
+}
+//logic for publishing a manifest of local recipes, ids create or replace it and without them the saved one is announced again
+async fn publish_manifest(name: &str, ids: Option<&str>, swarm: &mut Swarm<RecipeBehaviour>) {
+    let result = match ids {
+        Some(ids) => match ids.split(',').map(|id| id.trim().parse::<usize>()).collect::<Result<Vec<_>, _>>() {
+            Ok(ids) => build_manifest(name, &ids).await,
+            Err(e) => Err(RecipeError::Validation(format!("invalid id in {}, {} - Format: collection publish <name> <id1,id2,...>", ids, e))),
+        },
+        None => manifests::get(name)
+            .await
+            .and_then(|m| m.ok_or_else(|| RecipeError::Validation(format!("no collection manifest named {}, publish it with a list of ids first", name)))),
+    };
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => {
+            info!("{}", e);
+            return;
+        }
+        Err(e) => {
+            error!("error publishing collection {}: {}", name, e);
+            return;
+        }
+    };
+    let json = serde_json::to_string(&ManifestAnnouncement { manifest: manifest.clone() }).expect("can jsonify manifest");
+    let announced = swarm.behaviour_mut().announce(TOPIC.clone(), json.into_bytes());
+    info!("Publishing collection {} with {} recipes: {}", manifest.name, manifest.entries.len(), announced);
+}
+//logic for saving a manifest of local recipes in the given order
+async fn build_manifest(name: &str, ids: &[usize]) -> RecipeResult<Manifest> {
+    let recipes = read_local_recipes().await?;
+    let manifest = Manifest::from_recipes(name, ids, &recipes)?;
+    //private and archived recipes stay in the manifest but peers will find them missing
+    for recipe in recipes.iter().filter(|r| ids.contains(&r.id) && !r.is_served()) {
+        info!("recipe {} is not public, peers fetching {} will see it as missing", recipe.id, name);
+    }
+    manifests::save(&manifest).await?;
+    Ok(manifest)
+}
+//logic for handling the interest filter commands
+async fn handle_interest(cmd: &str) {
+    let result = if let Some(criterion) = cmd.strip_prefix("interest add") {
+        let criterion = criterion.trim().to_owned();
+        if criterion.is_empty() {
+            info!("missing criterion - Format: interest add <tag:x|category:x|dietary:x|x>");
+            return;
+        }
+        config::update(|c| {
+            if !c.interests.contains(&criterion) {
+                c.interests.push(criterion);
+            }
+        })
+        .await
+    } else if cmd.trim() == "interest clear" {
+        config::update(|c| c.interests.clear()).await
+    } else if cmd.trim() == "interest" {
+        config::get()
+    } else {
+        info!("unknown interest command - Format: interest [add <criterion>|clear]");
+        return;
+    };
+    match result {
+        Ok(c) if c.interests.is_empty() => info!("No interests set, all recipes are kept"),
+        Ok(c) => info!("Interests: {}", c.interests.join(", ")),
+        Err(e) => error!("error updating interests: {}", e),
+    }
+}
+//resolves the "local" shorthand to this nodes peer id
+fn resolve_author(peer_id: &str) -> String {
+    if peer_id == "local" {
+        PEER_ID.to_string()
+    } else {
+        peer_id.to_owned()
+    }
+}
+//logic for handling the local notes commands
+async fn handle_note(cmd: &str) {
+    if let Some(rest) = cmd.strip_prefix("note add") {
+        //peer id, recipe id and then the rest of the line is the note
+        let mut args = rest.trim().splitn(3, ' ');
+        match (args.next(), args.next().map(|id| id.parse::<usize>()), args.next()) {
+            (Some(peer_id), Some(Ok(id)), Some(text)) if !text.trim().is_empty() => {
+                match notes::add_note(&resolve_author(peer_id), id, text.trim()).await {
+                    Ok(_) => info!("Added note to recipe {} from {}", id, peer_id),
+                    Err(e) => error!("error adding note: {}", e),
+                }
+            }
+            _ => info!("invalid arguments - Format: note add <peer_id|local> <recipe_id> <text>"),
+        }
+    } else if let Some(rest) = cmd.strip_prefix("note list") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        match (args.first(), args.get(1).map(|id| id.parse::<usize>())) {
+            (Some(peer_id), Some(Ok(id))) => match notes::notes_for(&resolve_author(peer_id), id).await {
+                Ok(notes) => {
+                    info!("Notes for recipe {} from {} ({})", id, peer_id, notes.len());
+                    notes.iter().for_each(|n| info!("- {}", n.text));
+                }
+                Err(e) => error!("error fetching notes: {}", e),
+            },
+            _ => info!("invalid arguments - Format: note list <peer_id|local> <recipe_id>"),
+        }
+    } else {
+        info!("unknown note command - Format: note add|list <peer_id|local> <recipe_id> [text]");
+    }
+}
+//logic for counting that the user made a local or cached recipe
+async fn handle_made(cmd: &str) {
+    let args: Vec<&str> = cmd.split_whitespace().skip(1).collect();
+    let (peer_id, id) = match args.as_slice() {
+        [peer_id, id] => match id.parse::<usize>() {
+            Ok(id) => (*peer_id, id),
+            Err(e) => {
+                info!("Invalid id {}, {} - Format: made <peer_id|local> <recipe_id>", id, e);
+                return;
+            }
+        },
+        _ => {
+            info!("invalid arguments - Format: made <peer_id|local> <recipe_id>");
+            return;
+        }
+    };
+    //only recipes that exist locally or in the cache can be counted
+    let reference = if peer_id == "local" { id.to_string() } else { format!("{}/{}", peer_id, id) };
+    let result = match lookup_recipe(&reference).await {
+        Ok(recipe) => made::record_made(&resolve_author(peer_id), id).await.map(|count| (recipe, count)),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok((recipe, count)) => info!("Made {} {} times", recipe.name, count.times_made),
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+        Err(e) => error!("error counting recipe {}: {}", reference, e),
+    }
+}
+//logic for showing a single local recipe along with its notes
+async fn handle_show_recipe(cmd: &str) {
+    if let Some(rest) = cmd.strip_prefix("show r") {
+        let id = match rest.trim().parse::<usize>() {
+            Ok(id) => id,
+            Err(e) => {
+                info!("Invalid id {}, {}", rest.trim(), e);
+                return;
+            }
+        };
+        let recipe = match read_local_recipes().await {
+            Ok(recipes) => recipes.into_iter().find(|r| r.id == id),
+            Err(e) => {
+                error!("error fetching local recipes: {}", e);
+                return;
+            }
+        };
+        match recipe {
+            Some(r) => {
+                info!("{:?}", r);
+                //recipes split into sections get their ingredients listed under each section's name
+                if r.structured_ingredients.iter().any(|i| i.group.is_some()) {
+                    info!("Ingredients:");
+                    for group in ingredients::groups(&r.structured_ingredients) {
+                        if let Some(name) = group.name {
+                            info!("{}:", name);
+                        }
+                        group.items.iter().for_each(|i| info!("- {}", i));
+                    }
+                }
+                if let Some(time) = r.time_breakdown() {
+                    info!("Time: {}", time);
+                }
+                if let Some(url) = &r.image_url {
+                    match images::cached(url) {
+                        Ok(Some(path)) => info!("Image: {} (saved as {})", url, path.display()),
+                        Ok(None) => info!("Image: {}, image fetch r {} saves a copy", url, r.id),
+                        Err(e) => error!("error looking for the saved image: {}", e),
+                    }
+                }
+                match made::counts_for(&PEER_ID.to_string()).await {
+                    Ok(counts) => match counts.iter().find(|c| c.recipe_id == id) {
+                        Some(count) => info!("Made {} times, last {}", count.times_made, ago(count.last_made)),
+                        None => info!("Not made yet"),
+                    },
+                    Err(e) => error!("error fetching how often the recipe was made: {}", e),
+                }
+                if !r.substitutions.is_empty() {
+                    info!("Substitutions:");
+                    r.substitutions.iter().for_each(|s| info!("- {}", s));
+                }
+                match notes::notes_for(&PEER_ID.to_string(), id).await {
+                    Ok(notes) if notes.is_empty() => {}
+                    Ok(notes) => {
+                        info!("Notes:");
+                        notes.iter().for_each(|n| info!("- {}", n.text));
+                    }
+                    Err(e) => error!("error fetching notes: {}", e),
+                }
+            }
+            None => info!("{}", RecipeError::NotFound { id }),
+        }
+    }
+}
+//collects the values given to a repeatable flag, a value runs until the next flag
+fn flag_values(args: &str, flag: &str) -> Vec<String> {
+    args.split("--")
+        .filter_map(|part| part.trim().strip_prefix(flag.trim_start_matches('-')))
+        .filter(|value| value.starts_with(' '))
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+//logic for finding local recipes that contain all of the given ingredients
+async fn handle_find_recipes(cmd: &str) {
+    let args = cmd.strip_prefix("find").unwrap_or_default();
+    let wanted = flag_values(args, "--ingredient");
+    //with --allow-subs an ingredient also matches recipes where it can stand in for something
+    let allow_subs = args.split_whitespace().any(|a| a == "--allow-subs");
+    if wanted.is_empty() {
+        info!("missing ingredient - Format: find --ingredient <name> [--ingredient <name>...] [--allow-subs]");
+        return;
+    }
+    let recipes = match read_local_recipes().await {
+        Ok(recipes) => recipes,
+        Err(e) => {
+            error!("error fetching local recipes: {}", e);
+            return;
+        }
+    };
+    let mut found = 0;
+    for recipe in &recipes {
+        //every requested ingredient has to be in the recipe
+        let matched: Option<Vec<String>> = wanted
+            .iter()
+            .map(|w| match recipe.find_ingredient(w) {
+                Some(m) => Some(format!("{} ({})", w, m)),
+                None if allow_subs => recipe.find_substitute(w).map(|s| format!("{} (instead of {})", w, s.original)),
+                None => None,
+            })
+            .collect();
+        if let Some(matched) = matched {
+            found += 1;
+            info!("{} [{}] matched: {}", recipe.name, recipe.id, matched.join(", "));
+        }
+    }
+    info!("Found {} recipes containing {}", found, wanted.join(" and "));
+}
+//logic for pinning or unpinning a cached remote recipe
+async fn handle_pin_recipe(cmd: &str, pinned: bool) {
+    let (prefix, verb) = if pinned { ("pin r", "pin") } else { ("unpin r", "unpin") };
+    let args: Vec<&str> = cmd.strip_prefix(prefix).unwrap_or_default().split_whitespace().collect();
+    let (peer_id, id) = match (args.first(), args.get(1).map(|id| id.parse::<usize>())) {
+        (Some(peer_id), Some(Ok(id))) => (peer_id.to_string(), id),
+        _ => {
+            info!("invalid arguments - Format: {} <peer_id> <recipe_id>", prefix);
+            return;
+        }
+    };
+    match cache::set_pinned(&peer_id, id, pinned).await {
+        Ok(()) => info!("{}ned recipe {} from {}", verb, id, peer_id),
+        Err(RecipeError::NotFound { id }) => info!("recipe {} from {} is not cached, fetch it first", id, peer_id),
+        Err(e) => error!("error trying to {} recipe: {}", verb, e),
+    }
+}
+//logic for listing pinned remote recipes
+async fn handle_list_pinned() {
+    match cache::pinned().await {
+        Ok(pinned) => {
+            info!("Pinned recipes ({})", pinned.len());
+            pinned.iter().for_each(|p| info!("{} [{}] from {}", p.recipe.name, p.recipe.id, p.author));
+        }
+        Err(e) => error!("error fetching pinned recipes: {}", e),
+    }
+}
+//how many ingredients report ingredients lists unless --top says otherwise
+const DEFAULT_REPORT_TOP: usize = 10;
+//logic for reporting the most used ingredients across every local recipe, archived ones included
+//recipes that only have the plain ingredients string can't be matched up reliably so they are skipped and counted
+async fn handle_ingredient_report(cmd: &str) {
+    let args: Vec<&str> = cmd.split_whitespace().skip(2).collect();
+    let top = match args.as_slice() {
+        [] => DEFAULT_REPORT_TOP,
+        ["--top", n] => match n.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                info!("invalid count {:?}, expected a number above 0 - Format: report ingredients [--top <n>]", n);
+                return;
+            }
+        },
+        _ => {
+            info!("unknown option - Format: report ingredients [--top <n>]");
+            return;
+        }
+    };
+    let recipes = match read_local_recipes().await {
+        Ok(recipes) => recipes,
+        Err(e) => {
+            error!("error fetching local recipes: {}", e);
+            return;
+        }
+    };
+    let (structured, legacy): (Vec<&Recipe>, Vec<&Recipe>) = recipes.iter().partition(|r| !r.structured_ingredients.is_empty());
+    let usage = ingredients::usage(structured.iter().map(|r| r.structured_ingredients.as_slice()));
+    info!("Top ingredients across {} recipes ({} distinct)", structured.len(), usage.len());
+    for entry in usage.iter().take(top) {
+        let amount = match (entry.total, &entry.unit) {
+            (Some(total), Some(unit)) => format!(", {} {} in total", total, unit),
+            (Some(total), None) => format!(", {} in total", total),
+            (None, _) => String::new(),
+        };
+        info!("{} - used {} times{}", entry.name, entry.uses, amount);
+    }
+    if !legacy.is_empty() {
+        info!("Skipped {} recipes that only have free-text ingredients, edit them to give them structured ingredients", legacy.len());
+    }
+}
+//logic for inspecting and clearing the cache of recipes from other peers
+async fn handle_cache(cmd: &str) {
+    let args: Vec<&str> = cmd.split_whitespace().skip(1).collect();
+    let author = match args.as_slice() {
+        ["list"] => {
+            match cache::entries().await {
+                Ok(entries) => {
+                    info!("Cached recipes ({})", entries.len());
+                    entries
+                        .iter()
+                        .for_each(|e| {
+                            let topic = if e.topic == DEFAULT_TOPIC { String::new() } else { format!(" on {}", e.topic) };
+                            info!("{} [{}] from {}{}{}", e.recipe.name, e.recipe.id, e.author, topic, if e.pinned { " (pinned)" } else { "" })
+                        });
+                }
+                Err(e) => error!("error reading the cache: {}", e),
+            }
+            return;
+        }
+        ["clear"] => None,
+        ["clear", "--peer", peer_id] => Some(resolve_author(peer_id)),
+        _ => {
+            info!("unknown cache command - Format: cache list|clear [--peer <peer_id>]");
+            return;
+        }
+    };
+    match cache::clear(author.as_deref()).await {
+        Ok((removed, freed)) => info!("Removed {} cached recipes, freed {} bytes, pinned recipes were kept", removed, freed),
+        Err(e) => error!("error clearing the cache: {}", e),
+    }
+}
+//looks up a recipe by "<id>" for a local one or "<peer_id>/<id>" for a cached remote one
+async fn lookup_recipe(reference: &str) -> RecipeResult<Recipe> {
+    let invalid = || RecipeError::Validation(format!("invalid recipe {:?} - Format: <id> or <peer_id>/<id>", reference));
+    match reference.split_once('/') {
+        Some((author, id)) => {
+            let id = id.parse::<usize>().map_err(|_| invalid())?;
+            let cached = cache::get(&resolve_author(author), id).await?;
+            cached.map(|c| c.recipe).ok_or(RecipeError::NotFound { id })
+        }
+        None => {
+            let id = reference.parse::<usize>().map_err(|_| invalid())?;
+            let recipes = read_local_recipes().await?;
+            recipes.into_iter().find(|r| r.id == id).ok_or(RecipeError::NotFound { id })
+        }
+    }
+}
+//logic for downloading the image a recipe links to into image_dir, image fetch r <id|peer_id/id>
+async fn handle_image_fetch(cmd: &str) {
+    let reference = cmd.strip_prefix("image fetch r").unwrap_or_default().trim();
+    if reference.is_empty() {
+        info!("missing recipe - Format: image fetch r <id|peer_id/id>");
+        return;
+    }
+    let recipe = match lookup_recipe(reference).await {
+        Ok(recipe) => recipe,
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => {
+            info!("{}", e);
+            return;
+        }
+        Err(e) => {
+            error!("error fetching recipe {}: {}", reference, e);
+            return;
+        }
+    };
+    let url = match recipe.image_url {
+        Some(url) => url,
+        None => {
+            info!("{} has no image url, add one as the last field of edit r", recipe.name);
+            return;
+        }
+    };
+    #[cfg(feature = "http")]
+    let result = web::fetch_image(&url).await;
+    //without the feature there is nothing to download images with
+    #[cfg(not(feature = "http"))]
+    let result: RecipeResult<std::path::PathBuf> =
+        Err(RecipeError::Validation("image fetch r needs the http feature, rebuild with: cargo build --features http".to_owned()));
+    match result {
+        Ok(path) => info!("Saved the image of {} to {}", recipe.name, path.display()),
+        Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+        Err(e) => error!("error fetching the image of {} from {}: {}", recipe.name, url, e),
+    }
+}
+//logic for writing a recipe out in another recipe app's format
+async fn handle_export_recipe(cmd: &str) {
+    let args: Vec<&str> = cmd.strip_prefix("export r").unwrap_or_default().split_whitespace().collect();
+    let (reference, format, path) = match args.as_slice() {
+        [reference, "--format", format, path] => (*reference, *format, *path),
+        _ => {
+            info!("missing arguments - Format: export r <id|peer_id/id> --format paprika|mealmaster <path>");
+            return;
+        }
+    };
+    let result = match format.parse::<export::ExportFormat>() {
+        Ok(format) => match lookup_recipe(reference).await {
+            Ok(recipe) => export::render(&recipe, format),
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    let content = match result {
+        Ok(content) => content,
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => {
+            info!("{}", e);
+            return;
+        }
+        Err(e) => {
+            error!("error exporting recipe {}: {}", reference, e);
+            return;
+        }
+    };
+    match tokio::fs::write(path, content).await {
+        Ok(_) => info!("Exported recipe {} to {}", reference, path),
+        Err(e) => error!("error writing {}: {}", path, e),
+    }
+}
+//logic for comparing two recipes field by field and step by step
+async fn handle_diff_recipes(cmd: &str) {
+    let args: Vec<&str> = cmd.strip_prefix("diff r").unwrap_or_default().split_whitespace().collect();
+    if args.len() != 2 {
+        info!("invalid arguments - Format: diff r <id|peer_id/id> <id|peer_id/id>");
+        return;
+    }
+    let (old, new) = match (lookup_recipe(args[0]).await, lookup_recipe(args[1]).await) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => {
+            info!("{}", e);
+            return;
+        }
+    };
+    info!("Diff {} -> {}", args[0], args[1]);
+    //single value fields only show when they changed
+    let field = |label: &str, old: String, new: String| {
+        if old != new {
+            info!("{}: {:?} -> {:?}", label, old, new);
+        }
+    };
+    field("name", old.name.clone(), new.name.clone());
+    field("tags", old.tags.join(", "), new.tags.join(", "));
+    field("category", old.category.clone().unwrap_or_default(), new.category.clone().unwrap_or_default());
+    field("dietary", old.dietary.join(", "), new.dietary.join(", "));
+    let sections = [
+        ("ingredients", old.ingredient_lines(), new.ingredient_lines()),
+        ("instructions", old.steps(), new.steps()),
+    ];
+    for (label, old_lines, new_lines) in &sections {
+        if old_lines == new_lines {
+            continue;
+        }
+        info!("{}:", label);
+        for line in diff::diff_lines(old_lines, new_lines) {
+            match line {
+                diff::DiffLine::Same(l) => info!("  {}", l),
+                diff::DiffLine::Removed(l) => info!("- {}", l),
+                diff::DiffLine::Added(l) => info!("+ {}", l),
+            }
+        }
+    }
+}
+//logic for scaling a recipe to a number of servings or to a yield, the stored recipe is left as is
+async fn handle_scale_recipe(cmd: &str) {
+    let args = cmd.strip_prefix("scale r").unwrap_or_default();
+    let reference = args.split("--").next().unwrap_or_default().trim();
+    let recipe = match lookup_recipe(reference).await {
+        Ok(recipe) => recipe,
+        Err(e @ RecipeError::Validation(_)) => {
+            info!("{} - Format: {}", e, SCALE_ARGS_FORMAT);
+            return;
+        }
+        Err(e @ RecipeError::NotFound { .. }) => {
+            info!("{}", e);
+            return;
+        }
+        Err(e) => {
+            error!("error fetching recipe {}: {}", reference, e);
+            return;
+        }
+    };
+    let (factor, target) = match scale_factor(&recipe, args) {
+        Ok(scale) => scale,
+        Err(e) => {
+            info!("{}", e);
+            return;
+        }
+    };
+    info!("{} scaled to {} (x{:.2})", recipe.name, target, factor);
+    if recipe.structured_ingredients.is_empty() {
+        info!("no structured ingredients to scale: {}", recipe.ingredients);
+    }
+    recipe.structured_ingredients.iter().for_each(|i| info!("- {}", i.scaled(factor)));
+}
+//logic for estimating what a serving of a recipe holds from its structured ingredients, the stored recipe is left as is
+async fn handle_nutrition(cmd: &str) {
+    let reference = cmd.strip_prefix("nutrition r").unwrap_or_default().trim();
+    let estimate = match lookup_recipe(reference).await {
+        Ok(recipe) => nutrition::table().await.map(|table| (nutrition::estimate(&recipe.structured_ingredients, &table), recipe)),
+        Err(e) => Err(e),
+    };
+    let (estimate, recipe) = match estimate {
+        Ok(estimate) => estimate,
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => {
+            info!("{}", e);
+            return;
+        }
+        Err(e) => {
+            error!("error estimating nutrition for {}: {}", reference, e);
+            return;
+        }
+    };
+    if recipe.structured_ingredients.is_empty() {
+        info!("no structured ingredients to estimate: {}", recipe.ingredients);
+        return;
+    }
+    let (estimate, basis) = match recipe.servings {
+        Some(servings) => (estimate.per_serving(servings), format!("per serving of {}", servings)),
+        None => (estimate, "for the whole recipe, it has no servings".to_owned()),
+    };
+    info!("{} [{}] {}", recipe.name, recipe.id, basis);
+    info!(
+        "calories {:.0} kcal, protein {:.1} g, carbs {:.1} g, fat {:.1} g",
+        estimate.calories, estimate.protein, estimate.carbs, estimate.fat
+    );
+    info!("coverage {:.0}% ({} of {} ingredients)", estimate.coverage(), estimate.estimated, recipe.structured_ingredients.len());
+    estimate.not_estimated.iter().for_each(|(ingredient, reason)| info!("not estimated: {} ({})", ingredient, reason));
+}
+//format of the scale r command
+const SCALE_ARGS_FORMAT: &str = "scale r <id|peer_id/id> --servings <n> | --yield <amount> <unit>";
+//works out how much to multiply by and what the target was, the flag picks servings or yield as the basis
+fn scale_factor(recipe: &Recipe, args: &str) -> RecipeResult<(f64, String)> {
+    match (flag_values(args, "--servings").pop(), flag_values(args, "--yield").pop()) {
+        (Some(servings), None) => {
+            let target = servings
+                .parse::<u32>()
+                .ok()
+                .filter(|t| *t > 0)
+                .ok_or_else(|| RecipeError::Validation(format!("invalid servings {:?}", servings)))?;
+            let current = recipe.servings.filter(|c| *c > 0).ok_or_else(|| {
+                RecipeError::Validation(format!("{} has no servings, scale it with --yield instead", recipe.name))
+            })?;
+            Ok((f64::from(target) / f64::from(current), format!("{} servings", target)))
+        }
+        (None, Some(target)) => {
+            let target = ingredients::parse_yield(&target)?;
+            let current = recipe.recipe_yield.as_ref().ok_or_else(|| {
+                RecipeError::Validation(format!("{} has no yield, scale it with --servings instead", recipe.name))
+            })?;
+            Ok((ingredients::yield_factor(current, &target)?, target.to_string()))
+        }
+        _ => Err(RecipeError::Validation(format!("pick one basis - Format: {}", SCALE_ARGS_FORMAT))),
+    }
+}
+//logic for setting what a local recipe yields by weight or volume
+async fn handle_set_yield(cmd: &str) {
+    let rest = cmd.strip_prefix("yield r").unwrap_or_default().trim();
+    let (id, amount) = rest.split_once(' ').unwrap_or((rest, ""));
+    let id = match id.parse::<usize>() {
+        Ok(id) => id,
+        Err(e) => {
+            info!("Invalid id {}, {} - Format: yield r <id> <amount> <unit>", id, e);
+            return;
+        }
+    };
+    let result = match ingredients::parse_yield(amount) {
+        Ok(recipe_yield) => set_yield(id, recipe_yield).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(recipe_yield) => info!("recipe {} now yields {}", id, recipe_yield),
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+        Err(e) => error!("error updating recipe {}: {}", id, e),
+    }
+}
+//logic for storing the yield of a recipe
+async fn set_yield(id: usize, recipe_yield: Yield) -> RecipeResult<Yield> {
+    let mut local_recipes = read_local_recipes().await?;
+    let recipe = local_recipes.iter_mut().find(|r| r.id == id).ok_or(RecipeError::NotFound { id })?;
+    recipe.recipe_yield = Some(recipe_yield.clone());
+    write_local_recipes(&local_recipes).await?;
+    Ok(recipe_yield)
+}
+//logic for printing a recipe as a plaintext card
+async fn handle_recipe_card(cmd: &str) {
+    let reference = cmd.strip_prefix("card r").unwrap_or_default().trim();
+    match lookup_recipe(reference).await {
+        Ok(recipe) => {
+            let width = config::get().map(|c| c.card_width).unwrap_or(48);
+            card::render(&recipe, width).iter().for_each(|line| info!("{}", line));
+        }
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+        Err(e) => error!("error fetching recipe {}: {}", reference, e),
+    }
+}
+//logic for walking through a local or cached recipe one step at a time
+async fn handle_cook_recipe(cmd: &str) {
+    let reference = cmd.strip_prefix("cook r").unwrap_or_default().trim();
+    match lookup_recipe(reference).await {
+        Ok(recipe) => cook::start(&recipe),
+        Err(e @ RecipeError::Validation(_)) | Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+        Err(e) => error!("error fetching recipe {}: {}", reference, e),
+    }
+}
+//logic for producing a shareable link to a local recipe
+async fn handle_share_recipe(cmd: &str) {
+    if let Some(rest) = cmd.strip_prefix("share r") {
+        let id = match rest.trim().parse::<usize>() {
+            Ok(id) => id,
+            Err(e) => {
+                info!("Invalid id {}, {}", rest.trim(), e);
+                return;
+            }
+        };
+        match read_local_recipes().await {
+            Ok(recipes) => match recipes.iter().find(|r| r.id == id) {
+                //only public recipes will be served to whoever opens the link
+                Some(r) if !r.public => info!("recipe {} is private, publish it first with: publish r {}", id, id),
+                Some(r) if r.archived => info!("recipe {} is archived, unarchive it first with: unarchive r {}", id, id),
+                Some(_) => info!("{}", fetch::share_link(&PEER_ID, id)),
+                None => info!("{}", RecipeError::NotFound { id }),
+            },
+            Err(e) => error!("error fetching local recipes: {}", e),
+        }
+    }
+}
+//logic for opening a shared link by fetching the recipe from its author
+async fn handle_open_link(cmd: &str, swarm: &mut Swarm<RecipeBehaviour>) {
+    let link = cmd.strip_prefix("open").unwrap_or_default().trim();
+    let (author, id) = match fetch::parse_link(link) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            info!("{}", e);
+            return;
+        }
+    };
+    //links to our own recipes dont need the network
+    if author == *PEER_ID {
+        handle_show_recipe(&format!("show r {}", id)).await;
+        return;
+    }
+    swarm.behaviour_mut().fetch.send_request(&author, FetchRequest::Recipe { id });
+    info!("Fetching recipe {} from {}", id, author);
+}
+//logic for archiving or unarchiving a local recipe
+async fn handle_archive_recipe(cmd: &str, archived: bool) {
+    let (prefix, verb) = if archived { ("archive r", "archived") } else { ("unarchive r", "unarchived") };
+    let rest = cmd.strip_prefix(prefix).unwrap_or_default().trim();
+    let id = match rest.parse::<usize>() {
+        Ok(id) => id,
+        Err(e) => {
+            info!("Invalid id {}, {} - Format: {} <id>", rest, e, prefix);
+            return;
+        }
+    };
+    match set_archived(id, archived).await {
+        Ok(true) => info!("recipe {} {}", id, verb),
+        Ok(false) => info!("recipe {} is already {}", id, verb),
+        Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+        Err(e) => error!("error updating recipe {}: {}", id, e),
+    }
+}
+//logic for setting the archived flag, returns whether anything changed
+async fn set_archived(id: usize, archived: bool) -> RecipeResult<bool> {
+    let mut local_recipes = read_local_recipes().await?;
+    let recipe = local_recipes.iter_mut().find(|r| r.id == id).ok_or(RecipeError::NotFound { id })?;
+    if recipe.archived == archived {
+        return Ok(false);
+    }
+    recipe.archived = archived;
+    write_local_recipes(&local_recipes).await?;
+    Ok(true)
+}
+//logic for withholding a recipe from a peer or serving it to them again
+async fn handle_deny_recipe(cmd: &str, denied: bool) {
+    let prefix = if denied { "deny r" } else { "allow r" };
+    let rest = cmd.strip_prefix(prefix).unwrap_or_default().trim();
+    let parsed = rest
+        .split_once(' ')
+        .and_then(|(id, peer)| Some((id.parse::<usize>().ok()?, peer.trim().parse::<PeerId>().ok()?)));
+    let (id, peer) = match parsed {
+        Some(parsed) => parsed,
+        None => {
+            info!("Invalid arguments {:?} - Format: {} <id> <peer_id>", rest, prefix);
+            return;
+        }
+    };
+    match set_denied(id, &peer.to_string(), denied).await {
+        Ok(true) if denied => info!("recipe {} is withheld from {}", id, peer),
+        Ok(true) => info!("recipe {} is served to {} again", id, peer),
+        Ok(false) if denied => info!("recipe {} is already withheld from {}", id, peer),
+        Ok(false) => info!("recipe {} isnt withheld from {}", id, peer),
+        Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+        Err(e) => error!("error updating recipe {}: {}", id, e),
     }
 }
-//logic for listing peers
-async fn handle_list_peers(swarm: &mut Swarm<RecipeBehaviour>){
-    info!("Discovered peers:");
-    //mdns shows all discovered nodes
-    let nodes = swarm.behaviour().mdns.discovered_nodes();
-    let mut unique_peers = HashSet::new();
-    //adds peers from list to hash set data structure which prevents duplicate values
-    for peer in nodes {
-        unique_peers.insert(peer);
+//logic for adding a peer to or removing it from a recipe's denied peers, returns whether anything changed
+async fn set_denied(id: usize, peer: &str, denied: bool) -> RecipeResult<bool> {
+    let mut local_recipes = read_local_recipes().await?;
+    let recipe = local_recipes.iter_mut().find(|r| r.id == id).ok_or(RecipeError::NotFound { id })?;
+    if recipe.denied_peers.iter().any(|p| p == peer) == denied {
+        return Ok(false);
     }
-    //iterates through the hashset and displays the peers
-    unique_peers.iter().for_each(|p| info!("{}",p));
-
+    if denied {
+        recipe.denied_peers.push(peer.to_owned());
+    } else {
+        recipe.denied_peers.retain(|p| p != peer);
+    }
+    write_local_recipes(&local_recipes).await?;
+    Ok(true)
 }
-//logic for handling recipe creation
-async fn handle_create_recipes(cmd :&str){
-    //removes the command from the string
-    if let Some(rest) = cmd.strip_prefix("create r"){
-        //splits arguments and stores their references in a array
-        let elements: Vec<&str> = rest.split("|").collect();
-        //Uses the len function to check number of args
-        if elements.len() < 3{
-            info!("too few arguments - Format: name|ingredients|instructions")
-        }
-        else {
-            //assigns varible names to arguments
-            let name = elements.get(0).expect("name is there");
-            let ingredients = elements.get(1).expect("ingredients are there");
-            let instructions = elements.get(2).expect("instructions are there");
-            //Uses Err enum to handle errors while creating recipes
-            if let  Err(e)= create_new_recipe(name,ingredients,instructions).await{
-                panic!("error creating recipe: {}", e);
-            };
+//logic for permanently removing a local recipe
+//at a terminal the delete waits for a y on the next line unless --yes is given, piped input is never asked
+async fn handle_delete_recipe(cmd: &str) {
+    let rest = cmd.strip_prefix("delete r").unwrap_or_default().trim();
+    let (rest, confirmed) = match rest.strip_suffix("--yes") {
+        Some(rest) => (rest.trim(), true),
+        None => (rest, false),
+    };
+    if let Some(tag) = rest.strip_prefix("--tag") {
+        return handle_delete_tagged(tag.trim(), confirmed).await;
+    }
+    let id = match rest.parse::<usize>() {
+        Ok(id) => id,
+        Err(e) => {
+            info!("Invalid id {}, {} - Format: delete r <id> [--yes] or delete r --tag <tag> [--yes]", rest, e);
+            return;
+        }
+    };
+    if !confirmed && prompt::is_interactive() {
+        match read_local_recipes().await.map(|recipes| recipes.into_iter().find(|r| r.id == id)) {
+            Ok(Some(r)) => {
+                info!("Delete recipe {} ({})? Type y to confirm, anything else cancels", id, r.name);
+                prompt::ask_confirmation(format!("delete r {} --yes", id));
+            }
+            Ok(None) => info!("{}", RecipeError::NotFound { id }),
+            Err(e) => error!("error deleting recipe {}: {}", id, e),
         }
+        return;
+    }
+    match delete_recipe(id).await {
+        Ok(r) => info!("Deleted recipe {} ({}), undo brings it back, archive r hides a recipe without deleting it", id, r.name),
+        Err(e @ RecipeError::NotFound { .. }) => info!("{}", e),
+        Err(e) => error!("error deleting recipe {}: {}", id, e),
     }
 }
-//logic for creating a recipe
-async fn create_new_recipe(name :&str,ingredients:&str,instructions :&str) -> Result<()>{
-    //Creates a list of recipe structs
+//logic for deleting every local recipe with a tag, e.g. the examples seed added, confirmed the same way as a single delete
+async fn handle_delete_tagged(tag: &str, confirmed: bool) {
+    if tag.is_empty() {
+        info!("missing tag - Format: delete r --tag <tag> [--yes]");
+        return;
+    }
+    let tagged: Vec<Recipe> = match read_local_recipes().await {
+        Ok(recipes) => recipes.into_iter().filter(|r| r.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))).collect(),
+        Err(e) => {
+            error!("error deleting recipes tagged {}: {}", tag, e);
+            return;
+        }
+    };
+    if tagged.is_empty() {
+        info!("No recipes are tagged {}", tag);
+        return;
+    }
+    if !confirmed && prompt::is_interactive() {
+        info!("Delete {} recipes tagged {}? Type y to confirm, anything else cancels", tagged.len(), tag);
+        prompt::ask_confirmation(format!("delete r --tag {} --yes", tag));
+        return;
+    }
+    //each one is recorded on its own so undo brings them back one at a time
+    for recipe in tagged {
+        match delete_recipe(recipe.id).await {
+            Ok(r) => info!("Deleted recipe {} ({})", r.id, r.name),
+            Err(e) => {
+                error!("error deleting recipe {}: {}", recipe.id, e);
+                return;
+            }
+        }
+    }
+}
+//logic for adding the example recipes, they are private and tagged so they dont reach peers unless published
+async fn handle_seed() {
+    match seed::seed().await {
+        Ok(report) => {
+            info!("Added {} example recipes tagged {}, try ls r, find --ingredient flour or publish r <id>", report.imported.len(), seed::SAMPLE_TAG);
+            report.imported.iter().for_each(|r| info!("{} [{}]", r.name, r.id));
+            report.failures.iter().for_each(|f| error!("example skipped, {}", f));
+            info!("delete r --tag {} removes them again", seed::SAMPLE_TAG);
+        }
+        Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+        Err(e) => error!("error adding example recipes: {}", e),
+    }
+}
+//logic for removing a recipe from storage
+async fn delete_recipe(id: usize) -> RecipeResult<Recipe> {
     let mut local_recipes = read_local_recipes().await?;
-    //loops trough local recipes and assigns appropriate id
-    //if no local recipes gives id of 0
-    let new_id = match local_recipes.iter().max_by_key(|r| r.id){
-        Some(v) => v.id +1,
-        None => 0,
+    let index = local_recipes.iter().position(|r| r.id == id).ok_or(RecipeError::NotFound { id })?;
+    let recipe = local_recipes.remove(index);
+    write_local_recipes(&local_recipes).await?;
+    undo::record(undo::ChangeKind::Deleted, recipe.clone())?;
+    Ok(recipe)
+}
+//logic for reversing the last delete or edit, an edit to a public recipe reaches peers as a patch like any other edit
+async fn handle_undo(swarm: Option<&mut Swarm<RecipeBehaviour>>) {
+    match undo_last().await {
+        Ok(None) => info!("Nothing to undo"),
+        Ok(Some((undo::ChangeKind::Deleted, restored, _))) => info!("Restored deleted recipe {} ({})", restored.id, restored.name),
+        Ok(Some((undo::ChangeKind::Edited, restored, replaced))) => {
+            info!("Reverted the last edit to recipe {} ({})", restored.id, restored.name);
+            if let (true, Some(swarm), Some(replaced)) = (restored.public, swarm, replaced) {
+                announce_patch(&replaced, &restored, swarm);
+            }
+        }
+        Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+        Err(e) => error!("error undoing the last change: {}", e),
+    }
+}
+//puts the recipe from the last change back, returns what kind of change it was, the restored recipe and what it replaced
+//a deleted recipe whose id has been taken since comes back under a new one
+async fn undo_last() -> RecipeResult<Option<(undo::ChangeKind, Recipe, Option<Recipe>)>> {
+    let change = match undo::take_last()? {
+        Some(change) => change,
+        None => return Ok(None),
+    };
+    let mut local_recipes = read_local_recipes().await?;
+    let mut restored = change.before;
+    let replaced = match local_recipes.iter_mut().find(|r| r.id == restored.id) {
+        Some(current) if matches!(change.kind, undo::ChangeKind::Edited) => Some(std::mem::replace(current, restored.clone())),
+        Some(_) => {
+            let id = local_recipes.iter().map(|r| r.id + 1).max().unwrap_or(0);
+            info!("id {} has been taken since the delete, the recipe comes back as {}", restored.id, id);
+            restored.id = id;
+            local_recipes.push(restored.clone());
+            None
+        }
+        None => {
+            local_recipes.push(restored.clone());
+            None
+        }
     };
-    //pushes new recipe to local recipe content
-    //to_owned used to transfer ownership
-    local_recipes.push(Recipe{
-        id: new_id,
-        name: name.to_owned(),
-        ingredients: ingredients.to_owned(),
-        instructions: instructions.to_owned(),
-        public: false
-    });
-
     write_local_recipes(&local_recipes).await?;
-    //feedback to the user
-    info!("Created Recipe:");
-    info!("Name: {:?}", name);
-    info!("Ingredients: {:?}",ingredients);
-    info!("Instructions: {:?}",instructions);
-    //
-    Ok(())
+    Ok(Some((change.kind, restored, replaced)))
 }
 //logic for handling recipe publication
-async fn handle_publish_recipes(cmd :&str){
+async fn handle_publish_recipes(cmd :&str, swarm: &mut Swarm<RecipeBehaviour>){
     //removes the command from the string
     if let Some(rest) = cmd.strip_prefix("publish r"){
-        //match statement check validity of id
-        match rest.trim().parse::<usize>() {
-            //
-            Ok(id) =>{
-                //
-                if let Err(e) = publish_recipe(id).await{
-                    info!("error publishing recipe with id {}, {}", id, e);
-                }
-                else {
-                    info!("Successful publication with id {}",id);
+        let (rest, topic) = take_topic(rest);
+        //announcements on a topic this node isnt on would never leave it
+        if !swarm.behaviour().topics.contains(&topic) {
+            info!("Not subscribed to {}, nothing was published - subscribe with: topic subscribe {}", topic, topic);
+            return;
+        }
+        //"all" publishes every private recipe, otherwise a comma separated list of ids
+        let ids = if rest == "all" {
+            None
+        } else {
+            match rest.split(',').map(|id| id.trim().parse::<usize>()).collect::<Result<Vec<_>, _>>() {
+                Ok(ids) => Some(ids),
+                Err(e) => {
+                    info!("Invalid id {}, {} - Format: publish r <id>|<id1,id2,...>|all [--topic <name>]", rest, e);
+                    return;
                 }
             }
-            //if id doesnt match it spits out error
-            Err(e)=> panic!("Invalid id {}, {}",rest.trim(),e),
+        };
+        match publish_recipes(ids.as_deref(), &topic).await {
+            Ok((newly, already)) => {
+                info!("Successful publication on {}: {} newly published, {} already on it", topic, newly.len(), already);
+                newly.iter().filter(|r| r.is_served()).for_each(|r| announce_recipe(r, &topic, swarm));
+            }
+            //a missing id is a user mistake rather than a failure
+            Err(RecipeError::NotFound { id }) => info!("no local recipe with id {} to publish, nothing was published", id),
+            Err(e) => error!("error publishing recipes {}, {}", rest, e),
         }
     }
 }
-//logic for publishing a recipe
-async fn publish_recipe(id: usize)->Result<()>{
+//logic for publishing the given recipes, or all of them, on a topic in a single write
+//every public recipe is on the default topic, others are added to the recipe's topics
+//returns the newly published recipes and how many were already public on the topic
+async fn publish_recipes(ids: Option<&[usize]>, topic: &str)->RecipeResult<(Recipes, usize)>{
     let mut local_recipes = read_local_recipes().await?;
-    //iterates through recipes and sets public flag to be true as the user intends to share it on the network
-    local_recipes.iter_mut().filter(|r| r.id == id).for_each(|r| r.public = true);
-    write_local_recipes(&local_recipes).await?;
-    //
-    Ok(())
-}
-//logic for reading local recipes
-async fn read_local_recipes()-> Result<Recipes>{
-    //reads content from storage
-    let content = fs::read(STORAGE_FILE_PATH).await?;
-    //deserialized result
-    let result = serde_json::from_slice(&content)?;
-    Ok(result)
-}
-//logic for writing local recipes
-async fn write_local_recipes(recipes: &Recipes)->Result<()>{
-    //Converts json to plain text
-    let json = serde_json::to_string(&recipes)?;
-    //Writes to local json file
-    fs::write(STORAGE_FILE_PATH, &json).await?;
-    //Ends function
-    Ok(())
+    //every requested id has to exist before anything changes
+    if let Some(ids) = ids {
+        if let Some(&id) = ids.iter().find(|id| !local_recipes.iter().any(|r| r.id == **id)) {
+            return Err(RecipeError::NotFound { id });
+        }
+    }
+    let (mut newly, mut already) = (Vec::new(), 0);
+    //sets public flag to be true as the user intends to share them on the network
+    for recipe in local_recipes.iter_mut().filter(|r| ids.map(|ids| ids.contains(&r.id)).unwrap_or(true)) {
+        if recipe.public && recipe.is_on_topic(topic) {
+            already += 1;
+        } else {
+            recipe.public = true;
+            if !recipe.is_on_topic(topic) {
+                recipe.topics.push(topic.to_owned());
+            }
+            newly.push(recipe.clone());
+        }
+    }
+    if !newly.is_empty() {
+        write_local_recipes(&local_recipes).await?;
+    }
+    Ok((newly, already))
 }
 //logic for handling incoming recipe lists shared by other people
-async fn handle_list_recipes(cmd :&str,swarm: &mut Swarm<RecipeBehaviour>){
+//--topic lists the recipes on one topic, or sends the request on it instead of the default topic
+async fn handle_list_recipes(cmd :&str,swarm: Option<&mut Swarm<RecipeBehaviour>>){
     //Strips the command prefix as this isn't needed any more and its easier to parse the command without it
-    let rest = cmd.strip_prefix("ls r");
+    let (rest, topic) = take_topic(cmd.strip_prefix("ls r").unwrap_or_default());
+    //--fields only applies to requests to peers, local recipes are read whole anyway
+    let (rest, fields) = match rest.split_once("--fields") {
+        Some((rest, fields)) => (rest.trim(), split_list(fields)),
+        None => (rest, Vec::new()),
+    };
+    //--table lays local recipes out in columns instead of a line of debug output each
+    let table = rest.split_whitespace().any(|a| a == "--table");
+    let rest = rest.split_whitespace().filter(|a| *a != "--table").collect::<Vec<_>>().join(" ");
+    if rest.is_empty() && topic != DEFAULT_TOPIC {
+        return list_topic_recipes(&topic).await;
+    }
+    //flags narrowing down the local recipes combine, anything else is a peer to ask
+    if rest.is_empty() || rest.starts_with("--") {
+        return match LocalListing::parse(&rest) {
+            Ok(listing) => list_local_recipes(&LocalListing { table, ..listing }).await,
+            Err(e) => info!("{}", e),
+        };
+    }
     // Control flow to execute the correct code based off user command
-    match rest {
+    let mode = match rest.as_str() {
         //If "all" command is encountered
-        Some("all") => {
-            let req = ListRequest {
-                mode: ListMode::ALL,
-            };
-            //serializes to json
-            let json = serde_json::to_string(&req).expect("can jsonify request");
-            //publish it to previously mentioned topic
-            swarm.behaviour_mut().floodsub.publish(TOPIC.clone(), json.as_bytes());
-        }
+        "all" => ListMode::ALL,
         //If peer id command is encountered
-        Some(recipes_peer_id) => {
-            let req = ListRequest {
-                //
-                mode: ListMode::One(recipes_peer_id.to_owned()),
+        recipes_peer_id => ListMode::One(recipes_peer_id.to_owned()),
+    };
+    let swarm = match swarm {
+        Some(swarm) => swarm,
+        None => return info!("{}", RecipeError::Offline(cmd.to_owned())),
+    };
+    let description = match &mode {
+        ListMode::ALL => "ls r all".to_owned(),
+        ListMode::One(peer_id) => format!("ls r {}", peer_id),
+    };
+    let description = if topic == DEFAULT_TOPIC { description } else { format!("{} on {}", description, topic) };
+    if !swarm.behaviour().topics.contains(&topic) {
+        info!("Not subscribed to {}, nothing was sent - subscribe with: topic subscribe {}", topic, topic);
+        return;
+    }
+    //peers that should answer, only subscribed ones receive the request so anyone else would never respond
+    let subscribed = swarm.behaviour().subscribed_peers(&topic);
+    let expected: HashSet<PeerId> = match &mode {
+        ListMode::ALL => subscribed.clone(),
+        ListMode::One(peer_id) => peer_id.parse().into_iter().collect(),
+    };
+    match &mode {
+        ListMode::ALL if expected.is_empty() => {
+            info!("No peers are subscribed to {} yet, nothing was sent", topic);
+            return;
+        }
+        ListMode::One(peer_id) if expected.is_empty() => {
+            info!("invalid peer id {:?} - Format: ls r <peer_id>", peer_id);
+            return;
+        }
+        ListMode::One(peer_id) if !expected.iter().any(|p| subscribed.contains(p)) => {
+            info!("{} isnt subscribed to {}, nothing was sent - check ls p for connected peers", peer_id, topic);
+            return;
+        }
+        _ => {}
+    }
+    //responses echo the id so they can be collected for this request only
+    let request_id = Uuid::new_v4();
+    let req = ListRequest {
+        mode,
+        request_id: Some(request_id),
+        fields,
+    };
+    //serializes to json
+    let json = serde_json::to_string(&req).expect("can jsonify request");
+    //publish it to previously mentioned topic
+    swarm.behaviour_mut().publish(Topic::new(topic), json.as_bytes());
+    let window = Duration::from_secs(config::get().map(|c| c.response_timeout_secs).unwrap_or(5));
+    swarm.behaviour_mut().pending_requests.start(request_id, description, window, expected);
+}
+
+//splits --topic <name> off the end of a command's arguments, without it the default topic is meant
+fn take_topic(args: &str) -> (&str, String) {
+    match args.split_once("--topic") {
+        Some((rest, topic)) if !topic.trim().is_empty() => (rest.trim(), topic.trim().to_owned()),
+        _ => (args.trim(), DEFAULT_TOPIC.to_owned()),
+    }
+}
+//logic for listing the local recipes published on a topic and the cached ones received on it
+async fn list_topic_recipes(topic: &str) {
+    match storage::read_shared_recipes().await {
+        Ok(recipes) => {
+            let on_topic: Vec<&Recipe> = recipes.iter().filter(|r| r.is_served() && r.is_on_topic(topic)).collect();
+            info!("Local recipes on {} ({})", topic, on_topic.len());
+            on_topic.iter().for_each(|r| info!("{:?}", r));
+        }
+        Err(e) => error!("error fetching local recipes: {}", e),
+    }
+    match cache::entries().await {
+        Ok(entries) => {
+            let on_topic: Vec<&cache::CachedRecipe> = entries.iter().filter(|e| e.topic == topic).collect();
+            info!("Cached recipes on {} ({})", topic, on_topic.len());
+            on_topic.iter().for_each(|e| info!("{} [{}] from {}", e.recipe.name, e.recipe.id, e.author));
+        }
+        Err(e) => error!("error reading the cache: {}", e),
+    }
+}
+//which local recipes ls r lists and how
+#[derive(Default)]
+struct LocalListing {
+    //archived recipes instead of the active ones
+    archived: bool,
+    //recipes done within this many minutes, recipes without any time are left out since they might take any amount of it
+    max_minutes: Option<u32>,
+    //recipes added or changed after this unix time, recipes that never recorded a change are listed after them as undated
+    since: Option<u64>,
+    //the recipes made most often first
+    most_made: bool,
+    //columns instead of a line of debug output each
+    table: bool,
+}
+
+impl LocalListing {
+    //reads --archived, --max-time <minutes>, --since <duration|date> and --sort most-made in any order
+    fn parse(args: &str) -> RecipeResult<LocalListing> {
+        const FORMAT: &str = "Format: ls r [--archived] [--max-time <minutes>] [--since <duration|date>] [--sort most-made] [--table]";
+        let mut listing = LocalListing::default();
+        let mut args = args.split_whitespace();
+        while let Some(arg) = args.next() {
+            match arg {
+                "--archived" => listing.archived = true,
+                "--sort" => match args.next() {
+                    Some("most-made") => listing.most_made = true,
+                    _ => return Err(RecipeError::Validation(format!("invalid sort, only most-made is supported - {}", FORMAT))),
+                },
+                "--max-time" => match args.next().map(str::parse::<u32>) {
+                    Some(Ok(minutes)) => listing.max_minutes = Some(minutes),
+                    _ => return Err(RecipeError::Validation(format!("invalid time - {}", FORMAT))),
+                },
+                "--since" => listing.since = Some(since::parse(args.next().unwrap_or_default(), unix_now())?),
+                other => return Err(RecipeError::Validation(format!("unknown option {} - {}", other, FORMAT))),
+            }
+        }
+        Ok(listing)
+    }
+}
+
+async fn list_local_recipes(listing: &LocalListing) {
+    let LocalListing { archived, max_minutes, since, most_made, table } = *listing;
+    //match statement catches error if no local recipes are present
+    match storage::read_shared_recipes().await {
+        //Ok(v) is the situation where there are local recipes
+        Ok(v) => {
+            let within = |r: &Recipe| max_minutes.is_none_or(|max| r.total_minutes().is_some_and(|t| t <= max));
+            let changed = |r: &Recipe| since.is_none_or(|since| r.updated_at.is_some_and(|at| at > since));
+            //recipes stored before changes were timed cant be told apart, so they are shown rather than quietly left out
+            let undated = |r: &Recipe| since.is_some() && r.updated_at.is_none();
+            let (mut v, mut undated): (Vec<&Recipe>, Vec<&Recipe>) =
+                v.iter().filter(|r| r.archived == archived && within(r) && (changed(r) || undated(r))).partition(|r| changed(r));
+            storage::sort(&mut v, storage::sort_order());
+            storage::sort(&mut undated, storage::sort_order());
+            if most_made {
+                let counts = made::counts_for(&PEER_ID.to_string()).await.unwrap_or_else(|e| {
+                    error!("error fetching how often recipes were made: {}", e);
+                    Vec::new()
+                });
+                let times = |r: &Recipe| counts.iter().find(|c| c.recipe_id == r.id).map(|c| c.times_made).unwrap_or(0);
+                v.sort_by_key(|r| std::cmp::Reverse(times(r)));
+            }
+            //outputs how many units there are in the local recipe list
+            info!("{} recipes ({})", if archived { "Archived" } else { "Local" }, v.len());
+            let show = |v: &[&Recipe]| {
+                if table {
+                    return recipe_table(v).iter().for_each(|line| info!("{}", line));
+                }
+                //iterates and outputs all local recipes to the user
+                v.iter().for_each(|r| info!("{:?}",r))
             };
-            //serializes to json
-            let json = serde_json::to_string(&req).expect("can jsonify request");
-            //publishes it to previously mentioned topic
-            swarm.behaviour_mut().floodsub.publish(TOPIC.clone(), json.as_bytes());
+            show(&v);
+            if !undated.is_empty() {
+                info!("Undated recipes ({}), stored before change times were kept so they may have changed since, they are dated on their next change", undated.len());
+                show(&undated);
+            }
         }
-        //if there is no command
-        None => {
-            //match statement catches error if no local recipes are present
-            match read_local_recipes().await {
-                //Ok(v) is the situation where there are local recipes
-                Ok(v) => {
-                    //outputs how many units there are in the local recipe list
-                    info!("Local recipes ({})",v.len());
-                    //iterates and outputs all local recipes to the user
-                    v.iter().for_each(|r| info!("{:?}",r))
+        //a missing storage file just means nothing has been created yet
+        Err(RecipeError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => info!("Local recipes (0)"),
+        //out puts error if recipes could not be read
+        Err(e) => error!("error fetching local recipes: {}",e),
+    }
+}
+
+//the columns of ls r --table, fitted to the terminal
+fn recipe_table(recipes: &[&Recipe]) -> Vec<String> {
+    let rows: Vec<Vec<String>> = recipes
+        .iter()
+        .map(|r| {
+            vec![
+                r.id.to_string(),
+                r.name.clone(),
+                r.servings.map_or("-".to_owned(), |s| s.to_string()),
+                r.total_minutes().map_or("-".to_owned(), |m| format!("{} min", m)),
+                if r.tags.is_empty() { "-".to_owned() } else { r.tags.join(",") },
+                match (r.public, r.archived) {
+                    (_, true) => "archived",
+                    (true, _) => "public",
+                    (false, _) => "private",
+                }
+                .to_owned(),
+            ]
+        })
+        .collect();
+    table::render(&["ID", "NAME", "SERVINGS", "TIME", "TAGS", "VISIBILITY"], &rows, table::output_width())
+}
+//logic for asking every peer for public recipes by name
+fn handle_find_remote(cmd: &str, swarm: &mut Swarm<RecipeBehaviour>) {
+    let name = cmd.strip_prefix("find-remote").unwrap_or_default().trim();
+    if name.is_empty() {
+        info!("missing name - Format: find-remote [--live] <name>");
+        return;
+    }
+    let (live, name) = match name.strip_prefix("--live") {
+        Some(rest) => (true, rest.trim()),
+        None => (false, name),
+    };
+    let expected = swarm.behaviour().subscribed_peers(DEFAULT_TOPIC);
+    if expected.is_empty() {
+        info!("No peers are subscribed to recipes yet, nothing was sent");
+        return;
+    }
+    let wanted = name.to_lowercase();
+    if !live && answer_from_digests(swarm, &expected, &format!("find-remote {}", name), |e| e.name.to_lowercase().contains(&wanted)) {
+        return;
+    }
+    let query = NameQuery {
+        name: name.to_owned(),
+        request_id: Uuid::new_v4(),
+    };
+    let json = serde_json::to_string(&query).expect("can jsonify name query");
+    swarm.behaviour_mut().publish(TOPIC.clone(), json.as_bytes());
+    let window = Duration::from_secs(config::get().map(|c| c.response_timeout_secs).unwrap_or(5));
+    swarm.behaviour_mut().pending_requests.start(query.request_id, format!("find-remote {}", name), window, expected);
+}
+
+//topic names are sent as they are typed, so they cant be empty or contain whitespace
+fn validate_topic(name: &str) -> RecipeResult<()> {
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        return Err(RecipeError::Validation(format!("invalid topic name {:?}, it cant be empty or contain spaces", name)));
+    }
+    Ok(())
+}
+//logic for changing which topics this node is on while it runs, topics in the config are subscribed to at startup
+fn handle_topic(cmd: &str, swarm: &mut Swarm<RecipeBehaviour>) {
+    let args: Vec<&str> = cmd.split_whitespace().skip(1).collect();
+    match args.as_slice() {
+        ["list"] => info!("Topics: {}", swarm.behaviour().topic_summary()),
+        ["subscribe", name] => {
+            if let Err(e) = validate_topic(name) {
+                return info!("{}", e);
+            }
+            if swarm.behaviour_mut().floodsub.subscribe(Topic::new(*name)) {
+                swarm.behaviour_mut().topics.insert(name.to_string());
+                info!("Subscribed to {}, {} peers are on it", name, swarm.behaviour().subscribed_peers(name).len());
+            } else {
+                info!("Already subscribed to {}", name);
+            }
+        }
+        //every node is on the default topic, requests and announcements rely on it
+        ["unsubscribe", name] if *name == DEFAULT_TOPIC => info!("{} is the default topic and cant be left", DEFAULT_TOPIC),
+        ["unsubscribe", name] => {
+            if swarm.behaviour_mut().floodsub.unsubscribe(Topic::new(*name)) {
+                swarm.behaviour_mut().topics.remove(*name);
+                info!("Unsubscribed from {}", name);
+            } else {
+                info!("Not subscribed to {}", name);
+            }
+        }
+        _ => info!("unknown topic command - Format: topic list|subscribe <name>|unsubscribe <name>"),
+    }
+}
+
+//answers a lookup from the peers' digests when every peer it would go to sent one recently, returns false when it has to be asked live
+fn answer_from_digests<F: Fn(&digest::DigestEntry) -> bool>(swarm: &Swarm<RecipeBehaviour>, peers: &HashSet<PeerId>, description: &str, wanted: F) -> bool {
+    let digests = &swarm.behaviour().digests;
+    if !digests.covers(peers) {
+        return false;
+    }
+    let found = digests.find(peers, wanted);
+    for (peer, entry) in &found {
+        info!("{} [{}] from {}, fetch with: open {}", entry.name, entry.id, peer, fetch::share_link(peer, entry.id));
+    }
+    info!("{}: {} matches in the digests of {} peers, nothing was sent, ask them with --live", description, found.len(), peers.len());
+    true
+}
+//logic for asking every peer whether it has a recipe, by its whole name or its id on that peer
+//only names and ids come back, the recipes themselves can be fetched afterwards with open
+fn handle_locate(cmd: &str, swarm: &mut Swarm<RecipeBehaviour>) {
+    let reference = cmd.strip_prefix("locate").unwrap_or_default().trim();
+    let (live, reference) = match reference.strip_prefix("--live") {
+        Some(rest) => (true, rest.trim()),
+        None => (false, reference),
+    };
+    if reference.is_empty() {
+        info!("missing recipe - Format: locate [--live] <name or id>");
+        return;
+    }
+    let expected = swarm.behaviour().subscribed_peers(DEFAULT_TOPIC);
+    if expected.is_empty() {
+        info!("No peers are subscribed to recipes yet, nothing was sent");
+        return;
+    }
+    let locate = match reference.parse::<usize>() {
+        Ok(id) => LocateTarget::Id(id),
+        Err(_) => LocateTarget::Name(reference.to_owned()),
+    };
+    if !live && answer_from_digests(swarm, &expected, &format!("locate {}", reference), |e| locate.matches_listing(e.id, &e.name)) {
+        return;
+    }
+    let query = LocateQuery { locate, request_id: Uuid::new_v4() };
+    let json = serde_json::to_string(&query).expect("can jsonify locate query");
+    swarm.behaviour_mut().publish(TOPIC.clone(), json.as_bytes());
+    let window = Duration::from_secs(config::get().map(|c| c.response_timeout_secs).unwrap_or(5));
+    swarm.behaviour_mut().pending_requests.start(query.request_id, format!("locate {}", reference), window, expected);
+}
+
+//serializes a response to a list request, recipes that fail to serialize are left out and named so they can be fixed
+//returns None if the response still cant be serialized without them
+fn serialize_response(resp: ListResponse) -> Option<String> {
+    let error = match serde_json::to_string(&resp) {
+        Ok(json) => return Some(json),
+        Err(e) => e,
+    };
+    error!("error serializing response to {}, retrying without the recipes that fail: {}", resp.receiver, error);
+    let resp = without_broken_recipes(resp, |r| serde_json::to_string(r).map(drop));
+    match serde_json::to_string(&resp) {
+        Ok(json) => Some(json),
+        Err(e) => {
+            error!("skipping response to {}: {}", resp.receiver, RecipeError::Serde(e));
+            None
+        }
+    }
+}
+//the response without the recipes serialize fails on, each one left out is logged with how to fix it
+fn without_broken_recipes(resp: ListResponse, serialize: impl Fn(&Recipe) -> serde_json::Result<()>) -> ListResponse {
+    let (data, broken): (Recipes, Recipes) = resp.data.into_iter().partition(|r| serialize(r).is_ok());
+    for recipe in &broken {
+        error!("recipe {} ({:?}) cant be serialized, fix it with edit r {}", recipe.id, recipe.name, recipe.id);
+    }
+    ListResponse { data, ..resp }
+}
+//how long ago a unix timestamp was, in the largest whole unit
+fn ago(timestamp: u64) -> String {
+    let secs = unix_now().saturating_sub(timestamp);
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+//logic for bringing a cached recipe up to date with a patch from its author, a cached copy of another version is fetched whole
+fn apply_remote_patch(stale_sender: mpsc::UnboundedSender<(PeerId, usize)>, author: PeerId, patch: RecipePatch) {
+    let received_at = unix_now();
+    //a patch that dates the recipe outside the configured window is refused like a recipe dated there
+    let updated_at = patch.changed_fields.get("updated_at").and_then(serde_json::Value::as_u64);
+    if let Some(reason) = config::get().ok().and_then(|config| update_rejection(updated_at, received_at, &config)) {
+        return warn!("refused patch to recipe {} from {}: {}", patch.id, author, reason);
+    }
+    tokio::spawn(async move {
+        match cache::apply_patch(&patch, received_at).await {
+            Ok(PatchOutcome::Applied(recipe)) => {
+                info!("Updated cached recipe {} ({:?}) from {}", recipe.id, recipe.name, author);
+                //logged as a receive of the patched recipe so replay ends up with the same copy
+                let event = audit::ReceiveEvent { received_at, author: patch.author, recipes: vec![*recipe], topic: None };
+                if let Err(e) = audit::record(&event).await {
+                    error!("error writing {} to {}: {}", event.author, audit::AUDIT_LOG_PATH, e);
+                }
+                archive_received(&event.author, &event.recipes, received_at).await;
+            }
+            Ok(PatchOutcome::Stale) => {
+                let _ = stale_sender.send((author, patch.id));
+            }
+            //recipes that were never received arent fetched just because they changed
+            Ok(PatchOutcome::Missing) => {}
+            Err(e) => error!("error patching cached recipe {} from {}: {}", patch.id, author, e),
+        }
+    });
+}
+//announces the ids, names and hashes of the recipes served to everyone, a page at a time through the publish cooldown
+//recipes withheld from some peers are left out, every subscribed peer sees the digest
+async fn broadcast_digest(swarm: &mut Swarm<RecipeBehaviour>) {
+    if swarm.behaviour().receivers(&TOPIC) == 0 {
+        return;
+    }
+    let config = match config::get() {
+        Ok(config) => config,
+        Err(e) => return error!("error reading config: {}", e),
+    };
+    let recipes: Recipes = match storage::read_shared_recipes().await {
+        Ok(recipes) => recipes.iter().filter(|r| r.is_served() && r.denied_peers.is_empty()).cloned().collect(),
+        //nothing created yet still tells peers there is nothing to find here
+        Err(RecipeError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return error!("error fetching local recipes for the digest: {}", e),
+    };
+    for page in digest::pages(&recipes, config.digest_page_size, unix_now(), config.digest_interval_secs) {
+        match serde_json::to_vec(&page) {
+            Ok(json) => {
+                swarm.behaviour_mut().announce_periodic(TOPIC.clone(), json);
+            }
+            Err(e) => return error!("{}", RecipeError::Serde(e)),
+        }
+    }
+}
+//cached recipes whose hash in a peer's digest no longer matches are fetched again, recipes never received arent fetched
+fn refetch_changed(stale_sender: mpsc::UnboundedSender<(PeerId, usize)>, author: PeerId, entries: Vec<digest::DigestEntry>) {
+    tokio::spawn(async move {
+        let key = author.to_string();
+        for entry in entries {
+            match cache::get(&key, entry.id).await {
+                Ok(Some(cached)) if digest::recipe_hash(&cached.recipe) != entry.hash => {
+                    let _ = stale_sender.send((author, entry.id));
+                }
+                Ok(_) => {}
+                Err(e) => return error!("error checking cached recipes from {} against its digest: {}", author, e),
+            }
+        }
+    });
+}
+//logic for storing recipes received from another peer in the background
+//recipes in the legacy format are upgraded to the structured one first
+//topic is what the recipes were received on, None for direct fetches which keep whatever topic a cached copy had
+fn cache_remote_recipes(author: String, mut recipes: Recipes, topic: Option<String>) {
+    let received_at = unix_now();
+    //recipes dated outside the configured window are refused before anything is logged or cached
+    match config::get() {
+        Ok(config) => recipes.retain(|r| match update_rejection(r.updated_at, received_at, &config) {
+            Some(reason) => {
+                warn!("refused recipe {} ({:?}) from {}: {}", r.id, r.name, author, reason);
+                false
+            }
+            None => true,
+        }),
+        Err(e) => error!("error reading config, recipes from {} are cached without checking their dates: {}", author, e),
+    }
+    if recipes.is_empty() {
+        return;
+    }
+    for recipe in recipes.iter_mut().filter_map(|r| r.upgrade_legacy().then_some(r)) {
+        info!("upgraded legacy format recipe {} ({:?}) from {}", recipe.id, recipe.name, author);
+    }
+    tokio::spawn(async move {
+        //logged first so a failed cache write can be recovered with replay
+        let event = audit::ReceiveEvent { received_at, author, recipes, topic };
+        if let Err(e) = audit::record(&event).await {
+            error!("error writing {} to {}: {}", event.author, audit::AUDIT_LOG_PATH, e);
+        }
+        archive_received(&event.author, &event.recipes, received_at).await;
+        if let Err(e) = cache::insert_at(&event.author, event.recipes, event.topic.as_deref(), event.received_at).await {
+            error!("error caching recipes from {}: {}", event.author, e);
+        }
+    });
+}
+//why a received recipe or patch dated updated_at is refused at now, None if it is accepted
+//undated ones were stored before change times were kept and limits of 0 are off, neither is ever refused
+fn update_rejection(updated_at: Option<u64>, now: u64, config: &config::Config) -> Option<String> {
+    let updated_at = updated_at?;
+    if config.max_update_age_secs > 0 && now.saturating_sub(updated_at) > config.max_update_age_secs {
+        Some(format!("dated {}s ago, older than max_update_age_secs {}", now - updated_at, config.max_update_age_secs))
+    } else if config.max_future_skew_secs > 0 && updated_at.saturating_sub(now) > config.max_future_skew_secs {
+        Some(format!("dated {}s ahead of this clock, beyond max_future_skew_secs {}", updated_at - now, config.max_future_skew_secs))
+    } else {
+        None
+    }
+}
+//keeps received recipes in the archive when archive_all is on, apart from the cache so eviction never touches them
+async fn archive_received(author: &str, recipes: &Recipes, received_at: u64) {
+    match config::get() {
+        Ok(config) if config.archive_all => {}
+        Ok(_) => return,
+        Err(e) => return error!("error reading config: {}", e),
+    }
+    match archive::insert(author, recipes, received_at).await {
+        Ok(outcome) if outcome.added > 0 => info!("Archived {} recipes from {}, {} already archived", outcome.added, author, outcome.duplicates),
+        Ok(_) => {}
+        Err(e) => error!("error archiving recipes from {}: {}", author, e),
+    }
+}
+//logic for listing and exporting the archive of received recipes
+async fn handle_archive(cmd: &str) {
+    const FORMAT: &str = "Format: archive list|export <path.json>";
+    match cmd.split_whitespace().skip(1).collect::<Vec<_>>().as_slice() {
+        ["list"] => match archive::entries().await {
+            Ok(entries) => {
+                info!("Archived recipes ({})", entries.len());
+                entries.iter().for_each(|e| info!("{} [{}] from {}, archived {}", e.recipe.name, e.recipe.id, e.author, ago(e.archived_at)));
+                if !config::get().is_ok_and(|c| c.archive_all) {
+                    info!("archive_all is off, received recipes arent being archived - turn it on with: config set archive_all true");
                 }
-                //out puts error if no recipes are found locally
-                Err(e) => error!("error fetching local recipes: {}",e),
+            }
+            Err(e) => error!("error reading the archive: {}", e),
+        },
+        ["export", path] => match archive::export(path).await {
+            Ok(count) => info!("Exported {} archived recipes to {}, import them with: import r --json {}", count, path, path),
+            Err(e @ RecipeError::Validation(_)) => info!("{}", e),
+            Err(e) => error!("error exporting the archive to {}: {}", path, e),
+        },
+        _ => info!("unknown archive command - {}", FORMAT),
+    }
+}
+//logic for re-applying logged receive events to the cache, events already applied change nothing
+async fn handle_replay(cmd: &str) {
+    let path = cmd.strip_prefix("replay").unwrap_or_default().trim();
+    let path = if path.is_empty() { audit::AUDIT_LOG_PATH } else { path };
+    let (events, failures) = match audit::read_events(path).await {
+        Ok(read) => read,
+        Err(e) => {
+            error!("error reading {}: {}", path, e);
+            return;
+        }
+    };
+    failures.iter().for_each(|f| info!("skipped {}", f));
+    let mut changed = 0;
+    for event in &events {
+        match cache::insert_at(&event.author, event.recipes.clone(), event.topic.as_deref(), event.received_at).await {
+            Ok(n) => changed += n,
+            Err(e) => {
+                error!("error replaying {}, stopped after {} recipes: {}", path, changed, e);
+                return;
             }
         }
     }
+    info!("Replayed {} events from {}, {} cached recipes added or updated, {} lines skipped", events.len(), path, changed, failures.len());
 }
+//bounds how many responders run at once so a burst of requests cant flood the disk, requests beyond it are dropped
+struct ResponderLimit {
+    permits: Arc<Semaphore>,
+    dropped: u64,
+}
+
+impl ResponderLimit {
+    fn new(max: usize) -> ResponderLimit {
+        ResponderLimit { permits: Arc::new(Semaphore::new(max.max(1))), dropped: 0 }
+    }
 
+    //a permit for one more responder task, none once every permit is held by a running one
+    fn permit(&mut self, peer: &PeerId) -> Option<OwnedSemaphorePermit> {
+        let permit = self.permits.clone().try_acquire_owned().ok();
+        if permit.is_none() {
+            self.dropped += 1;
+            network_event(Level::Warn, "request-dropped", peer, format_args!("too many requests being answered, {} dropped so far", self.dropped));
+        }
+        permit
+    }
+}
+//logic for answering a direct fetch with a public local recipe
+fn respond_to_fetch(
+    sender: mpsc::UnboundedSender<(ResponseChannel<FetchResponse>, FetchResponse)>,
+    requester: String,
+    request: FetchRequest,
+    channel: ResponseChannel<FetchResponse>,
+    permit: OwnedSemaphorePermit,
+) {
+    tokio::spawn(async move {
+        //held until the response is handed back to the swarm
+        let _permit = permit;
+        let response = match request {
+            FetchRequest::Recipe { id } => match storage::read_shared_recipes().await {
+                Ok(recipes) => FetchResponse::Recipe(
+                    recipes.iter().find(|r| r.id == id && r.is_served_to(&requester)).map(|r| Box::new(r.clone().shared())),
+                ),
+                Err(e) => {
+                    error!("error fetching local recipes to answer fetch request, {}", e);
+                    FetchResponse::Recipe(None)
+                }
+            },
+            FetchRequest::Manifest { name } => match manifests::get(&name).await {
+                Ok(manifest) => FetchResponse::Manifest(manifest),
+                Err(e) => {
+                    error!("error reading manifests to answer fetch request, {}", e);
+                    FetchResponse::Manifest(None)
+                }
+            },
+        };
+        if let Err(e) = sender.send((channel, response)) {
+            error!("{}", RecipeError::Network(format!("error sending fetch response via channel, {}", e)));
+        }
+    });
+}
 //logic for responding incoming recipe requests by other people
-fn respond_with_public_recipes(sender: mpsc::UnboundedSender<ListResponse>, receiver: String) {
+fn respond_with_public_recipes(
+    sender: mpsc::UnboundedSender<(Topic, ListResponse)>,
+    receiver: String,
+    request_id: Option<Uuid>,
+    topic: String,
+    fields: Vec<String>,
+    permit: OwnedSemaphorePermit,
+) {
     //spawns new asynchronous task
     tokio::spawn(async move {
+        //held until the response is handed back to the swarm
+        let _permit = permit;
         //check if there are even any recipes to respond with
-        match read_local_recipes().await {
+        match storage::read_shared_recipes().await {
             //case if recipe.json contains recipes
             Ok(recipes) => {
+                //iterates through all recipes adding then to the data section
+                let data: Recipes = recipes
+                    .iter()
+                    .filter(|r| r.is_served_to(&receiver) && r.is_on_topic(&topic))
+                    .cloned()
+                    .map(Recipe::shared)
+                    .collect();
+                //only the wanted fields are sent when the request named some
+                let (data, projected) = if fields.is_empty() {
+                    (data, Vec::new())
+                } else {
+                    (Vec::new(), data.iter().filter_map(|r| project(r, &fields)).collect())
+                };
                 //creates a response variable
                 let resp = ListResponse {
                     mode: ListMode::ALL,
                     receiver,
-                    //iterates through all recipes adding then to the data section
-                    data: recipes.into_iter().filter(|r| r.public).collect(),
+                    request_id,
+                    data,
+                    projected,
                 };
                 //"if let Err(e) specifies what to do if the message doesnt send
-                if let Err(e) = sender.send(resp) {
-                    error!("error sending response via channel, {}", e);
+                if let Err(e) = sender.send((Topic::new(topic), resp)) {
+                    error!("{}", RecipeError::Network(format!("error sending response via channel, {}", e)));
                 }
             }
             //error case
@@ -411,8 +3922,300 @@ fn respond_with_public_recipes(sender: mpsc::UnboundedSender<ListResponse>, rece
         }
     });
 }
+//the named fields of a recipe as they are serialized, the id is always kept so the answer can be matched up
+//names that arent recipe fields are left out, a recipe that cant be serialized is left out of the answer
+fn project(recipe: &Recipe, fields: &[String]) -> Option<serde_json::Map<String, serde_json::Value>> {
+    match serde_json::to_value(recipe) {
+        Ok(serde_json::Value::Object(all)) => Some(all.into_iter().filter(|(name, _)| name == "id" || fields.contains(name)).collect()),
+        _ => {
+            error!("recipe {} ({:?}) cant be serialized, fix it with edit r {}", recipe.id, recipe.name, recipe.id);
+            None
+        }
+    }
+}
+//logic for answering a name or locate query with the ids and names of the public recipes it matches
+fn respond_with_name_matches(
+    sender: mpsc::UnboundedSender<NameMatches>,
+    receiver: String,
+    request_id: Uuid,
+    permit: OwnedSemaphorePermit,
+    matches: impl Fn(&Recipe) -> bool + Send + 'static,
+) {
+    tokio::spawn(async move {
+        //held until the response is handed back to the swarm
+        let _permit = permit;
+        match storage::read_shared_recipes().await {
+            Ok(recipes) => {
+                //an empty answer is still sent so the asker knows this peer responded
+                let matches: Vec<NameMatch> = recipes
+                    .iter()
+                    .filter(|r| r.is_served_to(&receiver) && matches(r))
+                    .map(|r| NameMatch { id: r.id, name: r.name.clone() })
+                    .collect();
+                let resp = NameMatches {
+                    matches,
+                    receiver,
+                    request_id,
+                };
+                if let Err(e) = sender.send(resp) {
+                    error!("{}", RecipeError::Network(format!("error sending name matches via channel, {}", e)));
+                }
+            }
+            Err(e) => error!("error fetching local recipes to answer name query, {}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::transport::MemoryTransport;
+    use std::{future::{poll_fn, Future}, pin::Pin, sync::atomic::{AtomicBool, Ordering}, task::Poll};
+
+    const DENIED: &str = "12D3KooWDeniedPeerDeniedPeerDeniedPeerDeniedPeer";
+    const OTHER: &str = "12D3KooWOtherPeerOtherPeerOtherPeerOtherPeerOther";
+
+    #[test]
+    fn edits_keep_who_a_recipe_is_withheld_from() {
+        let mut stored = parse_recipe_args("Bread|flour|bake").unwrap();
+        stored.id = 3;
+        stored.public = true;
+        stored.denied_peers = vec![DENIED.to_owned()];
+        let edited = parse_recipe_args("Rye bread|rye flour|bake longer").unwrap().carrying_over(&stored);
+        assert_eq!(edited.id, 3);
+        assert_eq!(edited.name, "Rye bread");
+        assert!(!edited.is_served_to(DENIED));
+        assert!(edited.is_served_to(OTHER));
+    }
+
+    #[tokio::test]
+    async fn a_burst_of_requests_runs_at_most_the_configured_responders_at_once() {
+        let max = config::Config::default().max_concurrent_responders;
+        let mut responders = ResponderLimit::new(max);
+        let (sender, mut responses) = mpsc::unbounded_channel();
+        let peer = PeerId::random();
+        //the whole burst arrives before any responder gets to run, like a flood of requests in one swarm poll
+        for _ in 0..max * 10 {
+            if let Some(permit) = responders.permit(&peer) {
+                respond_with_public_recipes(sender.clone(), peer.to_string(), None, DEFAULT_TOPIC.to_owned(), Vec::new(), permit);
+            }
+        }
+        drop(sender);
+        assert_eq!(responders.dropped, (max * 9) as u64);
+        assert_eq!(responders.permits.available_permits(), 0);
+        //the channel closes once every responder has finished, answered or failed to read the storage file the tests run against
+        let mut answered = 0;
+        while let Some((_, resp)) = responses.recv().await {
+            assert_eq!(resp.receiver, peer.to_string());
+            answered += 1;
+        }
+        assert!(answered <= max);
+        //the permits come back once the responders are done
+        assert_eq!(responders.permits.available_permits(), max);
+        assert!(responders.permit(&peer).is_some());
+        assert_eq!(responders.dropped, (max * 9) as u64);
+    }
+
+    #[test]
+    fn recipes_with_unusual_values_are_still_answered() {
+        let mut odd = parse_recipe_args("Br\u{0}ead \u{1F35E}|\u{7}flour|bake\r\n").unwrap();
+        odd.id = 1;
+        odd.recipe_yield = Some(ingredients::Yield { amount: f64::NAN, unit: "loaves".to_owned() });
+        odd.structured_ingredients = vec![ingredients::Ingredient { name: "flour".to_owned(), quantity: Some(f64::INFINITY), quantity_max: None, unit: None, group: None }];
+        let mut plain = parse_recipe_args("Soup|water|boil").unwrap();
+        plain.id = 2;
+        let resp = ListResponse { mode: ListMode::ALL, data: vec![odd, plain], receiver: OTHER.to_owned(), request_id: None, projected: Vec::new() };
+        let json = serialize_response(resp).expect("the response is still sent");
+        let sent: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let ids: Vec<_> = sent["data"].as_array().unwrap().iter().map(|r| r["id"].as_u64()).collect();
+        assert_eq!(ids, [Some(1), Some(2)]);
+        //serde_json has no form for numbers that arent finite and writes them as null
+        assert!(sent["data"][0]["yield"]["amount"].is_null());
+        assert!(sent["data"][0]["structured_ingredients"][0]["quantity"].is_null());
+        assert_eq!(sent["data"][0]["name"], "Br\u{0}ead \u{1F35E}");
+    }
+
+    #[test]
+    fn recipes_that_fail_to_serialize_are_left_out_of_the_response() {
+        let recipes: Recipes = (1..=3)
+            .map(|id| {
+                let mut recipe = parse_recipe_args(&format!("Recipe {}|flour|bake", id)).unwrap();
+                recipe.id = id;
+                recipe
+            })
+            .collect();
+        let resp = ListResponse { mode: ListMode::ALL, data: recipes, receiver: OTHER.to_owned(), request_id: None, projected: Vec::new() };
+        let fail_on_2 = |r: &Recipe| match r.id {
+            2 => Err(<serde_json::Error as serde::ser::Error>::custom("recipe 2 is broken")),
+            _ => Ok(()),
+        };
+        let resp = without_broken_recipes(resp, fail_on_2);
+        assert_eq!(resp.data.iter().map(|r| r.id).collect::<Vec<_>>(), [1, 3]);
+        let sent: serde_json::Value = serde_json::from_str(&serde_json::to_string(&resp).unwrap()).unwrap();
+        assert_eq!(sent["data"].as_array().unwrap().iter().map(|r| r["id"].as_u64()).collect::<Vec<_>>(), [Some(1), Some(3)]);
+        assert_eq!(sent["receiver"], OTHER);
+    }
+
+    #[test]
+    fn legacy_recipes_from_peers_are_upgraded_to_the_structured_form() {
+        let legacy = r#"{"id":4,"name":"Bread","ingredients":"500 g flour, 2 tsp salt","instructions":"mix | knead |bake","public":true}"#;
+        let mut recipe: Recipe = serde_json::from_str(legacy).unwrap();
+        assert!(recipe.upgrade_legacy());
+        let names: Vec<_> = recipe.structured_ingredients.iter().map(|i| (i.quantity, i.unit.as_deref(), i.name.as_str())).collect();
+        assert_eq!(names, [(Some(500.0), Some("g"), "flour"), (Some(2.0), Some("tsp"), "salt")]);
+        assert_eq!(recipe.steps(), ["mix", "knead", "bake"]);
+        //the plain strings stay for peers that still read those
+        assert_eq!(recipe.ingredients, "500 g flour, 2 tsp salt");
+        assert!(!recipe.upgrade_legacy());
+    }
+
+    #[tokio::test]
+    async fn closing_an_answer_channel_shuts_the_loop_down() {
+        let (response_sender, responses) = mpsc::unbounded_channel();
+        let (name_sender, names) = mpsc::unbounded_channel();
+        let (fetch_sender, fetches) = mpsc::unbounded_channel();
+        let mut answers = Answers { responses, names, fetches };
+        name_sender.send(NameMatches { matches: Vec::new(), receiver: OTHER.to_owned(), request_id: Uuid::new_v4() }).unwrap();
+        drop(name_sender);
+        //whatever was queued before the senders went is still handled first
+        assert!(matches!(answers.next().await, EventType::NameMatches(_)));
+        //one channel closing is enough, even with the others still open
+        assert!(matches!(answers.next().await, EventType::Shutdown(1)));
+        drop((response_sender, fetch_sender));
+        assert!(matches!(answers.next().await, EventType::Shutdown(1)));
+    }
+
+    //a bare floodsub node on the in-memory transport, upgraded the way build_transport upgrades tcp and subscribed to the recipe topic
+    //its connection tasks run on tokio until stalled is set, from then on they are never polled again
+    fn floodsub_node(stalled: Arc<AtomicBool>) -> (Swarm<Floodsub>, Multiaddr) {
+        let keys = identity::Keypair::generate_ed25519();
+        let peer = PeerId::from(keys.public());
+        let auth_keys = Keypair::<X25519Spec>::new().into_authentic(&keys).unwrap();
+        let transport = MemoryTransport
+            .upgrade(upgrade::Version::V1)
+            .authenticate(libp2p::noise::NoiseConfig::xx(auth_keys).into_authenticated())
+            .multiplex(mplex::MplexConfig::new())
+            .boxed();
+        let mut floodsub = Floodsub::new(peer);
+        floodsub.subscribe(TOPIC.clone());
+        let mut swarm = SwarmBuilder::new(transport, floodsub, peer)
+            .executor(Box::new(move |mut fut: Pin<Box<dyn Future<Output = ()> + Send>>| {
+                let stalled = stalled.clone();
+                tokio::spawn(poll_fn(move |cx| if stalled.load(Ordering::SeqCst) { Poll::Pending } else { fut.as_mut().poll(cx) }));
+            }))
+            .build();
+        let address = Multiaddr::empty().with(Protocol::Memory(Uuid::new_v4().as_u128() as u64));
+        swarm.listen_on(address.clone()).unwrap();
+        (swarm, address)
+    }
+
+    #[tokio::test]
+    async fn a_peer_that_stops_reading_doesnt_hold_up_broadcasts_to_the_others() {
+        const BURST: usize = 500;
+        let stalled = Arc::new(AtomicBool::new(false));
+        let (mut publisher, _) = floodsub_node(Arc::default());
+        let (mut slow, slow_address) = floodsub_node(stalled.clone());
+        let (mut fast, fast_address) = floodsub_node(Arc::default());
+        let slow_peer = *slow.local_peer_id();
+        for (swarm, address) in [(&mut slow, slow_address), (&mut fast, fast_address)] {
+            publisher.behaviour_mut().add_node_to_partial_view(*swarm.local_peer_id());
+            swarm.behaviour_mut().add_node_to_partial_view(*publisher.local_peer_id());
+            publisher.dial_addr(address).unwrap();
+        }
+        let mut subscribed = HashSet::new();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while subscribed.len() < 2 {
+                tokio::select! {
+                    event = publisher.select_next_some() => if let SwarmEvent::Behaviour(FloodsubEvent::Subscribed { peer_id, .. }) = event {
+                        subscribed.insert(peer_id);
+                    },
+                    _ = slow.select_next_some() => {}
+                    _ = fast.select_next_some() => {}
+                }
+            }
+        })
+        .await
+        .expect("both peers subscribe to the publisher");
+
+        //from here the slow peer never reads again, neither its swarm nor its connection are polled
+        stalled.store(true, Ordering::SeqCst);
+        let started = Instant::now();
+        for n in 0..BURST {
+            //about a kilobyte each, well under the 2048 bytes floodsub reads per message
+            publisher.behaviour_mut().publish(TOPIC.clone(), format!("{:04}{}", n, "x".repeat(1000)));
+        }
+        let mut received = 0;
+        //inside the 10s an outbound substream waits before its connection is given up, so the slow peer is still being sent to
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while received < BURST {
+                tokio::select! {
+                    _ = publisher.select_next_some() => {}
+                    event = fast.select_next_some() => if let SwarmEvent::Behaviour(FloodsubEvent::Message(_)) = event {
+                        received += 1;
+                    },
+                }
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("the fast peer got {} of {} messages", received, BURST));
+        assert!(publisher.is_connected(&slow_peer), "the slow peer was dropped instead of being waited on");
+        println!("{} messages reached the fast peer in {:?}", BURST, started.elapsed());
+    }
 
+    #[test]
+    fn stale_updates_are_refused() {
+        let config = config::Config { max_update_age_secs: 3600, ..config::Config::default() };
+        let now = 1_700_000_000;
+        let reason = update_rejection(Some(now - 3601), now, &config).expect("an update older than the limit is refused");
+        assert!(reason.contains("max_update_age_secs"), "{}", reason);
+        assert_eq!(update_rejection(Some(now - 3600), now, &config), None);
+        //recipes from before change times were kept cant be dated, so they arent refused
+        assert_eq!(update_rejection(None, now, &config), None);
+        assert_eq!(update_rejection(Some(0), now, &config::Config::default()), None);
+    }
+
+    #[test]
+    fn future_dated_updates_are_refused() {
+        let config = config::Config { max_future_skew_secs: 300, ..config::Config::default() };
+        let now = 1_700_000_000;
+        let reason = update_rejection(Some(now + 301), now, &config).expect("an update too far ahead is refused");
+        assert!(reason.contains("max_future_skew_secs"), "{}", reason);
+        assert_eq!(update_rejection(Some(now + 300), now, &config), None);
+        assert_eq!(update_rejection(Some(now - 86400 * 365), now, &config), None);
+        assert_eq!(update_rejection(Some(u64::MAX), now, &config::Config::default()), None);
+    }
 
+    #[test]
+    fn only_peers_on_the_trust_list_are_trusted() {
+        let (trusted, untrusted) = (PeerId::random(), PeerId::random());
+        let list = trust_list(&[trusted.to_string(), "not a peer id".to_owned()]);
+        assert!(is_trusted_by(list.as_ref(), &trusted));
+        assert!(!is_trusted_by(list.as_ref(), &untrusted));
+    }
 
+    #[test]
+    fn without_a_trust_list_every_peer_is_trusted() {
+        assert!(is_trusted_by(trust_list(&[]).as_ref(), &PeerId::random()));
+        //a list with no usable entry doesnt fall back to trusting everyone
+        let broken = trust_list(&["not a peer id".to_owned()]);
+        assert!(!is_trusted_by(broken.as_ref(), &PeerId::random()));
+    }
 
+    #[test]
+    fn peers_past_the_failure_threshold_are_pruned_unless_allowlisted() {
+        let config = config::Config { peer_prune_failures: 3, peer_prune_ttl_secs: 0, ..config::Config::default() };
+        assert_eq!(prune_reason(false, 3, Some(10), &config).as_deref(), Some("3 failed dials in a row"));
+        assert_eq!(prune_reason(false, 2, Some(10), &config), None);
+        assert_eq!(prune_reason(true, 30, Some(10), &config), None);
+    }
 
+    #[test]
+    fn peers_unseen_past_the_ttl_are_pruned_unless_allowlisted() {
+        let config = config::Config { peer_prune_failures: 0, peer_prune_ttl_secs: 3600, ..config::Config::default() };
+        assert_eq!(prune_reason(false, 100, Some(3601), &config).as_deref(), Some("last seen 3601s ago"));
+        assert_eq!(prune_reason(false, 100, Some(3600), &config), None);
+        //a peer never seen has nothing to measure the ttl from
+        assert_eq!(prune_reason(false, 0, None, &config), None);
+        assert_eq!(prune_reason(true, 0, Some(u64::MAX), &config), None);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::error::{RecipeError, RecipeResult};
+use crate::Recipe;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+//file path for published collection manifests
+const MANIFESTS_FILE_PATH: &str = "./manifests.json";
+
+//a curated, ordered set of local recipes shared under one name, e.g. "thanksgiving-menu"
+//entries refer to recipes in the active collection by id, peers fetch each one on its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+//one recipe in a manifest, the name is only there so a listing makes sense before anything is fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub id: usize,
+    pub name: String,
+}
+
+impl Manifest {
+    //builds a manifest out of local recipes in the given order, every id has to exist
+    pub fn from_recipes(name: &str, ids: &[usize], recipes: &[Recipe]) -> RecipeResult<Manifest> {
+        let entries = ids
+            .iter()
+            .map(|id| {
+                recipes
+                    .iter()
+                    .find(|r| r.id == *id)
+                    .map(|r| ManifestEntry { id: r.id, name: r.name.clone() })
+                    .ok_or(RecipeError::NotFound { id: *id })
+            })
+            .collect::<RecipeResult<Vec<_>>>()?;
+        Ok(Manifest { name: name.to_owned(), entries })
+    }
+}
+
+//logic for reading manifests, no file yet just means none were published
+async fn read_manifests() -> RecipeResult<Vec<Manifest>> {
+    match fs::read(MANIFESTS_FILE_PATH).await {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//saves a manifest, replacing an earlier one with the same name
+pub async fn save(manifest: &Manifest) -> RecipeResult<()> {
+    let mut manifests = read_manifests().await?;
+    manifests.retain(|m| m.name != manifest.name);
+    manifests.push(manifest.clone());
+    let json = serde_json::to_string(&manifests)?;
+    fs::write(MANIFESTS_FILE_PATH, &json).await?;
+    Ok(())
+}
+
+//a published manifest by name
+pub async fn get(name: &str) -> RecipeResult<Option<Manifest>> {
+    Ok(read_manifests().await?.into_iter().find(|m| m.name == name))
+}
+
+//progress of fetching every recipe in a manifest from its author
+pub struct ManifestFetch {
+    pub name: String,
+    //ids still waiting for an answer
+    pub remaining: Vec<usize>,
+    pub fetched: Vec<Recipe>,
+    //entries the author didnt serve, e.g. because they were made private since
+    pub missing: Vec<ManifestEntry>,
+    entries: Vec<ManifestEntry>,
+}
+
+impl ManifestFetch {
+    pub fn new(manifest: Manifest) -> ManifestFetch {
+        ManifestFetch {
+            name: manifest.name,
+            remaining: manifest.entries.iter().map(|e| e.id).collect(),
+            fetched: Vec::new(),
+            missing: Vec::new(),
+            entries: manifest.entries,
+        }
+    }
+
+    //records the answer for one entry, a None recipe counts the entry as missing
+    pub fn record(&mut self, id: usize, recipe: Option<Recipe>) {
+        self.remaining.retain(|r| *r != id);
+        match recipe {
+            Some(recipe) => self.fetched.push(recipe),
+            None => self.missing.extend(self.entries.iter().find(|e| e.id == id).cloned()),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    //fetched recipes in manifest order
+    pub fn ordered(&self) -> Vec<&Recipe> {
+        self.entries
+            .iter()
+            .filter_map(|e| self.fetched.iter().find(|r| r.id == e.id))
+            .collect()
+    }
+}
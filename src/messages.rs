@@ -0,0 +1,95 @@
+use crate::manifests::Manifest;
+use crate::{Recipe, Recipes};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ListMode {
+    ALL,
+    One(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListRequest {
+    pub mode: ListMode,
+    //echoed back in responses so they can be matched to this request, older peers dont send one
+    #[serde(default)]
+    pub request_id: Option<Uuid>,
+    //recipe fields wanted in the response, empty asks for whole recipes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListResponse {
+    pub mode: ListMode,
+    pub data: Recipes,
+    pub receiver: String,
+    #[serde(default)]
+    pub request_id: Option<Uuid>,
+    //answers to a request for some fields only carry those in place of data, always with the id
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projected: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+//asks every peer for public recipes whose name contains the given text
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NameQuery {
+    pub name: String,
+    pub request_id: Uuid,
+}
+
+//asks every peer whether it serves a recipe, answered with NameMatches so only availability comes back
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocateQuery {
+    pub locate: LocateTarget,
+    pub request_id: Uuid,
+}
+
+//names are matched whole ignoring case, unlike find-remote which matches any part of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LocateTarget {
+    Name(String),
+    Id(usize),
+}
+
+impl LocateTarget {
+    pub fn matches(&self, recipe: &Recipe) -> bool {
+        self.matches_listing(recipe.id, &recipe.name)
+    }
+
+    //the same check against a recipe only known by its id and name, as digests list them
+    pub fn matches_listing(&self, id: usize, name: &str) -> bool {
+        match self {
+            LocateTarget::Name(wanted) => name.eq_ignore_ascii_case(wanted),
+            LocateTarget::Id(wanted) => id == *wanted,
+        }
+    }
+}
+
+//a recipe matching a name query, just enough to fetch it afterwards
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NameMatch {
+    pub id: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NameMatches {
+    pub matches: Vec<NameMatch>,
+    pub receiver: String,
+    pub request_id: Uuid,
+}
+
+//broadcast when a recipe is published as it is created, just enough to fetch it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecipeAnnouncement {
+    pub published: NameMatch,
+}
+
+//broadcast when a collection manifest is published so peers know it can be fetched
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestAnnouncement {
+    pub manifest: Manifest,
+}
@@ -0,0 +1,114 @@
+use crate::error::{RecipeError, RecipeResult};
+use crate::{Recipe, Recipes};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+//layout storage files are written in, bumped whenever a migration is added to MIGRATIONS
+pub const SCHEMA_VERSION: u32 = 2;
+
+//files from before the version was recorded are a bare array of recipes
+const UNVERSIONED: u32 = 1;
+
+//takes the recipes of a file from one version to the next
+type Migration = fn(Vec<Value>) -> RecipeResult<Vec<Value>>;
+
+//in order, the first one takes an unversioned file to version 2
+const MIGRATIONS: &[Migration] = &[fill_structured_fields];
+
+//a storage file as it is written
+#[derive(Serialize)]
+pub struct StorageFile<'a> {
+    pub schema_version: u32,
+    pub recipes: &'a [Recipe],
+}
+
+//only the version, read first so a current file is parsed straight into recipes
+#[derive(Deserialize)]
+struct Header {
+    schema_version: u32,
+}
+
+#[derive(Deserialize)]
+struct Current {
+    recipes: Recipes,
+}
+
+//the version a parsed file is at and its recipes as they are stored at that version
+pub fn records(value: Value) -> RecipeResult<(u32, Vec<Value>)> {
+    match value {
+        Value::Array(recipes) => Ok((UNVERSIONED, recipes)),
+        Value::Object(mut file) => {
+            let version = file
+                .get("schema_version")
+                .and_then(|v| v.as_u64())
+                .and_then(|v| u32::try_from(v).ok())
+                .filter(|v| *v >= UNVERSIONED)
+                .ok_or_else(|| RecipeError::Validation("storage file has no valid schema_version".to_owned()))?;
+            match file.remove("recipes") {
+                Some(Value::Array(recipes)) => Ok((version, recipes)),
+                _ => Err(RecipeError::Validation("storage file has no recipes list".to_owned())),
+            }
+        }
+        _ => Err(RecipeError::Validation("storage file is not a list of recipes".to_owned())),
+    }
+}
+
+//reads a storage file of any version, returning the version it was at and its recipes brought up to the current one
+pub fn parse(content: &[u8]) -> RecipeResult<(u32, Recipes)> {
+    let bare_array = content.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'[');
+    if !bare_array && serde_json::from_slice::<Header>(content)?.schema_version == SCHEMA_VERSION {
+        return Ok((SCHEMA_VERSION, serde_json::from_slice::<Current>(content)?.recipes));
+    }
+    let (version, recipes) = records(serde_json::from_slice(content)?)?;
+    if version > SCHEMA_VERSION {
+        return Err(RecipeError::Validation(format!(
+            "storage file is at schema version {}, this version only reads up to {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+    let recipes = MIGRATIONS[(version - UNVERSIONED) as usize..].iter().try_fold(recipes, |recipes, migration| migration(recipes))?;
+    let recipes = recipes.into_iter().map(serde_json::from_value).collect::<Result<Recipes, _>>()?;
+    Ok((version, recipes))
+}
+
+//unversioned to 2, recipes saved with only the plain ingredients string or pipe separated steps get the structured forms
+fn fill_structured_fields(recipes: Vec<Value>) -> RecipeResult<Vec<Value>> {
+    recipes
+        .into_iter()
+        .map(|value| {
+            let mut recipe: Recipe = serde_json::from_value(value)?;
+            recipe.upgrade_legacy();
+            Ok(serde_json::to_value(recipe)?)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: &str = r#"[{"id":0,"name":"Bread","ingredients":"500 g flour, 1 tsp salt","instructions":"mix|knead|bake","public":true}]"#;
+
+    #[test]
+    fn a_v1_file_is_migrated_to_the_current_version() {
+        let (version, recipes) = parse(V1.as_bytes()).unwrap();
+        assert_eq!(version, UNVERSIONED);
+        let recipe = &recipes[0];
+        assert_eq!(recipe.structured_ingredients.iter().map(|i| i.name.as_str()).collect::<Vec<_>>(), ["flour", "salt"]);
+        assert_eq!(recipe.steps(), ["mix", "knead", "bake"]);
+        //written back it is a current file, which reads as is
+        let written = serde_json::to_vec(&StorageFile { schema_version: SCHEMA_VERSION, recipes: &recipes }).unwrap();
+        let (version, reread) = parse(&written).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        assert_eq!(serde_json::to_value(&reread).unwrap(), serde_json::to_value(&recipes).unwrap());
+    }
+
+    #[test]
+    fn files_from_a_newer_version_are_refused() {
+        let newer = format!(r#"{{"schema_version":{},"recipes":[]}}"#, SCHEMA_VERSION + 1);
+        assert!(matches!(parse(newer.as_bytes()), Err(RecipeError::Validation(_))));
+        assert!(matches!(parse(br#"{"schema_version":0,"recipes":[]}"#), Err(RecipeError::Validation(_))));
+        let no_recipes = format!(r#"{{"schema_version":{}}}"#, SCHEMA_VERSION);
+        assert!(parse(no_recipes.as_bytes()).is_err());
+    }
+}
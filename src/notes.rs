@@ -0,0 +1,61 @@
+use crate::error::RecipeResult;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+//file path for notes, these never leave the local machine
+const NOTES_FILE_PATH: &str = "./notes.json";
+
+//a private annotation on somebody's recipe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    //peer id of the recipe's author
+    pub author: String,
+    pub recipe_id: usize,
+    pub text: String,
+    //seconds since the unix epoch
+    pub created_at: u64,
+}
+
+//logic for reading notes, no file yet just means no notes
+async fn read_notes() -> RecipeResult<Vec<Note>> {
+    match fs::read(NOTES_FILE_PATH).await {
+        Ok(content) => Ok(serde_json::from_slice(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//logic for writing notes
+async fn write_notes(notes: &[Note]) -> RecipeResult<()> {
+    let json = serde_json::to_string(notes)?;
+    fs::write(NOTES_FILE_PATH, &json).await?;
+    Ok(())
+}
+
+//adds a note to the recipe identified by author and id
+pub async fn add_note(author: &str, recipe_id: usize, text: &str) -> RecipeResult<Note> {
+    let mut notes = read_notes().await?;
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let note = Note {
+        author: author.to_owned(),
+        recipe_id,
+        text: text.to_owned(),
+        created_at,
+    };
+    notes.push(note.clone());
+    write_notes(&notes).await?;
+    Ok(note)
+}
+
+//returns the notes for a recipe in the order they were written
+pub async fn notes_for(author: &str, recipe_id: usize) -> RecipeResult<Vec<Note>> {
+    let notes = read_notes().await?;
+    Ok(notes
+        .into_iter()
+        .filter(|n| n.author == author && n.recipe_id == recipe_id)
+        .collect())
+}
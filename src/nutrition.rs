@@ -0,0 +1,183 @@
+use crate::config;
+use crate::error::RecipeResult;
+use crate::ingredients::Ingredient;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::fs;
+
+//what 100 g of an ingredient holds, grams_each lets counted ingredients like "2 eggs" be weighed
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Nutrients {
+    pub calories: f64,
+    pub protein: f64,
+    pub carbs: f64,
+    pub fat: f64,
+    #[serde(default)]
+    pub grams_each: Option<f64>,
+}
+
+//shorthand for the bundled table, calories, protein, carbs and fat then the weight of one if they are counted
+const fn per_100g(calories: f64, protein: f64, carbs: f64, fat: f64, grams_each: Option<f64>) -> Nutrients {
+    Nutrients { calories, protein, carbs, fat, grams_each }
+}
+
+//rough values per 100 g for common ingredients
+const BUNDLED: &[(&str, Nutrients)] = &[
+    ("flour", per_100g(364.0, 10.3, 76.3, 1.0, None)),
+    ("bread", per_100g(265.0, 9.0, 49.0, 3.2, Some(30.0))),
+    ("rice", per_100g(130.0, 2.7, 28.0, 0.3, None)),
+    ("pasta", per_100g(131.0, 5.0, 25.0, 1.1, None)),
+    ("oats", per_100g(389.0, 16.9, 66.3, 6.9, None)),
+    ("sugar", per_100g(387.0, 0.0, 100.0, 0.0, None)),
+    ("honey", per_100g(304.0, 0.3, 82.4, 0.0, None)),
+    ("butter", per_100g(717.0, 0.9, 0.1, 81.1, None)),
+    ("olive oil", per_100g(884.0, 0.0, 0.0, 100.0, None)),
+    ("oil", per_100g(884.0, 0.0, 0.0, 100.0, None)),
+    ("milk", per_100g(61.0, 3.2, 4.8, 3.3, None)),
+    ("cream", per_100g(340.0, 2.8, 2.7, 36.0, None)),
+    ("yogurt", per_100g(61.0, 3.5, 4.7, 3.3, None)),
+    ("cheese", per_100g(402.0, 25.0, 1.3, 33.0, None)),
+    ("egg", per_100g(143.0, 12.6, 0.7, 9.5, Some(50.0))),
+    ("chicken", per_100g(165.0, 31.0, 0.0, 3.6, None)),
+    ("beef", per_100g(250.0, 26.0, 0.0, 15.0, None)),
+    ("pork", per_100g(242.0, 27.0, 0.0, 14.0, None)),
+    ("bacon", per_100g(541.0, 37.0, 1.4, 42.0, Some(8.0))),
+    ("salmon", per_100g(208.0, 20.0, 0.0, 13.0, None)),
+    ("tofu", per_100g(76.0, 8.0, 1.9, 4.8, None)),
+    ("lentils", per_100g(116.0, 9.0, 20.0, 0.4, None)),
+    ("chickpeas", per_100g(164.0, 8.9, 27.4, 2.6, None)),
+    ("beans", per_100g(127.0, 8.7, 22.8, 0.5, None)),
+    ("potato", per_100g(77.0, 2.0, 17.0, 0.1, Some(170.0))),
+    ("onion", per_100g(40.0, 1.1, 9.3, 0.1, Some(110.0))),
+    ("garlic", per_100g(149.0, 6.4, 33.1, 0.5, Some(5.0))),
+    ("carrot", per_100g(41.0, 0.9, 9.6, 0.2, Some(60.0))),
+    ("tomato", per_100g(18.0, 0.9, 3.9, 0.2, Some(120.0))),
+    ("leek", per_100g(61.0, 1.5, 14.2, 0.3, Some(90.0))),
+    ("spinach", per_100g(23.0, 2.9, 3.6, 0.4, None)),
+    ("mushroom", per_100g(22.0, 3.1, 3.3, 0.3, Some(18.0))),
+    ("pepper", per_100g(31.0, 1.0, 6.0, 0.3, Some(120.0))),
+    ("apple", per_100g(52.0, 0.3, 13.8, 0.2, Some(180.0))),
+    ("banana", per_100g(89.0, 1.1, 22.8, 0.3, Some(120.0))),
+    ("lemon", per_100g(29.0, 1.1, 9.3, 0.3, Some(60.0))),
+    ("chocolate", per_100g(546.0, 4.9, 61.0, 31.0, None)),
+    ("cocoa", per_100g(228.0, 19.6, 57.9, 13.7, None)),
+    ("almonds", per_100g(579.0, 21.2, 21.6, 49.9, None)),
+    ("peanut butter", per_100g(588.0, 25.0, 20.0, 50.0, None)),
+    ("water", per_100g(0.0, 0.0, 0.0, 0.0, None)),
+    ("salt", per_100g(0.0, 0.0, 0.0, 0.0, None)),
+    ("yeast", per_100g(325.0, 40.4, 41.2, 7.6, None)),
+];
+
+//grams in one of a unit, volumes are weighed as if they were water
+const UNIT_GRAMS: &[(&str, f64)] = &[
+    ("mg", 0.001),
+    ("g", 1.0),
+    ("kg", 1000.0),
+    ("ml", 1.0),
+    ("l", 1000.0),
+    ("tsp", 5.0),
+    ("tbsp", 15.0),
+    ("cup", 240.0),
+    ("cups", 240.0),
+    ("oz", 28.35),
+    ("lb", 453.6),
+    ("lbs", 453.6),
+    ("pinch", 0.4),
+];
+
+//the bundled table with the user's nutrition_file laid over it, entries there replace bundled ones of the same name
+pub async fn table() -> RecipeResult<HashMap<String, Nutrients>> {
+    let mut table: HashMap<String, Nutrients> = BUNDLED
+        .iter()
+        .map(|&(name, nutrients)| (name.to_owned(), nutrients))
+        .collect();
+    let path = config::get()?.nutrition_file.clone();
+    if !path.is_empty() {
+        let overrides: HashMap<String, Nutrients> = serde_json::from_slice(&fs::read(&path).await?)?;
+        table.extend(overrides.into_iter().map(|(name, n)| (name.to_lowercase(), n)));
+    }
+    Ok(table)
+}
+
+//the table entry for an ingredient name, "eggs" finds egg and "plain flour" finds flour
+fn lookup<'a>(table: &'a HashMap<String, Nutrients>, name: &str) -> Option<&'a Nutrients> {
+    let name = name.trim().to_lowercase();
+    let singular = name.strip_suffix("es").filter(|s| table.contains_key(*s)).or_else(|| name.strip_suffix('s'));
+    table.get(&name).or_else(|| singular.and_then(|s| table.get(s))).or_else(|| {
+        //otherwise the longest entry the name ends with as a whole word
+        table
+            .iter()
+            .filter(|(entry, _)| name.ends_with(&format!(" {}", entry)) || singular.is_some_and(|s| s.ends_with(&format!(" {}", entry))))
+            .max_by_key(|(entry, _)| entry.len())
+            .map(|(_, n)| n)
+    })
+}
+
+//grams of an ingredient, or why it couldnt be weighed
+fn grams(ingredient: &Ingredient, nutrients: &Nutrients) -> Result<f64, String> {
+    let quantity = ingredient.typical_quantity().ok_or("no quantity")?;
+    match &ingredient.unit {
+        Some(unit) => UNIT_GRAMS
+            .iter()
+            .find(|(u, _)| u == unit)
+            .map(|(_, g)| quantity * g)
+            .ok_or_else(|| format!("{} cant be weighed", unit)),
+        None => nutrients.grams_each.map(|g| quantity * g).ok_or_else(|| "needs a unit to be weighed".to_owned()),
+    }
+}
+
+//totals for a recipe and the ingredients left out of them
+#[derive(Debug, Default)]
+pub struct Estimate {
+    pub calories: f64,
+    pub protein: f64,
+    pub carbs: f64,
+    pub fat: f64,
+    pub estimated: usize,
+    //ingredient and why it wasnt estimated
+    pub not_estimated: Vec<(String, String)>,
+}
+
+impl Estimate {
+    //share of ingredients that went into the totals
+    pub fn coverage(&self) -> f64 {
+        let total = self.estimated + self.not_estimated.len();
+        if total == 0 {
+            0.0
+        } else {
+            self.estimated as f64 * 100.0 / total as f64
+        }
+    }
+
+    //the totals divided between servings, the coverage stays the same
+    pub fn per_serving(mut self, servings: u32) -> Estimate {
+        let servings = f64::from(servings.max(1));
+        self.calories /= servings;
+        self.protein /= servings;
+        self.carbs /= servings;
+        self.fat /= servings;
+        self
+    }
+}
+
+//adds up what the structured ingredients hold, ingredients the table or units dont cover are listed instead
+pub fn estimate(ingredients: &[Ingredient], table: &HashMap<String, Nutrients>) -> Estimate {
+    let mut estimate = Estimate::default();
+    for ingredient in ingredients {
+        let weighed = match lookup(table, &ingredient.name) {
+            Some(nutrients) => grams(ingredient, nutrients).map(|g| (g / 100.0, nutrients)),
+            None => Err("not in the nutrition table".to_owned()),
+        };
+        match weighed {
+            Ok((hundreds, n)) => {
+                estimate.calories += n.calories * hundreds;
+                estimate.protein += n.protein * hundreds;
+                estimate.carbs += n.carbs * hundreds;
+                estimate.fat += n.fat * hundreds;
+                estimate.estimated += 1;
+            }
+            Err(reason) => estimate.not_estimated.push((ingredient.to_string(), reason)),
+        }
+    }
+    estimate
+}
@@ -0,0 +1,116 @@
+use crate::error::{RecipeError, RecipeResult};
+use crate::Recipe;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+//broadcast when a published recipe is edited, only the fields that changed are sent
+//base tells receivers which version the patch applies to, anyone holding a different one fetches the whole recipe instead
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecipePatch {
+    //peer id of the recipe's author, receivers only apply patches the author sent
+    pub author: String,
+    pub id: usize,
+    pub base: u64,
+    //serialized field name to its new value
+    pub changed_fields: Map<String, Value>,
+}
+
+//fingerprint of a recipe as it is serialized, fnv-1a so every peer computes the same one whatever it was built with
+pub fn version(recipe: &Recipe) -> RecipeResult<u64> {
+    let bytes = serde_json::to_vec(recipe)?;
+    Ok(bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3)))
+}
+
+//a recipe as a json object, field by field
+fn fields(recipe: &Recipe) -> RecipeResult<Map<String, Value>> {
+    match serde_json::to_value(recipe)? {
+        Value::Object(fields) => Ok(fields),
+        _ => unreachable!("recipes serialize to objects"),
+    }
+}
+
+//the fields of after that differ from before, empty when nothing changed
+//fields left out of after when empty, like an image_url that was removed, are sent as null
+pub fn changed_fields(before: &Recipe, after: &Recipe) -> RecipeResult<Map<String, Value>> {
+    let before = fields(before)?;
+    let after = fields(after)?;
+    let removed: Vec<(String, Value)> = before.keys().filter(|name| !after.contains_key(*name)).map(|name| (name.clone(), Value::Null)).collect();
+    Ok(after.into_iter().filter(|(name, value)| before.get(name) != Some(value)).chain(removed).collect())
+}
+
+//a copy of the recipe with the changed fields laid over it, the id a patch is addressed by never changes
+pub fn apply(recipe: &Recipe, changed_fields: &Map<String, Value>) -> RecipeResult<Recipe> {
+    if changed_fields.contains_key("id") {
+        return Err(RecipeError::Validation("a patch cant change a recipe's id".to_owned()));
+    }
+    let mut patched = fields(recipe)?;
+    for (name, value) in changed_fields {
+        //a null field goes back to its default, the way the author's copy leaves it out
+        match value {
+            Value::Null => patched.remove(name),
+            value => patched.insert(name.clone(), value.clone()),
+        };
+    }
+    Ok(serde_json::from_value(Value::Object(patched))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn published() -> Recipe {
+        let mut recipe = crate::recipe::parse_recipe_args("Bread|flour, water|knead\nbake").unwrap();
+        recipe.id = 7;
+        recipe.public = true;
+        recipe.tags = vec!["baking".to_owned()];
+        recipe.image_url = Some("https://example.com/bread.png".to_owned());
+        recipe
+    }
+
+    //what a receiver holds after applying the patch for before -> after to its copy of before
+    fn converged(before: &Recipe, after: &Recipe) -> Recipe {
+        let changed = changed_fields(before, after).unwrap();
+        apply(before, &changed).unwrap()
+    }
+
+    #[test]
+    fn a_tag_only_edit_sends_just_the_tags() {
+        let before = published();
+        let mut after = before.clone();
+        after.tags.push("quick".to_owned());
+        let changed = changed_fields(&before, &after).unwrap();
+        assert_eq!(changed.keys().collect::<Vec<_>>(), ["tags"]);
+        assert!(changed_fields(&before, &before).unwrap().is_empty());
+    }
+
+    #[test]
+    fn receivers_converge_on_the_edited_recipe() {
+        let before = published();
+        let mut after = before.clone();
+        after.tags = vec!["bread".to_owned(), "quick".to_owned()];
+        after.instructions = "knead\nrest\nbake".to_owned();
+        after.servings = Some(4);
+        let patched = converged(&before, &after);
+        assert_eq!(version(&patched).unwrap(), version(&after).unwrap());
+        assert_eq!(serde_json::to_value(&patched).unwrap(), serde_json::to_value(&after).unwrap());
+    }
+
+    #[test]
+    fn removed_fields_are_removed_on_the_receiver_too() {
+        let before = published();
+        let mut after = before.clone();
+        after.image_url = None;
+        after.tags.clear();
+        let patched = converged(&before, &after);
+        assert_eq!(patched.image_url, None);
+        assert!(patched.tags.is_empty());
+        assert_eq!(version(&patched).unwrap(), version(&after).unwrap());
+    }
+
+    #[test]
+    fn a_patch_cant_move_a_recipe_to_another_id() {
+        let mut changed = Map::new();
+        changed.insert("id".to_owned(), Value::from(8));
+        assert!(matches!(apply(&published(), &changed), Err(RecipeError::Validation(_))));
+    }
+}
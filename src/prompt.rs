@@ -0,0 +1,179 @@
+use log::{debug, error};
+use once_cell::sync::Lazy;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::io::IsTerminal;
+use std::sync::Mutex;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::mpsc;
+
+//file path for the history of commands typed at the prompt
+pub const HISTORY_FILE_PATH: &str = "./history.txt";
+
+//a command waiting for the user to confirm it, the next line typed is the answer
+static AWAITING_CONFIRMATION: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+//existing tags offered when completing the tags argument of create r and edit r, most used first
+static KNOWN_TAGS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+//pipe separated argument of create r and edit r that holds the tags
+const TAGS_ARGUMENT: usize = 3;
+
+//command keywords offered by tab completion and listed by help, kept in step with the commands main dispatches on
+//archive and tag lead their own subcommands, which tell what they take when given something else
+const COMMANDS: &[&str] = &[
+    "help", "ls p", "ls p --format", "ls r", "ls r --table", "ls r --since", "create r", "edit r", "import r", "merge", "publish r", "archive r", "unarchive r", "archive list", "archive export", "deny r", "allow r",
+    "delete r", "interest", "note", "made", "show r", "relay status", "requests", "save", "config set", "replay",
+    "collection", "status", "peer protocols", "peer ping", "peer stats", "peer prune", "graph export", "find-remote", "locate", "find", "diff r", "card r", "cook r", "export r", "image fetch r", "scale r",
+    "yield r", "pin r", "unpin r", "pinned list", "report ingredients", "cache", "share r", "open",
+    "topic", "undo", "archive", "tag", "tag rename", "tag merge", "tag apply", "tag suggest", "nutrition r", "seed",
+];
+
+//completes the command keyword being typed and the tags of create r and edit r, other arguments are left alone
+struct CommandHelper;
+
+impl Completer for CommandHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let typed = &line[..pos];
+        if (typed.starts_with("create r") || typed.starts_with("edit r")) && typed.matches('|').count() == TAGS_ARGUMENT {
+            //only the tag after the last comma is completed, an empty one lists every tag
+            let tag = typed.rsplit(['|', ',']).next().unwrap_or_default().trim_start();
+            let wanted = tag.to_lowercase();
+            let candidates = known_tags()
+                .into_iter()
+                .filter(|t| t.to_lowercase().starts_with(&wanted) && t.len() > tag.len())
+                .collect();
+            return Ok((pos - tag.len(), candidates));
+        }
+        let candidates = COMMANDS.iter().filter(|c| c.starts_with(typed) && c.len() > typed.len()).map(|c| c.to_string()).collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
+
+//whether a line starts with a command keyword as a whole word, anything else is turned away before dispatch
+pub fn is_command(line: &str) -> bool {
+    COMMANDS.iter().any(|c| line.strip_prefix(c).is_some_and(|rest| rest.is_empty() || rest.starts_with(' ')))
+}
+
+//every command keyword, for help
+pub fn command_list() -> String {
+    COMMANDS.join(", ")
+}
+
+//replaces the tags offered for completion
+pub fn set_known_tags(tags: Vec<String>) {
+    if let Ok(mut known) = KNOWN_TAGS.lock() {
+        *known = tags;
+    }
+}
+
+fn known_tags() -> Vec<String> {
+    KNOWN_TAGS.lock().map(|t| t.clone()).unwrap_or_default()
+}
+
+//whether commands are typed by someone who can answer a question, piped input never is
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal()
+}
+
+//holds back a command until the next line confirms it
+pub fn ask_confirmation(command: String) {
+    if let Ok(mut pending) = AWAITING_CONFIRMATION.lock() {
+        *pending = Some(command);
+    }
+}
+
+//the command waiting for confirmation, if any, it is only ever asked about once
+pub fn take_confirmation() -> Option<String> {
+    AWAITING_CONFIRMATION.lock().ok()?.take()
+}
+
+//reads command lines in the background, the channel closes once input ends or ctrl-c is pressed at the prompt
+//on a terminal lines are read with a prompt, history and tab completion, otherwise stdin is read line by line
+pub fn spawn_input(prompt: String) -> mpsc::UnboundedReceiver<String> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    if is_interactive() {
+        std::thread::spawn(move || read_interactive(&prompt, sender));
+    } else {
+        tokio::spawn(async move {
+            let mut stdin = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+            while let Ok(Some(line)) = stdin.next_line().await {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    receiver
+}
+
+//the line editor blocks, so it runs on its own thread
+fn read_interactive(prompt: &str, sender: mpsc::UnboundedSender<String>) {
+    let mut editor: Editor<CommandHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            error!("cant start the line editor: {}", e);
+            return;
+        }
+    };
+    editor.set_helper(Some(CommandHelper));
+    //there is no history on the first run
+    if let Err(e) = editor.load_history(HISTORY_FILE_PATH) {
+        debug!("no command history loaded from {}: {}", HISTORY_FILE_PATH, e);
+    }
+    loop {
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            //ctrl-c and ctrl-d both end the session
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                error!("error reading input: {}", e);
+                break;
+            }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+            //saved after every command so it survives however the node is stopped
+            if let Err(e) = editor.save_history(HISTORY_FILE_PATH) {
+                error!("error saving command history to {}: {}", HISTORY_FILE_PATH, e);
+            }
+        }
+        if sender.send(line).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_matched_as_whole_words() {
+        assert!(is_command("ls r"));
+        assert!(is_command("ls r --since 2h"));
+        assert!(is_command("made 3"));
+        assert!(is_command("tag frobnicate"));
+        assert!(!is_command("madeup"));
+        assert!(!is_command("ls"));
+        assert!(!is_command(""));
+        assert!(!is_command("frobnicate"));
+    }
+}
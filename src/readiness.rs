@@ -0,0 +1,66 @@
+use libp2p::PeerId;
+use log::{error, info};
+use std::fs;
+use std::path::PathBuf;
+
+//the file a container orchestrator probes for readiness, e.g. a kubernetes exec probe running: test -f <readiness_file>
+//it exists while the node listens, the startup grace period is over and, if required, a peer is connected
+//liveness is the process itself, the node exits with a non-zero code when it cant recover its listeners
+pub struct Readiness {
+    path: Option<PathBuf>,
+    require_peer: bool,
+    ready: bool,
+}
+
+impl Readiness {
+    //an empty path writes no file, one left behind by an earlier run is removed so it cant report a node that isnt up yet
+    pub fn new(path: &str, require_peer: bool) -> Readiness {
+        let readiness = Readiness {
+            path: (!path.is_empty()).then(|| PathBuf::from(path)),
+            require_peer,
+            ready: false,
+        };
+        readiness.remove();
+        readiness
+    }
+
+    //writes or removes the file when the node becomes ready or stops being ready
+    pub fn update(&mut self, peer: &PeerId, listening: bool, grace_over: bool, connected_peers: usize) {
+        let ready = listening && grace_over && (!self.require_peer || connected_peers > 0);
+        if ready == self.ready || self.path.is_none() {
+            return;
+        }
+        self.ready = ready;
+        if ready {
+            self.write(peer);
+        } else {
+            info!("Not ready any more, {}", if listening { "no peers are connected" } else { "no listeners are up" });
+            self.remove();
+        }
+    }
+
+    //removes the file on shutdown
+    pub fn clear(&mut self) {
+        self.ready = false;
+        self.remove();
+    }
+
+    fn write(&self, peer: &PeerId) {
+        if let Some(path) = &self.path {
+            match fs::write(path, format!("{}\n", peer)) {
+                Ok(()) => info!("Ready, wrote {}", path.display()),
+                Err(e) => error!("error writing readiness file {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn remove(&self) {
+        if let Some(path) = &self.path {
+            match fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => error!("error removing readiness file {}: {}", path.display(), e),
+            }
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::error::{RecipeError, RecipeResult};
+use crate::ingredients::{self, Ingredient, Substitution, Yield};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//every node is on the default topic, further topics come from config or topic subscribe
+pub const DEFAULT_TOPIC: &str = "recipes";
+
+//creates recipes type out of a list of the recipe type
+pub type Recipes = Vec<Recipe>;
+
+//format shared by create r and edit r
+pub const RECIPE_ARGS_FORMAT: &str = "name|ingredients or section: ingredients; section: ingredients|instructions[|tags|category|dietary|substitutions|prep minutes|cook minutes|image url]";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    pub id: usize,
+    pub name: String,
+    pub ingredients: String,
+    pub instructions: String,
+    pub public: bool,
+    //defaults keep recipes written before these fields existed readable
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub dietary: Vec<String>,
+    //parsed form of ingredients, the plain string is kept so older peers can still read the recipe
+    #[serde(default)]
+    pub structured_ingredients: Vec<Ingredient>,
+    #[serde(default)]
+    pub servings: Option<u32>,
+    //what the recipe makes by weight or volume, "yield" is a keyword so the field is renamed
+    #[serde(default, rename = "yield")]
+    pub recipe_yield: Option<Yield>,
+    #[serde(default)]
+    pub substitutions: Vec<Substitution>,
+    //hidden from ls r and never served, without losing the recipe like delete r does
+    #[serde(default)]
+    pub archived: bool,
+    //the total time is the sum of the breakdown, time_minutes is only used for recipes without one
+    #[serde(default)]
+    pub prep_minutes: Option<u32>,
+    #[serde(default)]
+    pub cook_minutes: Option<u32>,
+    #[serde(default, alias = "time")]
+    pub time_minutes: Option<u32>,
+    //topics the recipe is published on besides the default one, which carries every public recipe
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
+    //peers the recipe is withheld from even when it is public, never sent to anyone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_peers: Vec<String>,
+    //an http or https link to a picture of the dish, image fetch r keeps a copy of it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+    //unix time the recipe was last added or changed here, stamped when storage is written, recipes from before it was kept have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<u64>,
+}
+
+impl Recipe {
+    //prep plus cook time when either is known, otherwise the single total time if there is one
+    pub fn total_minutes(&self) -> Option<u32> {
+        match (self.prep_minutes, self.cook_minutes) {
+            (None, None) => self.time_minutes,
+            (prep, cook) => Some(prep.unwrap_or(0) + cook.unwrap_or(0)),
+        }
+    }
+
+    //"Prep 15 min - Cook 30 min - Total 45 min" with whichever parts are known
+    pub fn time_breakdown(&self) -> Option<String> {
+        let total = self.total_minutes()?;
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(prep) = self.prep_minutes {
+            parts.push(format!("Prep {} min", prep));
+        }
+        if let Some(cook) = self.cook_minutes {
+            parts.push(format!("Cook {} min", cook));
+        }
+        parts.push(format!("Total {} min", total));
+        Some(parts.join(" - "))
+    }
+
+    //checks a single interest criterion, either "tag:x", "category:x", "dietary:x" or a bare "x" matching any of them
+    pub fn matches_interest(&self, criterion: &str) -> bool {
+        let criterion = criterion.to_lowercase();
+        let has = |values: &[String], wanted: &str| values.iter().any(|v| v.to_lowercase() == wanted);
+        let in_category = |wanted: &str| self.category.as_deref().map(|c| c.to_lowercase() == wanted).unwrap_or(false);
+        match criterion.split_once(':') {
+            Some(("tag", wanted)) => has(&self.tags, wanted),
+            Some(("category", wanted)) => in_category(wanted),
+            Some(("dietary", wanted)) => has(&self.dietary, wanted),
+            _ => has(&self.tags, &criterion) || in_category(&criterion) || has(&self.dietary, &criterion),
+        }
+    }
+
+    //finds the ingredient matching a search term, falling back to the plain string for recipes without structured ingredients
+    pub fn find_ingredient(&self, wanted: &str) -> Option<String> {
+        let wanted = wanted.to_lowercase();
+        if self.structured_ingredients.is_empty() {
+            return self.ingredients.to_lowercase().contains(&wanted).then(|| self.ingredients.clone());
+        }
+        self.structured_ingredients
+            .iter()
+            .find(|i| i.name.to_lowercase().contains(&wanted))
+            .map(|i| i.name.clone())
+    }
+
+    //whether a search term appears in the name, ingredients, instructions, tags or category, ignoring case
+    pub fn matches_text(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [&self.name, &self.ingredients, &self.instructions]
+            .into_iter()
+            .chain(&self.tags)
+            .chain(&self.category)
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+
+    //a substitution whose alternative matches a search term
+    pub fn find_substitute(&self, wanted: &str) -> Option<&Substitution> {
+        let wanted = wanted.to_lowercase();
+        self.substitutions.iter().find(|s| s.alternative.to_lowercase().contains(&wanted))
+    }
+
+    //ingredients as separate lines, a recipe with only the plain string counts as a single line
+    pub fn ingredient_lines(&self) -> Vec<String> {
+        if self.structured_ingredients.is_empty() {
+            vec![self.ingredients.clone()]
+        } else {
+            self.structured_ingredients.iter().map(|i| i.to_string()).collect()
+        }
+    }
+
+    //instruction steps, one per line of the instructions
+    pub fn steps(&self) -> Vec<String> {
+        self.instructions.lines().map(|l| l.trim().to_owned()).filter(|l| !l.is_empty()).collect()
+    }
+
+    //fills in the structured form for recipes from peers that only send the plain strings
+    //legacy peers separated steps with pipes rather than newlines, returns whether anything changed
+    pub fn upgrade_legacy(&mut self) -> bool {
+        let mut upgraded = false;
+        if self.structured_ingredients.is_empty() && !self.ingredients.trim().is_empty() {
+            self.structured_ingredients = ingredients::parse_ingredients(&self.ingredients);
+            upgraded = !self.structured_ingredients.is_empty();
+        }
+        if !self.instructions.contains('\n') && self.instructions.contains('|') {
+            self.instructions = self.instructions.split('|').map(|s| s.trim()).filter(|s| !s.is_empty()).collect::<Vec<_>>().join("\n");
+            upgraded = true;
+        }
+        upgraded
+    }
+
+    //only public recipes that arent archived are handed out to peers
+    pub fn is_served(&self) -> bool {
+        self.public && !self.archived
+    }
+
+    //whether a peer asking for the recipe is handed it, denied peers are refused like for a private recipe
+    pub fn is_served_to(&self, peer: &str) -> bool {
+        self.is_served() && !self.denied_peers.iter().any(|p| p == peer)
+    }
+
+    //an edit of the stored recipe, the fields the pipe format doesnt cover are carried over from it
+    pub fn carrying_over(mut self, stored: &Recipe) -> Recipe {
+        self.id = stored.id;
+        self.public = stored.public;
+        self.servings = stored.servings;
+        self.recipe_yield = stored.recipe_yield.clone();
+        self.archived = stored.archived;
+        self.time_minutes = stored.time_minutes;
+        self.topics = stored.topics.clone();
+        self.denied_peers = stored.denied_peers.clone();
+        self
+    }
+
+    //the recipe as peers get it, without who it is withheld from
+    pub fn shared(mut self) -> Recipe {
+        self.denied_peers.clear();
+        self
+    }
+
+    //whether peers asking on a topic are handed the recipe, given it is served at all
+    pub fn is_on_topic(&self, topic: &str) -> bool {
+        topic == DEFAULT_TOPIC || self.topics.iter().any(|t| t == topic)
+    }
+
+    //a recipe is wanted when it matches every configured interest
+    pub fn matches_interests(&self, interests: &[String]) -> bool {
+        interests.iter().all(|i| self.matches_interest(i))
+    }
+}
+
+//turns pipe separated arguments into an unsaved recipe
+pub fn parse_recipe_args(rest: &str) -> RecipeResult<Recipe> {
+    //splits arguments and stores their references in a array
+    let elements: Vec<&str> = rest.split('|').map(|e| e.trim()).collect();
+    //Uses the len function to check number of args
+    if elements.len() < 3 {
+        return Err(RecipeError::Validation(format!("too few arguments - Format: {}", RECIPE_ARGS_FORMAT)));
+    }
+    Ok(Recipe {
+        id: 0,
+        name: elements[0].to_owned(),
+        ingredients: elements[1].to_owned(),
+        instructions: elements[2].to_owned(),
+        public: false,
+        //optional trailing arguments, tags and dietary are comma separated
+        tags: split_list(elements.get(3).copied().unwrap_or("")),
+        category: elements.get(4).map(|c| c.to_string()).filter(|c| !c.is_empty()),
+        dietary: split_list(elements.get(5).copied().unwrap_or("")),
+        structured_ingredients: ingredients::parse_ingredients(elements[1]),
+        servings: None,
+        recipe_yield: None,
+        //semicolon separated "original -> alternative (notes)"
+        substitutions: ingredients::parse_substitutions(elements.get(6).copied().unwrap_or(""))?,
+        archived: false,
+        prep_minutes: parse_minutes(elements.get(7).copied(), "prep")?,
+        cook_minutes: parse_minutes(elements.get(8).copied(), "cook")?,
+        time_minutes: None,
+        topics: Vec::new(),
+        denied_peers: Vec::new(),
+        image_url: elements.get(9).map(|u| u.to_string()).filter(|u| !u.is_empty()),
+        updated_at: None,
+    })
+}
+
+//parses an optional whole number of minutes, empty means not given
+fn parse_minutes(arg: Option<&str>, what: &str) -> RecipeResult<Option<u32>> {
+    match arg.filter(|a| !a.is_empty()) {
+        Some(minutes) => minutes
+            .parse::<u32>()
+            .map(Some)
+            .map_err(|_| RecipeError::Validation(format!("invalid {} time {:?}, expected whole minutes - Format: {}", what, minutes, RECIPE_ARGS_FORMAT))),
+        None => Ok(None),
+    }
+}
+
+//splits a comma separated argument into trimmed non empty values
+pub fn split_list(arg: &str) -> Vec<String> {
+    arg.split(',').map(|v| v.trim().to_owned()).filter(|v| !v.is_empty()).collect()
+}
+
+//seconds since the unix epoch, what updated_at holds
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
@@ -0,0 +1,101 @@
+use libp2p::PeerId;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+//responses gathered for one broadcast request while its collection window is open
+pub struct PendingRequest {
+    //what was asked for, used in the summary
+    pub description: String,
+    started: Instant,
+    deadline: Instant,
+    //peers known when the request was sent, used to detect missing responses
+    expected: HashSet<PeerId>,
+    //author and id of every recipe received, so repeats are only counted once
+    recipes: HashSet<(PeerId, usize)>,
+    peers: HashSet<PeerId>,
+}
+
+impl PendingRequest {
+    //how many unique recipes came back and from how many peers
+    pub fn summary(&self) -> (usize, usize) {
+        (self.recipes.len(), self.peers.len())
+    }
+
+    //peers that were expected to answer but havent
+    pub fn missing(&self) -> Vec<PeerId> {
+        self.expected.difference(&self.peers).copied().collect()
+    }
+}
+
+//every broadcast request still collecting responses
+#[derive(Default)]
+pub struct PendingRequests {
+    requests: HashMap<Uuid, PendingRequest>,
+}
+
+impl PendingRequests {
+    //starts the collection window for a newly sent request
+    pub fn start(&mut self, id: Uuid, description: String, window: Duration, expected: HashSet<PeerId>) {
+        let now = Instant::now();
+        self.requests.insert(
+            id,
+            PendingRequest {
+                description,
+                started: now,
+                deadline: now + window,
+                expected,
+                recipes: HashSet::new(),
+                peers: HashSet::new(),
+            },
+        );
+    }
+
+    //adds a response to its request, a response without an id is from an older peer and counts for every open request
+    //returns false when no open request wants the response, e.g. because its window already closed
+    pub fn record(&mut self, id: Option<Uuid>, peer: PeerId, recipe_ids: &[usize]) -> bool {
+        let mut recorded = false;
+        for (request_id, request) in self.requests.iter_mut() {
+            if id.map(|id| id == *request_id).unwrap_or(true) {
+                request.peers.insert(peer);
+                request.recipes.extend(recipe_ids.iter().map(|recipe_id| (peer, *recipe_id)));
+                recorded = true;
+            }
+        }
+        recorded
+    }
+
+    //whether every request's window has closed
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    //describes every open request for the requests command
+    pub fn outstanding(&self) -> Vec<String> {
+        self.requests
+            .iter()
+            .map(|(id, r)| {
+                format!(
+                    "{} ({}): {}s elapsed, {} of {} expected peers answered",
+                    r.description,
+                    id,
+                    r.started.elapsed().as_secs(),
+                    r.peers.intersection(&r.expected).count(),
+                    r.expected.len()
+                )
+            })
+            .collect()
+    }
+
+    //removes and returns the requests whose window has closed
+    pub fn take_expired(&mut self) -> Vec<PendingRequest> {
+        let now = Instant::now();
+        let expired: Vec<Uuid> = self
+            .requests
+            .iter()
+            .filter(|(_, r)| r.deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        expired.iter().filter_map(|id| self.requests.remove(id)).collect()
+    }
+}
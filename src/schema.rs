@@ -0,0 +1,144 @@
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+//json schema for a file of recipes as import r --json expects it, ids and visibility are assigned on import so they are optional
+const RECIPES_SCHEMA: &str = r#"{
+    "type": "array",
+    "items": {
+        "type": "object",
+        "required": ["name", "ingredients", "instructions"],
+        "properties": {
+            "id": { "type": "integer", "minimum": 0 },
+            "name": { "type": "string" },
+            "ingredients": { "type": "string" },
+            "instructions": { "type": "string" },
+            "public": { "type": "boolean" },
+            "tags": { "type": "array", "items": { "type": "string" } },
+            "category": { "type": ["string", "null"] },
+            "image_url": { "type": ["string", "null"] },
+            "dietary": { "type": "array", "items": { "type": "string" } },
+            "structured_ingredients": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "quantity": { "type": ["number", "null"] },
+                        "quantity_max": { "type": ["number", "null"] },
+                        "unit": { "type": ["string", "null"] },
+                        "group": { "type": ["string", "null"] }
+                    }
+                }
+            },
+            "servings": { "type": ["integer", "null"], "minimum": 0 },
+            "prep_minutes": { "type": ["integer", "null"], "minimum": 0 },
+            "cook_minutes": { "type": ["integer", "null"], "minimum": 0 },
+            "time_minutes": { "type": ["integer", "null"], "minimum": 0 },
+            "updated_at": { "type": ["integer", "null"], "minimum": 0 },
+            "yield": {
+                "type": ["object", "null"],
+                "required": ["amount", "unit"],
+                "properties": {
+                    "amount": { "type": "number" },
+                    "unit": { "type": "string" }
+                }
+            },
+            "substitutions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["original", "alternative"],
+                    "properties": {
+                        "original": { "type": "string" },
+                        "alternative": { "type": "string" },
+                        "notes": { "type": ["string", "null"] }
+                    }
+                }
+            },
+            "archived": { "type": "boolean" }
+        }
+    }
+}"#;
+
+//parsed once, the schema is part of the binary so it can only fail if it was edited badly
+static SCHEMA: Lazy<Value> = Lazy::new(|| serde_json::from_str(RECIPES_SCHEMA).expect("embedded recipe schema is valid json"));
+
+//checks one record of a file of recipes against the schema, so files can be checked without holding all of them
+//each problem is reported with its path like "recipe[2].servings: expected integer"
+pub fn validate_record(value: &Value, index: usize) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(value, &SCHEMA["items"], &format!("recipe[{}]", index), &mut errors);
+    errors
+}
+
+//the subset of json schema the embedded schema uses: type, required, properties, items and minimum
+fn check(value: &Value, schema: &Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|t| has_type(value, t)) {
+            errors.push(format!("{}: expected {}, found {}", path, types.join(" or "), type_name(value)));
+            return;
+        }
+    }
+    if let Some(minimum) = schema.get("minimum").and_then(|m| m.as_f64()) {
+        if value.as_f64().is_some_and(|v| v < minimum) {
+            errors.push(format!("{}: expected at least {}", path, minimum));
+        }
+    }
+    match value {
+        Value::Object(fields) => {
+            for required in schema.get("required").and_then(|r| r.as_array()).into_iter().flatten() {
+                if let Some(name) = required.as_str().filter(|name| !fields.contains_key(*name)) {
+                    errors.push(format!("{}.{}: missing required field", path, name));
+                }
+            }
+            if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+                for (name, field) in fields {
+                    if let Some(field_schema) = properties.get(name) {
+                        check(field, field_schema, &format!("{}.{}", path, name), errors);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    check(item, item_schema, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+//whether a value is of a json schema type
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.is_u64() || value.is_i64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+//json schema type of a value, used in error messages
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_u64() || n.is_i64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
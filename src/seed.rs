@@ -0,0 +1,88 @@
+use crate::error::{RecipeError, RecipeResult};
+use crate::import::{import_cookbook_text, ImportReport};
+use crate::storage::read_local_recipes;
+
+//tag every example carries so they can be found and removed together
+pub const SAMPLE_TAG: &str = "sample";
+
+//example recipes in the cookbook format import r --cookbook reads, so they go through the same parsing and validation
+const SAMPLES: &str = r#"
+---
+title: Simple White Bread
+tags: [sample, bread, baking]
+category: bread
+dietary: [vegetarian, vegan]
+servings: 8
+prep_minutes: 20
+cook_minutes: 35
+---
+## Ingredients
+- 500 g flour
+- 7 g yeast
+- 10 g salt
+- 320 ml water
+- 1 tbsp olive oil
+
+## Instructions
+1. Mix the flour, yeast and salt in a large bowl
+2. Add the water and oil and knead for 10 minutes until smooth
+3. Leave covered in a warm place for an hour until doubled
+4. Shape into a loaf and put it in a greased tin
+5. Bake at 220C for 30 to 35 minutes until it sounds hollow when tapped
+---
+title: Tomato Soup
+tags: [sample, soup, quick]
+category: soup
+dietary: [vegetarian]
+servings: 4
+prep_minutes: 10
+cook_minutes: 25
+---
+## Ingredients
+- 1 tbsp olive oil
+- 1 onion
+- 2 cloves garlic
+- 800 g tomatoes
+- 500 ml vegetable stock
+- 2 tbsp cream
+
+## Instructions
+1. Soften the chopped onion and garlic in the oil for 5 minutes
+2. Add the tomatoes and stock and simmer for 20 minutes
+3. Blend until smooth and stir in the cream
+4. Season with salt and pepper to taste
+---
+title: Fluffy Pancakes
+tags: [sample, breakfast, sweet]
+category: breakfast
+dietary: [vegetarian]
+servings: 4
+prep_minutes: 10
+cook_minutes: 15
+---
+## Ingredients
+- 200 g flour
+- 2 tsp baking powder
+- 1 tbsp sugar
+- 2 eggs
+- 250 ml milk
+- 25 g butter
+
+## Instructions
+1. Whisk the flour, baking powder and sugar together
+2. Beat in the eggs, milk and melted butter until just combined
+3. Cook ladlefuls in a hot buttered pan for 2 minutes each side
+4. Serve warm with fruit or honey
+"#;
+
+//adds the example recipes, refused once examples are already in the collection so running it twice doesnt duplicate them
+pub async fn seed() -> RecipeResult<ImportReport> {
+    let has_samples = read_local_recipes().await?.iter().any(|r| r.tags.iter().any(|t| t.eq_ignore_ascii_case(SAMPLE_TAG)));
+    if has_samples {
+        return Err(RecipeError::Validation(format!(
+            "the examples are already in this collection, delete r --tag {} removes them",
+            SAMPLE_TAG
+        )));
+    }
+    import_cookbook_text(SAMPLES).await
+}
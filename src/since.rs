@@ -0,0 +1,106 @@
+use crate::error::{RecipeError, RecipeResult};
+
+const FORMAT: &str = "Format: ls r --since <duration like 30m, 2h or 7d | date like 2024-05-01 or 2024-05-01T18:30[:00][Z|+02:00]>";
+
+//the unix time a --since argument points at, a duration counts back from now and a date without an offset is utc
+pub fn parse(arg: &str, now: u64) -> RecipeResult<u64> {
+    let arg = arg.trim();
+    let invalid = || RecipeError::Validation(format!("cant read {:?} as a duration or a date - {}", arg, FORMAT));
+    if arg.is_empty() {
+        return Err(RecipeError::Validation(format!("missing duration or date - {}", FORMAT)));
+    }
+    if let Some(secs) = duration_secs(arg) {
+        return Ok(now.saturating_sub(secs));
+    }
+    timestamp(arg).ok_or_else(invalid)
+}
+
+//a whole number followed by s, m, h, d or w
+fn duration_secs(arg: &str) -> Option<u64> {
+    let unit = arg.chars().last()?;
+    let secs_per_unit = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let count = &arg[..arg.len() - 1];
+    if count.is_empty() || !count.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    count.parse::<u64>().ok()?.checked_mul(secs_per_unit)
+}
+
+//an iso 8601 date, optionally with a time of day and then z or a utc offset
+fn timestamp(arg: &str) -> Option<u64> {
+    let (date, time) = match arg.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (arg, None),
+    };
+    let mut parts = date.split('-');
+    let year: i64 = number(parts.next()?, 4)?;
+    let month: u32 = number(parts.next()?, 2)?;
+    let day: u32 = number(parts.next()?, 2)?;
+    if parts.next().is_some() || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let (secs_of_day, offset) = match time {
+        Some(time) => time_of_day(time)?,
+        None => (0, 0),
+    };
+    let secs = days_from_civil(year, month, day) * 86400 + secs_of_day - offset;
+    u64::try_from(secs).ok()
+}
+
+//hh:mm or hh:mm:ss, then z, nothing or an offset like +02:00, as seconds into the day and seconds ahead of utc
+fn time_of_day(time: &str) -> Option<(i64, i64)> {
+    let (clock, offset) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else if let Some(at) = time.rfind(['+', '-']) {
+        let (clock, offset) = time.split_at(at);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = offset[1..].split_once(':')?;
+        let (hours, minutes): (i64, i64) = (number(hours, 2)?, number(minutes, 2)?);
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        (clock, sign * (hours * 3600 + minutes * 60))
+    } else {
+        (time, 0)
+    };
+    let mut parts = clock.split(':');
+    let hours: i64 = number(parts.next()?, 2)?;
+    let minutes: i64 = number(parts.next()?, 2)?;
+    let seconds: i64 = parts.next().map(|s| number(s, 2)).unwrap_or(Some(0))?;
+    if parts.next().is_some() || hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    Some((hours * 3600 + minutes * 60 + seconds, offset))
+}
+
+//exactly digits digits, so 2024-5-1 and 2024-05-001 are refused rather than guessed at
+fn number<T: std::str::FromStr>(part: &str, digits: usize) -> Option<T> {
+    (part.len() == digits && part.bytes().all(|b| b.is_ascii_digit())).then(|| part.parse().ok()).flatten()
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+//days since 1970-01-01, howard hinnant's days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
@@ -0,0 +1,531 @@
+use crate::config;
+use crate::error::{RecipeError, RecipeResult};
+use crate::migrate::{self, StorageFile, SCHEMA_VERSION};
+use crate::validation;
+use crate::{Recipe, Recipes};
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::fs;
+
+//last parsed copy of the storage file so unchanged data isn't parsed again on every read
+static CACHE: Lazy<Mutex<Option<CachedRecipes>>> = Lazy::new(|| Mutex::new(None));
+
+//recipes written while autosave is on, or while the storage isnt writable, that haven't reached the disk yet
+static PENDING: Lazy<Mutex<Option<PendingWrite>>> = Lazy::new(|| Mutex::new(None));
+
+//a buffered write, kept until the debounce interval passes without another one
+//it is tied to the collection rather than a path so a storage_file changed in the meantime gets the write
+struct PendingWrite {
+    collection: String,
+    recipes: Arc<Recipes>,
+    first_change: Instant,
+    last_change: Instant,
+}
+
+//name of the collection stored in storage_file itself
+pub const DEFAULT_COLLECTION: &str = "default";
+
+//collection every read and write goes to, picked with --collection or collection switch
+static ACTIVE_COLLECTION: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(DEFAULT_COLLECTION.to_owned()));
+
+//a steady stream of writes is still flushed after this many debounce intervals so little is lost on a crash
+const MAX_DEBOUNCE_INTERVALS: u32 = 5;
+
+//orders recipes can be written and listed in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    //as they were added, the order before storage_sort existed
+    Insertion,
+    Id,
+    //case insensitive, recipes with the same name go by id
+    Name,
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = RecipeError;
+
+    fn from_str(s: &str) -> RecipeResult<Self> {
+        match s.to_lowercase().as_str() {
+            "insertion" => Ok(SortOrder::Insertion),
+            "id" => Ok(SortOrder::Id),
+            "name" => Ok(SortOrder::Name),
+            "updated_at" => Err(RecipeError::Validation("storage cant be sorted by updated_at, it would be reordered on every change, use ls r --since to find recent changes".to_owned())),
+            other => Err(RecipeError::Validation(format!("unknown storage_sort {:?}, expected insertion, id or name", other))),
+        }
+    }
+}
+
+//the configured storage_sort, an invalid one keeps insertion order rather than failing every write
+pub fn sort_order() -> SortOrder {
+    match config::get().map(|c| c.storage_sort.clone()).and_then(|s| s.parse()) {
+        Ok(order) => order,
+        Err(e) => {
+            error!("{}, keeping insertion order", e);
+            SortOrder::Insertion
+        }
+    }
+}
+
+//sorts recipes in place, the sort is stable so insertion order leaves them as they are
+pub fn sort<T: std::borrow::Borrow<Recipe>>(recipes: &mut [T], order: SortOrder) {
+    match order {
+        SortOrder::Insertion => {}
+        SortOrder::Id => recipes.sort_by_key(|r| r.borrow().id),
+        SortOrder::Name => recipes.sort_by(|a, b| {
+            let (a, b) = (a.borrow(), b.borrow());
+            a.name.to_lowercase().cmp(&b.name.to_lowercase()).then(a.id.cmp(&b.id))
+        }),
+    }
+}
+
+//parsed recipes along with the file state they were parsed from
+struct CachedRecipes {
+    path: String,
+    modified: SystemTime,
+    len: u64,
+    //schema version of the file on disk, older ones are migrated in memory and written at the current one on the next write
+    version: u32,
+    //shared with every reader so a cache hit doesnt copy the recipes
+    recipes: Arc<Recipes>,
+}
+
+//returns the cached recipes if the file hasn't changed since they were parsed
+fn cached(path: &str, modified: SystemTime, len: u64) -> Option<Arc<Recipes>> {
+    let cache = CACHE.lock().ok()?;
+    cache
+        .as_ref()
+        .filter(|c| c.path == path && c.modified == modified && c.len == len)
+        .map(|c| c.recipes.clone())
+}
+
+//remembers freshly parsed or written recipes, a poisoned lock just means the next read parses again
+fn store(path: &str, modified: SystemTime, len: u64, version: u32, recipes: Arc<Recipes>) {
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = Some(CachedRecipes { path: path.to_owned(), modified, len, version, recipes });
+    }
+}
+
+//the schema version of the file at path when it was last read at an older one and hasnt changed since
+fn outdated_version(path: &str, modified: SystemTime, len: u64) -> Option<u32> {
+    let cache = CACHE.lock().ok()?;
+    cache
+        .as_ref()
+        .filter(|c| c.path == path && c.modified == modified && c.len == len && c.version < SCHEMA_VERSION)
+        .map(|c| c.version)
+}
+
+//locks the pending write buffer
+fn pending() -> RecipeResult<std::sync::MutexGuard<'static, Option<PendingWrite>>> {
+    PENDING.lock().map_err(|e| RecipeError::Lock(e.to_string()))
+}
+
+//file path for recipes in the active collection, the default one is the storage_file config
+pub fn storage_path() -> RecipeResult<String> {
+    Ok(collection_path(&active_collection()?, &config::get()?.storage_file))
+}
+
+//every other collection lives next to storage_file, e.g. ./recipes.desserts.json
+fn collection_path(name: &str, storage_file: &str) -> String {
+    if name == DEFAULT_COLLECTION {
+        return storage_file.to_owned();
+    }
+    match storage_file.strip_suffix(".json") {
+        Some(stem) => format!("{}.{}.json", stem, name),
+        None => format!("{}.{}", storage_file, name),
+    }
+}
+
+//the collection currently in use
+pub fn active_collection() -> RecipeResult<String> {
+    ACTIVE_COLLECTION
+        .read()
+        .map(|c| c.clone())
+        .map_err(|e| RecipeError::Lock(e.to_string()))
+}
+
+//the default collection followed by every created one
+pub fn collections() -> RecipeResult<Vec<String>> {
+    let mut names = vec![DEFAULT_COLLECTION.to_owned()];
+    names.extend(config::get()?.collections.clone());
+    Ok(names)
+}
+
+//registers a new empty collection, names end up in file names so they are kept to letters, digits, - and _
+pub async fn create_collection(name: &str) -> RecipeResult<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(RecipeError::Validation(format!(
+            "invalid collection name {:?}, use letters, digits, - and _",
+            name
+        )));
+    }
+    if collections()?.iter().any(|c| c == name) {
+        return Err(RecipeError::Validation(format!("collection {} already exists", name)));
+    }
+    let path = collection_path(name, &config::get()?.storage_file);
+    //a file left over from an earlier collection of the same name is picked up as is
+    if fs::metadata(&path).await.is_err() {
+        write_atomic(&path, &Vec::new()).await?;
+    }
+    config::update(|c| c.collections.push(name.to_owned())).await?;
+    Ok(())
+}
+
+//makes another existing collection the active one, anything buffered for the old one is written first
+pub async fn switch_collection(name: &str) -> RecipeResult<()> {
+    if !collections()?.iter().any(|c| c == name) {
+        return Err(RecipeError::Validation(format!(
+            "no collection named {}, create it with: collection create {}",
+            name, name
+        )));
+    }
+    flush().await?;
+    *ACTIVE_COLLECTION.write().map_err(|e| RecipeError::Lock(e.to_string()))? = name.to_owned();
+    Ok(())
+}
+
+//logic for reading local recipes, a copy to change and write back
+pub async fn read_local_recipes()-> RecipeResult<Recipes>{
+    Ok(Recipes::clone(&*read_shared_recipes().await?))
+}
+//the local recipes shared with the cache, for reading them without copying every recipe
+pub async fn read_shared_recipes() -> RecipeResult<Arc<Recipes>> {
+    let collection = active_collection()?;
+    //buffered writes are newer than whatever is on disk
+    if let Some(p) = pending()?.as_ref().filter(|p| p.collection == collection) {
+        return Ok(p.recipes.clone());
+    }
+    let path = collection_path(&collection, &config::get()?.storage_file);
+    //checking the modification time and size is cheap and catches edits made by another process
+    let metadata = fs::metadata(&path).await?;
+    let modified = metadata.modified()?;
+    if let Some(recipes) = cached(&path, modified, metadata.len()) {
+        return Ok(recipes);
+    }
+    //reads content from storage
+    let content = fs::read(&path).await?;
+    //deserialized result, brought up to the current schema version in memory, reading never writes the file
+    let (version, result) = migrate::parse(&content)?;
+    if version < SCHEMA_VERSION {
+        info!("Read {} at schema version {}, it is written at version {} on the next change", path, version, SCHEMA_VERSION);
+    }
+    let result = Arc::new(result);
+    store(&path, modified, content.len() as u64, version, result.clone());
+    Ok(result)
+}
+//logic for writing local recipes, buffered when STORAGE_AUTOSAVE_MS is set and written straight away otherwise
+pub async fn write_local_recipes(recipes: &Recipes)->RecipeResult<()>{
+    let recipes = &stamp_changes(recipes).await;
+    if config::get()?.storage_autosave_ms == 0 {
+        return write_or_keep(recipes).await;
+    }
+    let collection = active_collection()?;
+    let now = Instant::now();
+    let mut pending = pending()?;
+    let first_change = pending.as_ref().filter(|p| p.collection == collection).map(|p| p.first_change).unwrap_or(now);
+    *pending = Some(PendingWrite { collection, recipes: Arc::new(recipes.clone()), first_change, last_change: now });
+    Ok(())
+}
+//sets updated_at on recipes that are new or differ from the stored recipe with the same id, unchanged ones keep theirs
+//one already later than the stored recipe's, like a newer copy merged in from another file, is kept as well
+//nothing stored, or storage that cant be read, makes every recipe new
+async fn stamp_changes(recipes: &Recipes) -> Recipes {
+    let stored = read_shared_recipes().await.unwrap_or_default();
+    let stored: HashMap<usize, &Recipe> = stored.iter().map(|r| (r.id, r)).collect();
+    let now = crate::recipe::unix_now();
+    recipes
+        .iter()
+        .cloned()
+        .map(|mut recipe| {
+            recipe.updated_at = match stored.get(&recipe.id) {
+                Some(old) if same_content(old, &recipe) => old.updated_at,
+                Some(old) if recipe.updated_at > old.updated_at => recipe.updated_at,
+                _ => Some(now),
+            };
+            recipe
+        })
+        .collect()
+}
+
+//whether two recipes differ in anything but when they were updated
+fn same_content(a: &Recipe, b: &Recipe) -> bool {
+    let b = Recipe { updated_at: a.updated_at, ..b.clone() };
+    serde_json::to_vec(a).ok() == serde_json::to_vec(&b).ok()
+}
+
+//writes straight away, if the storage isn't writable the recipes are kept in memory so reads still see them and save can retry
+async fn write_or_keep(recipes: &Recipes) -> RecipeResult<()> {
+    let collection = active_collection()?;
+    let path = collection_path(&collection, &config::get()?.storage_file);
+    match write_atomic(&path, recipes).await {
+        Err(e @ RecipeError::Storage { .. }) => {
+            let now = Instant::now();
+            *pending()? = Some(PendingWrite { collection, recipes: Arc::new(recipes.clone()), first_change: now, last_change: now });
+            Err(e)
+        }
+        result => result,
+    }
+}
+//permission problems and a storage path under something that isnt a directory get their own error
+//so callers can tell them apart from other io failures, either way the fix is another storage_file
+fn storage_error(path: &str, e: std::io::Error) -> RecipeError {
+    match e.kind() {
+        std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem | std::io::ErrorKind::NotADirectory => RecipeError::Storage {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        },
+        _ => e.into(),
+    }
+}
+//writes to a temporary file and renames it over the storage file so a crash never leaves half a file behind
+async fn write_atomic(path: &str, recipes: &Recipes) -> RecipeResult<()> {
+    backup_before_migrating(path).await?;
+    let json = storage_json(recipes, sort_order())?;
+    let temp = format!("{}.tmp", path);
+    fs::write(&temp, &json).await.map_err(|e| storage_error(path, e))?;
+    fs::rename(&temp, path).await.map_err(|e| storage_error(path, e))?;
+    //the written data becomes the cached copy so the next read doesn't parse it back
+    let metadata = fs::metadata(path).await?;
+    store(path, metadata.modified()?, metadata.len(), SCHEMA_VERSION, Arc::new(recipes.clone()));
+    Ok(())
+}
+//keeps a file read at an older schema version as it was before the first write replaces it
+//in case the migration got something wrong, the write doesnt go ahead without the backup
+async fn backup_before_migrating(path: &str) -> RecipeResult<()> {
+    let metadata = match fs::metadata(path).await {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()),
+    };
+    let version = match outdated_version(path, metadata.modified()?, metadata.len()) {
+        Some(version) => version,
+        None => return Ok(()),
+    };
+    let backup = format!("{}.v{}.bak", path, version);
+    fs::copy(path, &backup).await.map_err(|e| storage_error(&backup, e))?;
+    info!("Migrated {} from schema version {} to {}, the original is backed up to {}", path, version, SCHEMA_VERSION, backup);
+    Ok(())
+}
+//the storage file content for the recipes, a sorted file comes out the same however they were ordered in memory
+fn storage_json(recipes: &Recipes, order: SortOrder) -> RecipeResult<String> {
+    let mut sorted;
+    let recipes = match order {
+        SortOrder::Insertion => recipes,
+        order => {
+            sorted = recipes.clone();
+            sort(&mut sorted, order);
+            &sorted
+        }
+    };
+    //Converts json to plain text
+    Ok(serde_json::to_string(&StorageFile { schema_version: SCHEMA_VERSION, recipes })?)
+}
+//writes out buffered recipes, returns whether there was anything to write
+pub async fn flush() -> RecipeResult<bool> {
+    let write = pending()?.take();
+    let write = match write {
+        Some(write) => write,
+        None => return Ok(false),
+    };
+    let path = collection_path(&write.collection, &config::get()?.storage_file);
+    if let Err(e) = write_atomic(&path, &write.recipes).await {
+        //put it back for the next attempt unless something newer was buffered in the meantime
+        let mut pending = pending()?;
+        if pending.is_none() {
+            *pending = Some(write);
+        }
+        return Err(e);
+    }
+    Ok(true)
+}
+//flushes once writes have stopped for the debounce interval, or have kept coming for too long
+pub async fn flush_if_due() -> RecipeResult<bool> {
+    let delay = Duration::from_millis(config::get()?.storage_autosave_ms);
+    //without autosave only writes the storage refused are pending, they wait for save instead of failing every tick
+    if delay.is_zero() {
+        return Ok(false);
+    }
+    let due = pending()?
+        .as_ref()
+        .map(|p| p.last_change.elapsed() >= delay || p.first_change.elapsed() >= delay * MAX_DEBOUNCE_INTERVALS)
+        .unwrap_or(false);
+    if due {
+        flush().await
+    } else {
+        Ok(false)
+    }
+}
+//checks every local recipe and prints a pass or fail line for each, returns whether they all passed
+//with fix the trivially repairable problems are repaired and written back before checking
+pub async fn validate_all(fix: bool) -> RecipeResult<bool> {
+    let max_bytes = config::get()?.max_recipe_bytes;
+    let mut recipes = read_local_recipes().await?;
+    let mut fixed = 0;
+    if fix {
+        fixed = recipes.iter_mut().map(validation::tidy).filter(|changed| *changed).count();
+        if fixed > 0 {
+            write_local_recipes(&recipes).await?;
+            flush().await?;
+        }
+    }
+    let mut failed = 0;
+    for recipe in &recipes {
+        let problems = validation::problems(recipe, max_bytes);
+        if problems.is_empty() {
+            info!("[pass] {} [{}]", recipe.name, recipe.id);
+        } else {
+            failed += 1;
+            error!("[fail] {} [{}]: {}", recipe.name, recipe.id, problems.join(", "));
+        }
+    }
+    if fix {
+        info!("Fixed {} recipes", fixed);
+    }
+    if failed == 0 {
+        info!("All {} recipes passed", recipes.len());
+    } else {
+        let hint = if fix { "" } else { ", whitespace and empty entries can be repaired with --fix" };
+        error!("{} of {} recipes failed{}", failed, recipes.len(), hint);
+    }
+    Ok(failed == 0)
+}
+
+//reads and validates the storage file once at startup so corruption shows up early instead of deep in a handler
+//a storage that cant be written is reported as RecipeError::Storage, recipes can still be read and changes stay in memory
+pub async fn self_check(repair: bool) -> RecipeResult<()> {
+    let path = storage_path()?;
+    let content = match fs::read(&path).await {
+        Ok(content) => content,
+        //no file yet just means no recipes yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return write_or_keep(&Vec::new()).await,
+        Err(e) => return Err(e.into()),
+    };
+    //an empty file is treated the same as an empty collection
+    if content.iter().all(|b| b.is_ascii_whitespace()) {
+        return write_or_keep(&Vec::new()).await;
+    }
+    let problem = match find_corruption(&content) {
+        Some(problem) => problem,
+        None => return check_writable(&path).await,
+    };
+    if !repair {
+        return Err(RecipeError::Validation(format!(
+            "storage file {} is corrupt: {}. Fix it by hand or restart with --repair to back it up and start fresh",
+            path, problem
+        )));
+    }
+    let backup = format!("{}.bak", path);
+    fs::write(&backup, &content).await?;
+    write_atomic(&path, &Vec::new()).await?;
+    warn!("storage file {} was corrupt ({}), backed it up to {} and started fresh", path, problem, backup);
+    Ok(())
+}
+
+//writes go through a temporary file next to the storage file, so that is what has to be creatable
+async fn check_writable(path: &str) -> RecipeResult<()> {
+    let probe = format!("{}.tmp", path);
+    fs::write(&probe, b"").await.map_err(|e| storage_error(path, e))?;
+    fs::remove_file(&probe).await.map_err(|e| storage_error(path, e))?;
+    Ok(())
+}
+
+//describes where the storage content fails to parse, None if it is valid
+pub fn find_corruption(content: &[u8]) -> Option<String> {
+    //not even valid json, serde knows the line and column
+    let value = match serde_json::from_slice(content) {
+        Ok(value) => value,
+        Err(e) => return Some(format!("invalid json at line {} column {}: {}", e.line(), e.column(), e)),
+    };
+    let values = match migrate::records(value) {
+        Ok((version, _)) if version > SCHEMA_VERSION => {
+            return Some(format!("schema version {} is newer than this version reads ({})", version, SCHEMA_VERSION))
+        }
+        Ok((_, values)) => values,
+        Err(e) => return Some(e.to_string()),
+    };
+    //valid json but one of the records isn't a recipe
+    values.into_iter().enumerate().find_map(|(index, value)| {
+        serde_json::from_value::<Recipe>(value)
+            .err()
+            .map(|e| format!("record {} is not a valid recipe: {}", index, e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a fresh directory under the system temp dir, removed again by the caller
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("p2precipe-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn recipes(names: &[(usize, &str)]) -> Recipes {
+        names
+            .iter()
+            .map(|(id, name)| {
+                let mut recipe = crate::recipe::parse_recipe_args(&format!("{}|flour|bake", name)).unwrap();
+                recipe.id = *id;
+                recipe
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorted_writes_are_byte_identical_whatever_the_input_order() {
+        let one = recipes(&[(2, "soup"), (0, "Bread"), (1, "apple pie"), (3, "bread")]);
+        let other = recipes(&[(3, "bread"), (1, "apple pie"), (2, "soup"), (0, "Bread")]);
+        for order in [SortOrder::Id, SortOrder::Name] {
+            assert_eq!(storage_json(&one, order).unwrap(), storage_json(&other, order).unwrap(), "{:?}", order);
+        }
+        assert_ne!(storage_json(&one, SortOrder::Insertion).unwrap(), storage_json(&other, SortOrder::Insertion).unwrap());
+    }
+
+    #[test]
+    fn names_sort_ignoring_case_and_ties_go_by_id() {
+        let mut sorted = recipes(&[(3, "bread"), (2, "soup"), (0, "Bread"), (1, "apple pie")]);
+        sort(&mut sorted, SortOrder::Name);
+        assert_eq!(sorted.iter().map(|r| r.id).collect::<Vec<_>>(), [1, 0, 3, 2]);
+        assert!("updated_at".parse::<SortOrder>().is_err());
+    }
+
+    #[test]
+    fn permission_problems_are_storage_errors() {
+        let denied = storage_error("recipes.json", std::io::ErrorKind::PermissionDenied.into());
+        assert!(matches!(denied, RecipeError::Storage { ref path, .. } if path == "recipes.json"));
+        let missing = storage_error("recipes.json", std::io::ErrorKind::NotFound.into());
+        assert!(matches!(missing, RecipeError::Io(_)));
+    }
+
+    #[tokio::test]
+    async fn a_storage_path_under_a_file_is_reported_with_its_path() {
+        let dir = temp_dir("under-a-file");
+        let file = dir.join("not-a-directory");
+        std::fs::write(&file, b"").unwrap();
+        let path = file.join("recipes.json").to_string_lossy().into_owned();
+        let checked = check_writable(&path).await;
+        let written = write_atomic(&path, &Vec::new()).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(checked, Err(RecipeError::Storage { path: ref p, .. }) if *p == path), "{:?}", checked);
+        assert!(matches!(written, Err(RecipeError::Storage { path: ref p, .. }) if *p == path), "{:?}", written);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[ignore = "root writes through missing permissions, run it as a regular user with cargo test -- --ignored"]
+    async fn a_read_only_directory_is_reported_with_its_path() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = temp_dir("read-only");
+        let path = dir.join("recipes.json").to_string_lossy().into_owned();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+        let checked = check_writable(&path).await;
+        let written = write_atomic(&path, &Vec::new()).await;
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(checked, Err(RecipeError::Storage { path: ref p, .. }) if *p == path), "{:?}", checked);
+        assert!(matches!(written, Err(RecipeError::Storage { path: ref p, .. }) if *p == path), "{:?}", written);
+    }
+}
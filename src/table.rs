@@ -0,0 +1,80 @@
+use std::io::IsTerminal;
+use std::process::{Command, Stdio};
+
+//width tables are fitted to when output isnt a terminal, so piped output is the same everywhere
+const FIXED_WIDTH: usize = 100;
+
+//every line is logged behind a timestamp, level and target about this wide
+const LOG_PREFIX_WIDTH: usize = 45;
+
+//columns are never shrunk below this, short headers included, so a cut cell keeps a character before its ellipsis
+const MIN_COLUMN_WIDTH: usize = 4;
+
+//ascii like the rest of the output, see logging
+const ELLIPSIS: &str = "...";
+
+//spaces between columns
+const GAP: usize = 2;
+
+//columns left for a table on the terminal logs are written to, COLUMNS wins when it is set, otherwise stty is asked
+//anything that isnt a terminal gets the fixed width
+pub fn output_width() -> usize {
+    match terminal_columns() {
+        Some(columns) => columns.saturating_sub(LOG_PREFIX_WIDTH),
+        None => FIXED_WIDTH,
+    }
+}
+
+fn terminal_columns() -> Option<usize> {
+    if !std::io::stderr().is_terminal() {
+        return None;
+    }
+    if let Some(columns) = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).filter(|c| *c > 0) {
+        return Some(columns);
+    }
+    //stty reports "rows columns" for the terminal on its stdin
+    let tty = std::fs::File::open("/dev/tty").ok()?;
+    Command::new("stty")
+        .arg("size")
+        .stdin(tty)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .and_then(|size| size.split_whitespace().nth(1)?.parse().ok())
+        .filter(|c| *c > 0)
+}
+
+//lays rows out in aligned columns no wider than width in total, the widest columns give way first and cut cells end in an ellipsis
+pub fn render(headers: &[&str], rows: &[Vec<String>], width: usize) -> Vec<String> {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(c, h)| rows.iter().map(|r| r[c].chars().count()).chain([h.chars().count()]).max().unwrap_or(0))
+        .collect();
+    let gaps = GAP * headers.len().saturating_sub(1);
+    while widths.iter().sum::<usize>() + gaps > width {
+        match widths.iter_mut().filter(|w| **w > MIN_COLUMN_WIDTH).max_by_key(|w| **w) {
+            Some(widest) => *widest -= 1,
+            //every column is as narrow as it goes, the line is left to wrap
+            None => break,
+        }
+    }
+    let header: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    [header].iter().chain(rows).map(|row| line(row, &widths)).collect()
+}
+
+fn line(row: &[String], widths: &[usize]) -> String {
+    let cells: Vec<String> = row.iter().zip(widths).map(|(cell, w)| format!("{:<w$}", fit(cell, *w), w = w)).collect();
+    cells.join(&" ".repeat(GAP)).trim_end().to_owned()
+}
+
+//the cell cut to the width, ending in an ellipsis when anything was cut
+fn fit(cell: &str, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell.to_owned();
+    }
+    let mut cut: String = cell.chars().take(width.saturating_sub(ELLIPSIS.len())).collect();
+    cut.push_str(ELLIPSIS);
+    cut
+}
@@ -0,0 +1,55 @@
+use crate::config;
+use crate::error::{RecipeError, RecipeResult};
+use crate::storage;
+use crate::Recipe;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+//destructive changes made this session, oldest first, nothing survives a restart
+static HISTORY: Lazy<Mutex<VecDeque<Change>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+//what was done to a recipe
+#[derive(Debug, Clone, Copy)]
+pub enum ChangeKind {
+    Deleted,
+    Edited,
+}
+
+//a change undo can reverse, with the recipe as it was before and the collection it was in
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub collection: String,
+    pub before: Recipe,
+}
+
+fn history() -> RecipeResult<std::sync::MutexGuard<'static, VecDeque<Change>>> {
+    HISTORY.lock().map_err(|e| RecipeError::Lock(e.to_string()))
+}
+
+//remembers the state a recipe had before a delete or an edit in the active collection
+//only the last undo_depth changes are kept, 0 keeps none
+pub fn record(kind: ChangeKind, before: Recipe) -> RecipeResult<()> {
+    let depth = config::get()?.undo_depth;
+    let collection = storage::active_collection()?;
+    let mut history = history()?;
+    history.push_back(Change { kind, collection, before });
+    while history.len() > depth {
+        history.pop_front();
+    }
+    Ok(())
+}
+
+//takes the most recent change so it can be reversed, it stays put if it was made in another collection
+pub fn take_last() -> RecipeResult<Option<Change>> {
+    let active = storage::active_collection()?;
+    let mut history = history()?;
+    match history.back() {
+        Some(change) if change.collection != active => Err(RecipeError::Validation(format!(
+            "the last change was made in collection {}, switch to it first with: collection switch {}",
+            change.collection, change.collection
+        ))),
+        _ => Ok(history.pop_back()),
+    }
+}
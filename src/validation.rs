@@ -0,0 +1,176 @@
+use crate::config;
+use crate::error::{RecipeError, RecipeResult};
+use crate::Recipe;
+
+//checks a recipe is fit to be stored and shared, used by every path that writes a recipe
+pub fn validate_recipe(recipe: &Recipe) -> RecipeResult<()> {
+    validate_recipe_within(recipe, config::get()?.max_recipe_bytes)
+}
+
+//validate_recipe against a size limit already read from the config, for checking many recipes without reading it for each
+pub fn validate_recipe_within(recipe: &Recipe, max_bytes: usize) -> RecipeResult<()> {
+    //a recipe without a name cant be found or shared
+    if recipe.name.trim().is_empty() {
+        return Err(RecipeError::Validation("recipe name cannot be empty".to_owned()));
+    }
+    if let Some(url) = &recipe.image_url {
+        check_image_url(url)?;
+    }
+    check_recipe_size(recipe, max_bytes)
+}
+
+//rejects recipes whose serialized form is bigger than allowed so broadcasts stay bounded
+pub fn check_recipe_size(recipe: &Recipe, max_bytes: usize) -> RecipeResult<()> {
+    let size = serde_json::to_vec(recipe)?.len();
+    if size > max_bytes {
+        return Err(RecipeError::Validation(format!(
+            "recipe {:?} is {} bytes, the maximum allowed is {} bytes",
+            recipe.name, size, max_bytes
+        )));
+    }
+    Ok(())
+}
+
+//longest image url accepted, anything longer is almost certainly pasted by mistake
+const MAX_IMAGE_URL_LEN: usize = 2048;
+
+//an image url has to be an absolute http or https url with a host and without whitespace
+pub fn check_image_url(url: &str) -> RecipeResult<()> {
+    let invalid = |why: &str| Err(RecipeError::Validation(format!("invalid image url {:?}, {}", url, why)));
+    let rest = match url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) {
+        Some(rest) => rest,
+        None => return invalid("it has to start with http:// or https://"),
+    };
+    if url.len() > MAX_IMAGE_URL_LEN {
+        return invalid(&format!("it is longer than {} characters", MAX_IMAGE_URL_LEN));
+    }
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return invalid("it has whitespace in it");
+    }
+    //the host runs up to the path, query or fragment, a user or port before it doesnt count
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    //ipv6 hosts are in brackets since they have colons of their own
+    let (host, port) = match host.strip_prefix('[') {
+        Some(bracketed) => match bracketed.split_once(']') {
+            Some((host, "")) => (host, None),
+            Some((host, after)) => (host, Some(after.strip_prefix(':').unwrap_or(after))),
+            None => return invalid("its ipv6 host isnt closed with ]"),
+        },
+        None => match host.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host, None),
+        },
+    };
+    if port.is_some_and(|p| p.parse::<u16>().is_err()) {
+        return invalid("its port isnt a number");
+    }
+    if host.is_empty() {
+        return invalid("it has no host");
+    }
+    Ok(())
+}
+
+//every problem with a stored recipe rather than only the first, for auditing the whole collection
+pub fn problems(recipe: &Recipe, max_bytes: usize) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (field, value) in [("name", &recipe.name), ("ingredients", &recipe.ingredients), ("instructions", &recipe.instructions)] {
+        if value.trim().is_empty() {
+            problems.push(format!("{} is empty", field));
+        } else if value.trim() != value {
+            problems.push(format!("{} has whitespace around it", field));
+        }
+    }
+    for (field, values) in [("tags", &recipe.tags), ("dietary", &recipe.dietary)] {
+        if values.iter().any(|v| v.trim().is_empty()) {
+            problems.push(format!("{} has an empty entry", field));
+        }
+        if values.iter().any(|v| !v.trim().is_empty() && v.trim() != v) {
+            problems.push(format!("{} has an entry with whitespace around it", field));
+        }
+    }
+    if recipe.category.as_deref().is_some_and(|c| c.trim().is_empty() || c.trim() != c) {
+        problems.push("category is empty or has whitespace around it".to_owned());
+    }
+    if recipe.structured_ingredients.iter().any(|i| i.name.trim().is_empty()) {
+        problems.push("a structured ingredient has no name".to_owned());
+    }
+    if recipe.structured_ingredients.iter().any(|i| i.quantity.is_some_and(|q| !q.is_finite() || q < 0.0)) {
+        problems.push("a structured ingredient has a negative or invalid quantity".to_owned());
+    }
+    if recipe.structured_ingredients.iter().any(|i| i.quantity_max.is_some_and(|max| !max.is_finite() || i.quantity.is_none_or(|q| max < q))) {
+        problems.push("a structured ingredient's range ends below where it starts".to_owned());
+    }
+    if recipe.servings == Some(0) {
+        problems.push("servings is 0".to_owned());
+    }
+    if recipe.recipe_yield.as_ref().is_some_and(|y| !y.amount.is_finite() || y.amount <= 0.0 || y.unit.trim().is_empty()) {
+        problems.push("yield needs an amount above 0 and a unit".to_owned());
+    }
+    if recipe.substitutions.iter().any(|s| s.original.trim().is_empty() || s.alternative.trim().is_empty()) {
+        problems.push("a substitution is missing its original or alternative".to_owned());
+    }
+    if let Some(Err(e)) = recipe.image_url.as_deref().map(check_image_url) {
+        problems.push(e.to_string());
+    }
+    if let Err(e) = check_recipe_size(recipe, max_bytes) {
+        problems.push(e.to_string());
+    }
+    problems
+}
+
+//turns one record into an unsaved recipe, ids are assigned fresh and imports start out private, same as cookbooks
+pub fn parse_record(record: &[u8], max_bytes: usize) -> RecipeResult<Recipe> {
+    let mut value = serde_json::from_slice::<serde_json::Value>(record)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.insert("id".to_owned(), 0.into());
+        fields.insert("public".to_owned(), false.into());
+    }
+    let recipe = serde_json::from_value::<Recipe>(value)?;
+    validate_recipe_within(&recipe, max_bytes)?;
+    Ok(recipe)
+}
+
+//repairs what needs no guessing, whitespace around text and empty tags, dietary labels or image urls, returns whether anything changed
+pub fn tidy(recipe: &mut Recipe) -> bool {
+    let before = serde_json::to_string(recipe).ok();
+    for value in [&mut recipe.name, &mut recipe.ingredients, &mut recipe.instructions] {
+        *value = value.trim().to_owned();
+    }
+    for values in [&mut recipe.tags, &mut recipe.dietary] {
+        *values = values.iter().map(|v| v.trim().to_owned()).filter(|v| !v.is_empty()).collect();
+    }
+    recipe.category = recipe.category.as_deref().map(str::trim).filter(|c| !c.is_empty()).map(str::to_owned);
+    recipe.image_url = recipe.image_url.as_deref().map(str::trim).filter(|u| !u.is_empty()).map(str::to_owned);
+    serde_json::to_string(recipe).ok() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a recipe whose serialized form is exactly size bytes, padded out in its instructions
+    fn recipe_of_size(size: usize) -> Recipe {
+        let mut recipe = crate::recipe::parse_recipe_args("Bread|flour|").unwrap();
+        let base = serde_json::to_vec(&recipe).unwrap().len();
+        recipe.instructions = "a".repeat(size - base);
+        assert_eq!(serde_json::to_vec(&recipe).unwrap().len(), size);
+        recipe
+    }
+
+    #[test]
+    fn recipes_up_to_the_limit_are_accepted() {
+        let max = config::Config::default().max_recipe_bytes;
+        assert!(validate_recipe_within(&recipe_of_size(max), max).is_ok());
+        assert!(validate_recipe_within(&recipe_of_size(max - 1), max).is_ok());
+    }
+
+    #[test]
+    fn recipes_over_the_limit_are_rejected() {
+        let max = config::Config::default().max_recipe_bytes;
+        match validate_recipe_within(&recipe_of_size(max + 1), max) {
+            Err(RecipeError::Validation(e)) => assert!(e.contains(&format!("{} bytes", max + 1)), "{}", e),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+}
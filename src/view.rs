@@ -0,0 +1,101 @@
+use libp2p::PeerId;
+use std::collections::HashSet;
+
+//what happened to a peer asking to join the partial view
+pub enum Admission {
+    //it is in the view now, along with the peers evicted to make room for it
+    Added { evicted: Vec<PeerId> },
+    //the view is full and the peer isnt allowlisted, it stays connected but messages arent propagated to it
+    LeftOut,
+    //the peer was already in the view or already left out
+    Unchanged,
+}
+
+//the peers floodsub propagates messages to, capped so fan-out stays bounded on a busy network
+//allowlisted peers are always let in and never evicted, even if that takes the view over the cap
+pub struct PeerView {
+    max: usize,
+    allowlist: HashSet<PeerId>,
+    members: HashSet<PeerId>,
+    //connected peers turned away because the view was full, the most recently active one fills the next free spot
+    left_out: HashSet<PeerId>,
+}
+
+impl PeerView {
+    //a max of 0 leaves the view unbounded
+    pub fn new(max: usize, allowlist: HashSet<PeerId>) -> PeerView {
+        PeerView {
+            max,
+            allowlist,
+            members: HashSet::new(),
+            left_out: HashSet::new(),
+        }
+    }
+
+    //lets a peer in if there is room or it is allowlisted, last_active tells how recently each peer was heard from
+    pub fn admit(&mut self, peer: PeerId, last_active: impl Fn(&PeerId) -> u64) -> Admission {
+        if self.members.contains(&peer) {
+            return Admission::Unchanged;
+        }
+        if self.is_full() && !self.allowlist.contains(&peer) {
+            return if self.left_out.insert(peer) { Admission::LeftOut } else { Admission::Unchanged };
+        }
+        self.left_out.remove(&peer);
+        self.members.insert(peer);
+        let mut evicted = Vec::new();
+        while self.max > 0 && self.members.len() > self.max {
+            let victim = self
+                .members
+                .iter()
+                .filter(|p| !self.allowlist.contains(p))
+                .min_by_key(|p| last_active(p))
+                .copied();
+            match victim {
+                Some(victim) => {
+                    self.members.remove(&victim);
+                    self.left_out.insert(victim);
+                    evicted.push(victim);
+                }
+                //only allowlisted peers are left
+                None => break,
+            }
+        }
+        Admission::Added { evicted }
+    }
+
+    //forgets a peer that is gone, returns the left out peer that takes its spot if it was in the view
+    pub fn remove(&mut self, peer: &PeerId, last_active: impl Fn(&PeerId) -> u64) -> Option<PeerId> {
+        self.left_out.remove(peer);
+        if !self.members.remove(peer) || self.is_full() {
+            return None;
+        }
+        let promoted = self.left_out.iter().max_by_key(|p| last_active(p)).copied()?;
+        self.left_out.remove(&promoted);
+        self.members.insert(promoted);
+        Some(promoted)
+    }
+
+    fn is_full(&self) -> bool {
+        self.max > 0 && self.members.len() >= self.max
+    }
+
+    pub fn is_allowlisted(&self, peer: &PeerId) -> bool {
+        self.allowlist.contains(peer)
+    }
+
+    pub fn contains(&self, peer: &PeerId) -> bool {
+        self.members.contains(peer)
+    }
+
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    pub fn left_out(&self) -> usize {
+        self.left_out.len()
+    }
+}
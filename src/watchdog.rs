@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+//what to do after a transport failure
+#[derive(Debug, PartialEq)]
+pub enum WatchdogAction {
+    //below the threshold, keep going
+    Continue,
+    //too many failures, listeners should be set up again
+    Relisten,
+    //listeners were already set up again too often, exit so a supervisor can restart the node
+    Exit,
+}
+
+//counts listener errors and closures within a sliding window and escalates once they pile up
+pub struct Watchdog {
+    threshold: usize,
+    window: Duration,
+    max_restarts: u32,
+    failures: VecDeque<Instant>,
+    restarts: u32,
+}
+
+impl Watchdog {
+    pub fn new(threshold: usize, window: Duration, max_restarts: u32) -> Watchdog {
+        Watchdog {
+            threshold: threshold.max(1),
+            window,
+            max_restarts,
+            failures: VecDeque::new(),
+            restarts: 0,
+        }
+    }
+
+    //records a failure, deaf means no listener is left so waiting for more failures would wait forever
+    pub fn record_failure(&mut self, deaf: bool) -> WatchdogAction {
+        let now = Instant::now();
+        self.failures.push_back(now);
+        while self.failures.front().is_some_and(|f| now.duration_since(*f) > self.window) {
+            self.failures.pop_front();
+        }
+        if self.failures.len() < self.threshold && !deaf {
+            return WatchdogAction::Continue;
+        }
+        self.failures.clear();
+        if self.restarts < self.max_restarts {
+            self.restarts += 1;
+            WatchdogAction::Relisten
+        } else {
+            WatchdogAction::Exit
+        }
+    }
+
+    //how many times listeners have been set up again
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+}
@@ -0,0 +1,187 @@
+use crate::config;
+use crate::error::{RecipeError, RecipeResult};
+use crate::images;
+use crate::import::{add_recipes, ImportReport};
+use crate::ingredients;
+use crate::validation::validate_recipe;
+use crate::Recipe;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+//downloads the body at a url and its content type, giving up once it takes longer than the timeout or grows past max_bytes
+async fn fetch_bytes(url: &str, timeout: Duration, max_bytes: usize) -> RecipeResult<(Vec<u8>, String)> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("P2PRecipe/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| RecipeError::Fetch(e.to_string()))?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| RecipeError::Fetch(e.to_string()))?;
+    let too_large = || RecipeError::Fetch(format!("{} is larger than the {} byte limit", url, max_bytes));
+    //the length header is only a hint, the body is still counted as it arrives
+    if response.content_length().is_some_and(|len| len > max_bytes as u64) {
+        return Err(too_large());
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| RecipeError::Fetch(e.to_string()))? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, content_type))
+}
+
+//downloads a page as text
+async fn fetch_page(url: &str, timeout: Duration, max_bytes: usize) -> RecipeResult<String> {
+    let (body, _) = fetch_bytes(url, timeout, max_bytes).await?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+//downloads a recipe's image into image_dir, the recipe itself isnt touched whether or not this works
+pub async fn fetch_image(url: &str) -> RecipeResult<PathBuf> {
+    let config = config::get()?;
+    let (body, content_type) = fetch_bytes(url, Duration::from_secs(config.url_import_timeout_secs), config.image_max_bytes).await?;
+    images::store(url, &content_type, &body)
+}
+
+//the contents of every <script type="application/ld+json"> block on the page
+fn json_ld_blocks(html: &str) -> Vec<&str> {
+    //tags are matched without caring about case, lowercasing ascii keeps byte offsets the same
+    let lower = html.to_ascii_lowercase();
+    let mut blocks = Vec::new();
+    let mut rest = 0;
+    while let Some(start) = lower[rest..].find("<script").map(|i| rest + i) {
+        let tag_end = match lower[start..].find('>') {
+            Some(i) => start + i + 1,
+            None => break,
+        };
+        let close = match lower[tag_end..].find("</script") {
+            Some(i) => tag_end + i,
+            None => break,
+        };
+        if lower[start..tag_end].contains("application/ld+json") {
+            blocks.push(&html[tag_end..close]);
+        }
+        rest = close;
+    }
+    blocks
+}
+
+//whether a json-ld node has Recipe among its types
+fn is_recipe(node: &Value) -> bool {
+    match node.get("@type") {
+        Some(Value::String(t)) => t == "Recipe",
+        Some(Value::Array(types)) => types.iter().any(|t| t == "Recipe"),
+        _ => false,
+    }
+}
+
+//the first Recipe node, looking through arrays and @graph lists as well as the top level
+fn find_recipe(node: &Value) -> Option<&Value> {
+    match node {
+        Value::Array(nodes) => nodes.iter().find_map(find_recipe),
+        Value::Object(_) if is_recipe(node) => Some(node),
+        Value::Object(fields) => fields.get("@graph").and_then(find_recipe),
+        _ => None,
+    }
+}
+
+//pages often escape text a second time inside json-ld, only the common entities are undone
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_owned()
+}
+
+//recipeInstructions is a string, a list of strings, a list of HowToStep or HowToSection items holding more steps
+fn instruction_steps(node: &Value, steps: &mut Vec<String>) {
+    match node {
+        Value::String(text) => steps.extend(text.lines().map(decode_entities).filter(|s| !s.is_empty())),
+        Value::Array(items) => items.iter().for_each(|item| instruction_steps(item, steps)),
+        Value::Object(fields) => {
+            if let Some(items) = fields.get("itemListElement") {
+                instruction_steps(items, steps);
+            } else if let Some(text) = fields.get("text").or_else(|| fields.get("name")) {
+                instruction_steps(text, steps);
+            }
+        }
+        _ => {}
+    }
+}
+
+//maps the name, ingredients and instructions of a schema.org Recipe onto an unsaved recipe
+fn parse_recipe(node: &Value) -> RecipeResult<Recipe> {
+    let name = node.get("name").and_then(Value::as_str).map(decode_entities).unwrap_or_default();
+    //older pages use ingredients instead of recipeIngredient
+    let ingredient_items: Vec<String> = match node.get("recipeIngredient").or_else(|| node.get("ingredients")) {
+        Some(Value::Array(items)) => items.iter().filter_map(Value::as_str).map(decode_entities).filter(|i| !i.is_empty()).collect(),
+        Some(Value::String(item)) => vec![decode_entities(item)],
+        _ => Vec::new(),
+    };
+    let mut steps = Vec::new();
+    if let Some(instructions) = node.get("recipeInstructions") {
+        instruction_steps(instructions, &mut steps);
+    }
+    if ingredient_items.is_empty() || steps.is_empty() {
+        return Err(RecipeError::Validation("the recipe on the page has no ingredients or no instructions".to_owned()));
+    }
+    Ok(Recipe {
+        id: 0,
+        name,
+        ingredients: ingredient_items.join(", "),
+        instructions: steps.join("\n"),
+        public: false,
+        tags: Vec::new(),
+        category: None,
+        dietary: Vec::new(),
+        structured_ingredients: ingredient_items.iter().filter_map(|i| ingredients::parse_ingredient(i)).collect(),
+        servings: None,
+        recipe_yield: None,
+        substitutions: Vec::new(),
+        archived: false,
+        prep_minutes: None,
+        cook_minutes: None,
+        time_minutes: None,
+        topics: Vec::new(),
+        denied_peers: Vec::new(),
+        image_url: None,
+        updated_at: None,
+    })
+}
+
+//imports the schema.org Recipe embedded as json-ld in a web page, it starts out private like any other import
+pub async fn import_url(url: &str) -> RecipeResult<ImportReport> {
+    let config = config::get()?;
+    let html = fetch_page(url, Duration::from_secs(config.url_import_timeout_secs), config.url_import_max_bytes).await?;
+    //blocks that arent valid json are skipped, pages often carry several unrelated ones
+    let node = json_ld_blocks(&html)
+        .into_iter()
+        .filter_map(|block| serde_json::from_str::<Value>(block.trim()).ok())
+        .find_map(|block| find_recipe(&block).cloned())
+        .ok_or_else(|| RecipeError::NoRecipeFound { url: url.to_owned() })?;
+    let recipe = parse_recipe(&node)?;
+    validate_recipe(&recipe)?;
+    Ok(ImportReport {
+        imported: add_recipes(vec![recipe]).await?,
+        failures: Vec::new(),
+        schema_errors: Vec::new(),
+    })
+}
@@ -0,0 +1,161 @@
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+//the messages sent on a topic, older peers dont tag them so they are told apart by the fields they carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    ListResponse,
+    NameMatches,
+    RecipeAnnouncement,
+    RecipePatch,
+    ManifestAnnouncement,
+    LocateQuery,
+    NameQuery,
+    AvailabilityDigest,
+    ListRequest,
+}
+
+//top level fields that tell messages apart, one bit each
+const MODE: u16 = 1;
+const DATA: u16 = 1 << 1;
+const RECEIVER: u16 = 1 << 2;
+const REQUEST_ID: u16 = 1 << 3;
+const MATCHES: u16 = 1 << 4;
+const PUBLISHED: u16 = 1 << 5;
+const AUTHOR: u16 = 1 << 6;
+const ID: u16 = 1 << 7;
+const BASE: u16 = 1 << 8;
+const CHANGED_FIELDS: u16 = 1 << 9;
+const MANIFEST: u16 = 1 << 10;
+const LOCATE: u16 = 1 << 11;
+const NAME: u16 = 1 << 12;
+const DIGEST: u16 = 1 << 13;
+
+//the fields each message needs, checked in this order so a message is read as the same type it always was
+const KINDS: &[(u16, MessageKind)] = &[
+    (MODE | DATA | RECEIVER, MessageKind::ListResponse),
+    (MATCHES | RECEIVER | REQUEST_ID, MessageKind::NameMatches),
+    (PUBLISHED, MessageKind::RecipeAnnouncement),
+    (AUTHOR | ID | BASE | CHANGED_FIELDS, MessageKind::RecipePatch),
+    (MANIFEST, MessageKind::ManifestAnnouncement),
+    (LOCATE | REQUEST_ID, MessageKind::LocateQuery),
+    (NAME | REQUEST_ID, MessageKind::NameQuery),
+    (DIGEST, MessageKind::AvailabilityDigest),
+    (MODE, MessageKind::ListRequest),
+];
+
+//works out what a payload is from its top level keys so only one deserializer has to run on it
+//answers addressed to another peer, payloads that arent json objects and unknown messages come back as None
+//nothing is allocated along the way, values are skipped over and the receiver is compared in place
+pub fn classify(data: &[u8], local_peer: &str) -> Option<MessageKind> {
+    let mut deserializer = serde_json::Deserializer::from_slice(data);
+    let peek = Peek { local_peer }.deserialize(&mut deserializer).ok()?;
+    let kind = KINDS.iter().find(|(needed, _)| peek.fields & needed == *needed).map(|(_, kind)| *kind)?;
+    match kind {
+        MessageKind::ListResponse | MessageKind::NameMatches if !peek.for_us => None,
+        kind => Some(kind),
+    }
+}
+
+//a top level key as its bit, keys no message needs are 0
+struct Field(u16);
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Field, D::Error> {
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
+struct FieldVisitor;
+
+impl Visitor<'_> for FieldVisitor {
+    type Value = Field;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a field name")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Field, E> {
+        Ok(Field(match name {
+            "mode" => MODE,
+            "data" => DATA,
+            "receiver" => RECEIVER,
+            "request_id" => REQUEST_ID,
+            "matches" => MATCHES,
+            "published" => PUBLISHED,
+            "author" => AUTHOR,
+            "id" => ID,
+            "base" => BASE,
+            "changed_fields" => CHANGED_FIELDS,
+            "manifest" => MANIFEST,
+            "locate" => LOCATE,
+            "name" => NAME,
+            "digest" => DIGEST,
+            _ => 0,
+        }))
+    }
+}
+
+//what the top level of a payload held
+struct Peeked {
+    fields: u16,
+    //whether the receiver field, if there is one, names the local peer
+    for_us: bool,
+}
+
+struct Peek<'a> {
+    local_peer: &'a str,
+}
+
+impl<'de> DeserializeSeed<'de> for Peek<'_> {
+    type Value = Peeked;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Peeked, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Peek<'_> {
+    type Value = Peeked;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a message object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Peeked, A::Error> {
+        let mut peeked = Peeked { fields: 0, for_us: false };
+        while let Some(Field(field)) = map.next_key()? {
+            peeked.fields |= field;
+            if field == RECEIVER {
+                peeked.for_us = map.next_value_seed(IsPeer(self.local_peer))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(peeked)
+    }
+}
+
+//compares a string value against a peer id without copying it out
+struct IsPeer<'a>(&'a str);
+
+impl<'de> DeserializeSeed<'de> for IsPeer<'_> {
+    type Value = bool;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for IsPeer<'_> {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a peer id")
+    }
+
+    fn visit_str<E: de::Error>(self, receiver: &str) -> Result<bool, E> {
+        Ok(receiver == self.0)
+    }
+}
@@ -0,0 +1,32 @@
+use std::num::NonZeroUsize;
+
+//worker threads used when none are configured, one per cpu the process may run on
+pub fn available() -> usize {
+    std::thread::available_parallelism().map(NonZeroUsize::get).unwrap_or(1)
+}
+
+//applies f to every item on up to workers threads, results come back in the order of the items
+//items are split into one contiguous run per thread, so which thread got which item never shows in the results
+pub fn map_ordered<T, R, F>(items: Vec<T>, workers: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = workers.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items.into_iter().map(f).collect();
+    }
+    let run = items.len().div_ceil(workers);
+    let mut runs: Vec<Vec<T>> = Vec::with_capacity(workers);
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        runs.push(items.by_ref().take(run).collect());
+    }
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = runs.into_iter().map(|run| scope.spawn(move || run.into_iter().map(f).collect::<Vec<R>>())).collect();
+        //a worker that panicked takes the import down with it, same as it would have without workers
+        handles.into_iter().flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+    })
+}